@@ -1,14 +1,21 @@
-use std::{collections::HashMap, fs, sync::mpsc};
+use std::{collections::HashMap, f32::consts::PI, fs, sync::mpsc, time::Instant};
 
 use crate::{
-    connect_to_server, display_error, read_file_into_memory, Application, ApplicationContext,
-    ConnectionSession, FileSession, TabType, DRAWING_BOARD_IMAGE_EXT, DRAWING_BOARD_WORKSPACE_EXT,
+    brush_textures::stamp_line_shape,
+    camera::canvas_zoom,
+    connect_to_server, display_error, import_svg,
+    pressure::{pointer_pressure, variable_width_stroke},
+    smoothing::simplify_line,
+    Application, ApplicationContext, ConnectionSession, TabType,
+};
+use common_definitions::{
+    graffiti_particles, Brush, BrushTexture, BrushType, ExportFormat, ExportRequest, FileKind,
+    LinePos, MessageType, PointerProperties, BACKGROUND_IMAGE_ID, GRAFFITI_DENSITY_RANGE,
 };
-use common_definitions::{Brush, BrushType, LinePos, PointerProperties};
 use egui::{
     emath::{self},
-    vec2, Align2, CentralPanel, Color32, Context, FontId, Frame, Key, Modifiers, Pos2, Rect,
-    RichText, Sense, Stroke, TopBottomPanel, Ui, Vec2,
+    vec2, CentralPanel, Color32, Frame, Key, Modifiers, Pos2, RichText, Sense, Stroke,
+    TextureHandle, TopBottomPanel, Ui,
 };
 use egui_dock::{DockArea, TabViewer};
 
@@ -17,141 +24,256 @@ impl ApplicationContext {
         let (mut response, painter) =
             ui.allocate_painter(ui.available_size_before_wrap(), Sense::drag());
 
-        let paint_area_square_proportions = response.rect.square_proportions();
-
-        let to_screen = emath::RectTransform::from_to(
-            Rect::from_min_size(Pos2::ZERO, paint_area_square_proportions),
-            response.rect,
-        );
+        let is_panning = self.camera_interaction(ui, &response);
 
+        let to_screen = self.camera.to_screen(response.rect);
         let from_screen = to_screen.inverse();
 
+        self.update_pointer_canvas_pos(ui, response.rect, to_screen);
+
+        self.visible_canvas_area = Some(from_screen.transform_rect(response.rect));
+
+        self.load_brush_textures(ui.ctx());
+        self.ensure_active_layer();
+        self.update_pen_force(ui.ctx());
+
+        self.draw_background(ui, &painter, to_screen);
+        self.draw_grid(ui, &painter, to_screen);
+
+        // Images are drawn first, below the lines and the selection outlines, so that they can be annotated
+        self.draw_images(ui, &painter, to_screen);
+        self.draw_shapes(&painter, to_screen);
+
+        if self.paintbrush.brush_type != BrushType::Shape {
+            self.shape_draft = None;
+        }
+
+        if self.paintbrush.brush_type != BrushType::None {
+            self.selected_lines.clear();
+            self.selected_shape = None;
+            self.selection_drag = None;
+        }
+
+        self.clipboard_interaction(ui, to_screen);
+
+        // A click finishing the text being edited shouldn't also start editing another one
+        let was_editing_text = self.text_draft.is_some();
+
+        if self.edit_text_draft(ui, to_screen) {
+            response.mark_changed();
+        }
+
         match self.paintbrush.brush_type {
-            BrushType::Graffiti | BrushType::Pencil | BrushType::Marker => {
-                if self.paintbrush.get_current_brush().1.a() != 0 {
+            // The pointer moves the camera while panning
+            _ if is_panning => (),
+            BrushType::Graffiti | BrushType::Pencil | BrushType::Marker | BrushType::Stamp => {
+                // Hidden and locked layers can't be drawn on
+                if self.paintbrush.get_current_brush().color.a() != 0
+                    && self.layers.is_editable(self.paintbrush.layer)
+                {
                     if self.lines.is_empty() {
-                        self.lines.push((
-                            vec![],
-                            self.paintbrush
-                                .get_nth_brush(self.paintbrush.brush_type as usize),
-                        ));
+                        self.lines
+                            .push((vec![], self.paintbrush.get_current_brush()));
                     }
 
+                    // The ends of the line are snapped, the points in between follow the pointer
+                    let snapped_start = response
+                        .interact_pointer_pos()
+                        .map(|pos| self.snap_point(from_screen * pos, to_screen));
+                    let snapped_end =
+                        self.lines
+                            .last()
+                            .and_then(|(points, _)| points.last())
+                            .map(|pos| {
+                                LinePos::with_pressure(
+                                    self.snap_point(Pos2::from(*pos), to_screen),
+                                    pos.pressure(),
+                                )
+                            });
+
                     let last_line_entry = self.lines.last_mut().unwrap();
                     if let Some(pointer_pos) = response.interact_pointer_pos() {
-                        let on_canvas_pointer_pos = from_screen * pointer_pos;
-                        if last_line_entry.0.last() != Some(&on_canvas_pointer_pos.into()) {
-                            last_line_entry.0.push(on_canvas_pointer_pos.into());
-                            last_line_entry.1 = self
-                                .paintbrush
-                                .get_nth_brush(self.paintbrush.brush_type as usize);
+                        // The smoothing is done in points, so that it feels the same at every window size
+                        let last_point = last_line_entry
+                            .0
+                            .last()
+                            .map(|pos| to_screen * Pos2::from(*pos));
+
+                        if let Some(smoothed_pos) = self
+                            .paintbrush
+                            .smoothing
+                            .smooth(last_point, pointer_pos)
+                            .filter(|smoothed_pos| Some(*smoothed_pos) != last_point)
+                        {
+                            let on_canvas_pointer_pos = match snapped_start {
+                                Some(snapped_start) if last_line_entry.0.is_empty() => {
+                                    snapped_start
+                                }
+                                _ => from_screen * smoothed_pos,
+                            };
+
+                            let pressure = if self.paintbrush.dynamic_width {
+                                pointer_pressure(
+                                    ui.ctx(),
+                                    self.pen_force,
+                                    last_line_entry.0.last().map(|pos| pos.pressure()),
+                                )
+                            } else {
+                                1.
+                            };
+
+                            last_line_entry
+                                .0
+                                .push(LinePos::with_pressure(on_canvas_pointer_pos, pressure));
+                            last_line_entry.1 = self.paintbrush.get_current_brush();
 
                             response.mark_changed();
                         }
                     } else if !last_line_entry.0.is_empty() {
-                        if let Some(current_session) = &self.connection.current_session {
-                            let current_line = self.lines.last().unwrap();
-                            if let Err(err) = current_session.sender_to_server.try_send(
-                                common_definitions::MessageType::AddLine((
-                                    current_line.0.to_vec(),
-                                    current_line.1,
-                                )),
-                            ) {
-                                dbg!(err);
+                        // Graffiti lines aren't simplified, as their particles are sprayed along every segment
+                        if last_line_entry.1.brush_type != BrushType::Graffiti {
+                            let tolerance = self.paintbrush.smoothing.simplify_tolerance
+                                / to_screen.scale().min_elem();
 
-                                self.connection.current_session = None;
-                            }
+                            last_line_entry.0 = simplify_line(&last_line_entry.0, tolerance);
                         }
 
-                        self.lines.push((
-                            vec![],
-                            self.paintbrush
-                                .get_nth_brush(self.paintbrush.brush_type as usize),
-                        ));
-
-                        if !self.undoer.is_in_flux() {
-                            self.undoer.add_undo(&self.lines);
+                        if let (Some(snapped_end), Some(end)) =
+                            (snapped_end, last_line_entry.0.last_mut())
+                        {
+                            *end = snapped_end;
                         }
 
+                        let current_line = self.lines.last().unwrap();
+                        self.send_message(common_definitions::MessageType::AddLine((
+                            current_line.0.to_vec(),
+                            current_line.1,
+                        )));
+
+                        self.lines
+                            .push((vec![], self.paintbrush.get_current_brush()));
+
+                        self.add_undo_point();
+
                         response.mark_changed();
                     }
                 }
             }
+            BrushType::Shape => {
+                // Hidden and locked layers can't be drawn on
+                if self.layers.is_editable(self.paintbrush.layer)
+                    && self.shape_interaction(ui, &response, &painter, to_screen)
+                {
+                    response.mark_changed();
+                }
+            }
+            BrushType::Text => {
+                if !was_editing_text {
+                    self.text_interaction(ui, &response, to_screen);
+                }
+            }
             BrushType::Eraser => {
                 if let Some(pointer_pos) = response.interact_pointer_pos() {
-                    let (brush_width, _, _) = self.paintbrush.get_current_brush();
-                    for (line_idx, (lines_pos, (line_width, _, _))) in
-                        self.lines.clone().iter().enumerate()
-                    {
-                        let mut last_rect = Rect::NOTHING;
-
-                        for line_pos in lines_pos {
-                            let current_rect = Rect::from_center_size(
-                                to_screen * (*line_pos).into(),
-                                vec2(*line_width + brush_width, *line_width + brush_width),
-                            );
-                            let rect = last_rect.union(current_rect);
+                    // The eraser is swept from its last position, so that fast strokes don't skip over lines
+                    let eraser_start = self.last_eraser_pos.unwrap_or(pointer_pos);
 
-                            if rect.contains(pointer_pos) {
-                                self.lines.swap_remove(line_idx);
-
-                                self.undoer.add_undo(&self.lines);
-
-                                response.mark_changed();
-                                break;
-                            }
-
-                            last_rect = current_rect;
-                        }
+                    if self.erase(eraser_start, pointer_pos, to_screen) {
+                        response.mark_changed();
                     }
+
+                    self.last_eraser_pos = Some(pointer_pos);
+                } else {
+                    self.last_eraser_pos = None;
                 }
             }
             BrushType::None => {
-                for (line_pos, _) in self.lines.iter() {
-                    let line_rect = Rect::from_points(
-                        &line_pos
-                            .iter()
-                            .map(|pos| to_screen * Into::<Pos2>::into(*pos))
-                            .collect::<Vec<Pos2>>(),
-                    );
-
-                    if let Some(pointer_pos) = ui.ctx().pointer_hover_pos() {
-                        if line_rect.contains(pointer_pos) {
-                            ui.painter().rect(
-                                line_rect,
-                                1.,
-                                Color32::from_rgba_unmultiplied(0, 255, 0, 80),
-                                Stroke::new(2., Color32::GREEN),
-                            );
+                self.image_interaction(ui, to_screen);
 
-                            //If It matched just continue with the for loop so that this line's rect wont get displayed twice
-                            continue;
-                        }
-                    }
+                if !was_editing_text {
+                    self.text_interaction(ui, &response, to_screen);
+                }
 
-                    ui.painter().rect(
-                        line_rect,
-                        1.,
-                        Color32::TRANSPARENT,
-                        Stroke::new(1., Color32::WHITE),
-                    );
+                if self.selection_interaction(ui, &response, to_screen) {
+                    response.mark_changed();
                 }
             }
         }
 
+        // The lines are drawn layer by layer, the hidden layers are skipped
         painter.extend(
-            self.lines
-                .iter()
-                .filter(|line| line.0.len() >= 2)
-                .map(|line| draw_line_to_screen_with_brush(line, to_screen)),
+            self.layers
+                .layered_lines(&self.lines)
+                .filter(|(line_pos, _)| line_pos.len() >= 2)
+                .map(|(line_pos, brush)| {
+                    draw_line_to_screen_with_brush(line_pos, brush, to_screen, &self.brush_textures)
+                }),
         );
 
+        self.draw_texts(&painter, to_screen);
+
+        if !is_panning
+            && matches!(
+                self.paintbrush.brush_type,
+                BrushType::Graffiti
+                    | BrushType::Pencil
+                    | BrushType::Marker
+                    | BrushType::Stamp
+                    | BrushType::Shape
+            )
+        {
+            self.draw_snap_indicator(ui, &painter, to_screen);
+        }
+
+        // The selection is drawn on the top of the lines
+        if self.paintbrush.brush_type == BrushType::None {
+            self.draw_selection(ui, &painter, to_screen);
+        }
+
+        self.draw_remote_cursors(ui, &painter, to_screen);
+        self.draw_rulers(ui, &painter, to_screen);
+        self.draw_follow_indicator(&painter, response.rect);
+
+        if response.changed() {
+            self.mark_unsaved();
+        }
+
         response
     }
 
+    /// Displays the settings of the ```BrushType::Stamp``` brush.
+    fn stamp_settings(&mut self, ui: &mut Ui) {
+        let stamp = &mut self.paintbrush.stamp;
+        let texture_name: &'static str = stamp.texture.into();
+
+        ui.label("Texture");
+        egui::ComboBox::from_id_salt("stamp_texture")
+            .selected_text(texture_name)
+            .show_ui(ui, |ui| {
+                for texture in BrushTexture::ALL {
+                    let texture_name: &'static str = texture.into();
+
+                    ui.selectable_value(&mut stamp.texture, texture, texture_name);
+                }
+            });
+
+        ui.label("Spacing");
+        ui.add(egui::Slider::new(&mut stamp.spacing, 0.05..=2.0))
+            .on_hover_text("The distance between two stamps, relative to the brush's width.");
+
+        ui.label("Rotation jitter");
+        ui.add(egui::Slider::new(&mut stamp.rotation_jitter, 0.0..=PI).suffix(" rad"))
+            .on_hover_text("The largest random rotation of a stamp.");
+
+        ui.label("Scatter");
+        ui.add(egui::Slider::new(&mut stamp.scatter, 0.0..=2.0))
+            .on_hover_text(
+            "The largest random distance of a stamp from the line, relative to the brush's width.",
+        );
+    }
+
     /// This function handles the usage of a colorpicker for multiple paintbrushes.
     fn color_picker(&mut self, ui: &mut Ui) {
-        let mut color: [u8; 4] = self.paintbrush.get_current_brush().1.to_array();
+        let mut color: [u8; 4] = self.paintbrush.get_current_brush().color.to_array();
 
         ui.color_edit_button_srgba_premultiplied(&mut color);
 
@@ -160,23 +282,64 @@ impl ApplicationContext {
     }
 }
 
-/// This function draws a line ((Vec<LinePos>, Brush)) to the screen.
+/// This function draws a line (The points of the line and its ```Brush```) to the screen.
 fn draw_line_to_screen_with_brush(
-    line: &(Vec<LinePos>, Brush),
+    line_pos: &[LinePos],
+    brush: Brush,
     to_screen: emath::RectTransform,
+    brush_textures: &HashMap<BrushTexture, TextureHandle>,
 ) -> egui::Shape {
-    let points: Vec<Pos2> = line.0.iter().map(|p| to_screen * (*p).into()).collect();
-    let (width, color, brush_type) = line.1;
+    let points: Vec<Pos2> = line_pos.iter().map(|p| to_screen * (*p).into()).collect();
+    let zoom = canvas_zoom(to_screen);
+    let Brush {
+        width,
+        color,
+        brush_type,
+        density,
+        stamp,
+        ..
+    } = brush;
+
+    // The width of the brush is in points at the default zoom, the particles and stamps are scaled the same way
+    let screen_width = width * zoom;
 
     match brush_type {
         BrushType::Pencil => egui::Shape::Vec(egui::Shape::dashed_line(
             &points,
-            Stroke::new(width, color),
-            width,
-            width,
+            Stroke::new(screen_width, color),
+            screen_width,
+            screen_width,
         )),
-        BrushType::Marker => egui::Shape::line(points, Stroke::new(width, color)),
-        BrushType::Graffiti => egui::Shape::Noop,
+        BrushType::Marker => {
+            if line_pos.iter().any(|pos| pos.pressure() < 1.) {
+                let widths: Vec<f32> = line_pos
+                    .iter()
+                    .map(|pos| screen_width * pos.pressure())
+                    .collect();
+
+                variable_width_stroke(&points, &widths, color)
+            } else {
+                egui::Shape::line(points, Stroke::new(screen_width, color))
+            }
+        }
+        BrushType::Graffiti => egui::Shape::Vec(
+            graffiti_particles(line_pos, width / 2., density)
+                .into_iter()
+                .map(|particle| {
+                    egui::Shape::circle_filled(
+                        to_screen * particle.anchor + particle.offset * zoom,
+                        particle.radius * zoom,
+                        color,
+                    )
+                })
+                .collect(),
+        ),
+        BrushType::Stamp => match brush_textures.get(&stamp.texture) {
+            Some(texture) => stamp_line_shape(line_pos, width, color, &stamp, texture, to_screen),
+            None => egui::Shape::Noop,
+        },
+        BrushType::Shape => egui::Shape::Noop,
+        BrushType::Text => egui::Shape::Noop,
         BrushType::Eraser => egui::Shape::Noop,
         BrushType::None => egui::Shape::Noop,
     }
@@ -194,40 +357,29 @@ impl TabViewer for ApplicationContext {
         match _tab {
             TabType::Canvas => false,
             TabType::BrushSettings => true,
+            TabType::Layers => true,
         }
     }
 
     fn ui(&mut self, ui: &mut Ui, tab: &mut Self::Tab) {
         match tab {
             TabType::Canvas => {
-                let canvas = egui::Frame::canvas(ui.style()).show(ui, |ui| self.ui_content(ui));
+                egui::Frame::canvas(ui.style()).show(ui, |ui| self.ui_content(ui));
 
                 if let Some(pointer_pos) = ui.ctx().pointer_hover_pos() {
-                    let (size, color, _) = self.paintbrush.get_current_brush();
-                    ui.painter()
-                        .circle_filled(pointer_pos, size / 2., color.gamma_multiply(0.5));
+                    let brush = self.paintbrush.get_current_brush();
+                    // The preview is scaled by the zoom of the camera, just like the lines drawn with the brush
+                    ui.painter().circle_filled(
+                        pointer_pos,
+                        brush.width * self.camera.zoom / 2.,
+                        brush.color.gamma_multiply(0.5),
+                    );
                 }
+            }
+            TabType::Layers => {
+                ui.allocate_space(vec2(ui.available_width(), 10.));
 
-                if let Some(export_save_path) = &self.export_path {
-                    ui.ctx().input(|i| {
-                        for event in &i.raw.events {
-                            if let egui::Event::Screenshot { image, .. } = event {
-                                let pixels_per_point = i.pixels_per_point();
-                                let region = canvas.inner.rect;
-                                let image_region = image.region(&region, Some(pixels_per_point));
-                                if let Err(err) = image::save_buffer(
-                                    export_save_path,
-                                    image_region.as_raw(),
-                                    image_region.width() as u32,
-                                    image_region.height() as u32,
-                                    image::ColorType::Rgba8,
-                                ) {
-                                    display_error(err);
-                                }
-                            }
-                        }
-                    });
-                }
+                self.layers_ui(ui);
             }
             TabType::BrushSettings => {
                 ui.allocate_space(vec2(ui.available_width(), 10.));
@@ -251,6 +403,21 @@ impl TabViewer for ApplicationContext {
                             BrushType::Pencil,
                             "Pencil",
                         );
+                        ui.selectable_value(
+                            &mut self.paintbrush.brush_type,
+                            BrushType::Stamp,
+                            "Stamp",
+                        );
+                        ui.selectable_value(
+                            &mut self.paintbrush.brush_type,
+                            BrushType::Shape,
+                            "Shape",
+                        );
+                        ui.selectable_value(
+                            &mut self.paintbrush.brush_type,
+                            BrushType::Text,
+                            "Text",
+                        );
                         ui.selectable_value(
                             &mut self.paintbrush.brush_type,
                             BrushType::Eraser,
@@ -268,19 +435,20 @@ impl TabViewer for ApplicationContext {
 
                 //The `BrushType`-s which properties cannot be changed
                 match (
-                    matches!(self.paintbrush.get_current_brush().2, BrushType::None),
-                    matches!(self.paintbrush.get_current_brush().2, BrushType::Eraser),
+                    matches!(self.paintbrush.brush_type, BrushType::None),
+                    matches!(self.paintbrush.brush_type, BrushType::Eraser),
                 ) {
                     (false, true) => {
                         ui.label("Width");
                         ui.add(
                             egui::Slider::new(
-                                &mut self.paintbrush.brush_width
-                                    [self.paintbrush.brush_type as usize],
+                                self.paintbrush.get_mut_current_brush().0,
                                 1.0..=100.0,
                             )
                             .step_by(0.2),
                         );
+
+                        self.paintbrush.eraser_mode.settings_ui(ui);
                     }
                     (false, false) => {
                         ui.horizontal(|ui| {
@@ -288,15 +456,44 @@ impl TabViewer for ApplicationContext {
                             self.color_picker(ui);
                         });
 
-                        ui.label("Width");
-                        ui.add(
-                            egui::Slider::new(
-                                &mut self.paintbrush.brush_width
-                                    [self.paintbrush.brush_type as usize],
-                                1.0..=100.0,
-                            )
-                            .step_by(0.2),
-                        );
+                        // Texts are sized by their font size instead of a width
+                        if self.paintbrush.brush_type == BrushType::Text {
+                            self.text_settings(ui);
+                        } else {
+                            ui.label("Width");
+                            ui.add(
+                                egui::Slider::new(
+                                    self.paintbrush.get_mut_current_brush().0,
+                                    1.0..=100.0,
+                                )
+                                .step_by(0.2),
+                            );
+                        }
+
+                        // Shapes and texts aren't drawn freehand, so they aren't affected by the pressure and the smoothing
+                        if self.paintbrush.brush_type == BrushType::Shape {
+                            self.shape_settings(ui);
+                        } else if self.paintbrush.brush_type != BrushType::Text {
+                            ui.checkbox(&mut self.paintbrush.dynamic_width, "Pressure sensitivity")
+                                .on_hover_text("The width of the line follows the pen's pressure, or the pointer's speed if the pen doesn't report pressure.");
+
+                            self.paintbrush.smoothing.settings_ui(ui);
+                        }
+
+                        if self.paintbrush.brush_type == BrushType::Graffiti {
+                            ui.label("Density");
+                            ui.add(egui::Slider::new(
+                                &mut self.paintbrush.graffiti_density,
+                                GRAFFITI_DENSITY_RANGE,
+                            ))
+                            .on_hover_text(
+                                "The number of particles sprayed along every segment of the line.",
+                            );
+                        }
+
+                        if self.paintbrush.brush_type == BrushType::Stamp {
+                            self.stamp_settings(ui);
+                        }
                     }
 
                     _ => (),
@@ -304,8 +501,9 @@ impl TabViewer for ApplicationContext {
 
                 ui.separator();
 
-                let can_undo = self.undoer.has_undo(&self.lines);
-                let can_redo = self.undoer.has_redo(&self.lines);
+                let undo_state = self.undo_state();
+                let can_undo = self.undoer.has_undo(&undo_state);
+                let can_redo = self.undoer.has_redo(&undo_state);
 
                 ui.horizontal(|ui| {
                     if ui
@@ -313,8 +511,8 @@ impl TabViewer for ApplicationContext {
                         .clicked()
                         || ui.input_mut(|input| input.consume_key(Modifiers::CTRL, Key::Z))
                     {
-                        if let Some(state) = self.undoer.undo(&self.lines) {
-                            self.lines = state.clone();
+                        if let Some(state) = self.undoer.undo(&undo_state).cloned() {
+                            self.restore_undo_state(state);
                         }
                     }
                     if ui
@@ -322,13 +520,44 @@ impl TabViewer for ApplicationContext {
                         .clicked()
                         || ui.input_mut(|input| input.consume_key(Modifiers::CTRL, Key::Y))
                     {
-                        if let Some(state) = self.undoer.redo(&self.lines) {
-                            self.lines = state.clone();
+                        if let Some(state) = self.undoer.redo(&undo_state).cloned() {
+                            self.restore_undo_state(state);
                         }
                     }
 
                     if ui.button("Erase board").clicked() {
+                        self.send_messages(
+                            self.lines
+                                .iter()
+                                .filter(|(points, _)| !points.is_empty())
+                                .map(|(points, _)| MessageType::ModifyLine((points.clone(), None)))
+                                .chain(
+                                    self.shapes
+                                        .keys()
+                                        .map(|id| MessageType::ModifyShape((*id, None))),
+                                )
+                                .chain(
+                                    self.texts
+                                        .keys()
+                                        .map(|id| MessageType::ModifyText((*id, None))),
+                                )
+                                .chain(
+                                    self.images
+                                        .keys()
+                                        .map(|id| MessageType::ModifyImage((*id, None))),
+                                )
+                                .collect(),
+                        );
+
                         self.lines.clear();
+                        self.shapes.clear();
+                        self.texts.clear();
+                        self.images.clear();
+                        self.selected_shape = None;
+                        self.selected_image = None;
+                        self.text_draft = None;
+                        self.add_undo_point();
+                        self.mark_unsaved();
                     }
                 });
 
@@ -343,100 +572,122 @@ impl eframe::App for Application {
             ui.horizontal(|ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("New File").clicked() {
-                        self.reset();
+                        self.new_document();
                     }
 
                     if ui.button("Open Image").clicked() {
-                        if let Err(err) =
-                            read_file_into_memory(&mut self.context.lines, DRAWING_BOARD_IMAGE_EXT)
-                        {
+                        if let Err(err) = self.open_document(FileKind::Image) {
                             display_error(err);
-                        };
+                        }
                     }
 
                     if ui.button("Open Workspace").clicked() {
-                        if let Err(err) =
-                            read_file_into_memory(&mut self.context, DRAWING_BOARD_WORKSPACE_EXT)
-                        {
+                        if let Err(err) = self.open_document(FileKind::Workspace) {
                             display_error(err);
-                        };
+                        }
                     }
 
-                    ui.separator();
+                    self.recent_files_menu(ui);
 
-                    if ui.button("Save Workspace As").clicked() {
-                        if let Some(saved_file_path) = rfd::FileDialog::new()
-                            .add_filter("Project File", &[DRAWING_BOARD_WORKSPACE_EXT])
-                            .save_file()
+                    if ui.button("Import Svg").clicked() {
+                        if let Some(svg_path) = rfd::FileDialog::new()
+                            .add_filter("Vector image", &["svg"])
+                            .pick_file()
                         {
-                            if let Err(err) = fs::write(
-                                saved_file_path,
-                                miniz_oxide::deflate::compress_to_vec(
-                                    &rmp_serde::to_vec(&self.context).unwrap(),
-                                    10,
-                                ),
-                            ) {
-                                display_error(err);
+                            match fs::read(svg_path)
+                                .map_err(anyhow::Error::from)
+                                .and_then(|svg_data| import_svg(&svg_data))
+                            {
+                                Ok(imported) => self.context.insert_imported_svg(imported),
+                                Err(err) => display_error(err),
                             }
-                        };
+                        }
                     }
 
-                    if ui.button("Save Image").clicked() {
-                        let compressed_save_file = miniz_oxide::deflate::compress_to_vec(
-                            &rmp_serde::to_vec(&self.context).unwrap(),
-                            10,
-                        );
-
-                        if let Some(session) = &self.context.file_session {
-                            if let Err(err) =
-                                fs::write(session.file_path.clone(), compressed_save_file)
-                            {
-                                display_error(err);
-                            }
-                        } else if let Some(saved_file_path) = rfd::FileDialog::new()
-                            .add_filter("Project File", &[DRAWING_BOARD_WORKSPACE_EXT])
-                            .save_file()
+                    if ui.button("Insert Image").clicked() {
+                        if let Some(image_path) = rfd::FileDialog::new()
+                            .add_filter("Image", &["png", "jpg", "jpeg"])
+                            .pick_file()
                         {
-                            if let Err(err) = fs::write(saved_file_path, compressed_save_file) {
-                                display_error(err);
+                            match fs::read(image_path)
+                                .map_err(anyhow::Error::from)
+                                .and_then(|image_data| self.context.insert_image(image_data))
+                            {
+                                Ok(()) => {
+                                    // Images can only be moved when no brush is selected
+                                    self.context.paintbrush.brush_type = BrushType::None;
+                                }
+                                Err(err) => display_error(err),
                             }
                         }
                     }
 
-                    if ui.button("Save Image As").clicked() {
-                        if let Some(saved_file_path) = rfd::FileDialog::new()
-                            .add_filter("Project File", &[DRAWING_BOARD_IMAGE_EXT])
-                            .save_file()
-                        {
-                            if let Err(err) = fs::write(
-                                &saved_file_path,
-                                miniz_oxide::deflate::compress_to_vec(
-                                    &rmp_serde::to_vec(&self.context.lines).unwrap(),
-                                    10,
-                                ),
-                            ) {
+                    ui.separator();
+
+                    for (label, kind, save_as) in [
+                        ("Save Image", FileKind::Image, false),
+                        ("Save Image As", FileKind::Image, true),
+                        ("Save Workspace", FileKind::Workspace, false),
+                        ("Save Workspace As", FileKind::Workspace, true),
+                    ] {
+                        if ui.button(label).clicked() {
+                            if let Err(err) = self.save_document(kind, save_as) {
                                 display_error(err);
                             }
+                        }
+                    }
 
-                            self.create_session(saved_file_path, ctx);
-                        };
+                    if ui.button("Project Properties").clicked() {
+                        self.context.properties_dialog_open = true;
+                        ui.close_menu();
                     }
 
+                    if ui.button("Canvas Background").clicked() {
+                        self.context.background_dialog_open = true;
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
                     if ui.button("Export As Png").clicked() {
-                        if let Some(save_path) = rfd::FileDialog::new()
-                            .add_filter("Image", &["png"])
-                            .save_file()
-                        {
-                            self.context.export_path = Some(save_path);
-                            ui.close_menu();
+                        self.context.export_settings.format = ExportFormat::Png;
+                        self.context.export_dialog_open = true;
+                        ui.close_menu();
+                    }
 
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot);
-                        }
+                    if ui.button("Export As Svg").clicked() {
+                        self.context.export_settings.format = ExportFormat::Svg;
+                        self.context.export_dialog_open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Export As Pdf").clicked() {
+                        self.context.export_settings.format = ExportFormat::Pdf;
+                        self.context.export_dialog_open = true;
+                        ui.close_menu();
                     }
 
                     ui.separator();
 
-                    ui.checkbox(&mut true, "Auto Save");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.autosave.enabled, "Auto Save");
+                        ui.add_enabled(
+                            self.autosave.enabled,
+                            egui::DragValue::new(&mut self.autosave.interval_secs)
+                                .range(10..=3600)
+                                .prefix("every ")
+                                .suffix("s"),
+                        );
+                    });
+
+                    ui.add_enabled(
+                        self.autosave.enabled,
+                        egui::Checkbox::new(
+                            &mut self.autosave.save_to_file,
+                            "Auto Save Into The Open File",
+                        ),
+                    )
+                    .on_hover_text("Otherwise the changes are only saved into a recovery file, which is offered after a crash.");
 
                     ui.separator();
 
@@ -445,19 +696,93 @@ impl eframe::App for Application {
                     }
                 });
 
+                ui.menu_button("Edit", |ui| {
+                    let has_selection = !self.context.selected_lines.is_empty();
+
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Cut").shortcut_text("Ctrl+X"))
+                        .clicked()
+                    {
+                        if let Err(err) = self.context.cut_selection() {
+                            display_error(err);
+                        }
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Copy").shortcut_text("Ctrl+C"))
+                        .clicked()
+                    {
+                        if let Err(err) = self.context.copy_selection() {
+                            display_error(err);
+                        }
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Copy As Image"))
+                        .on_hover_text("Copies the selected lines as an image, which can be pasted into image editors.")
+                        .clicked()
+                    {
+                        if let Err(err) = self.context.copy_selection_as_image() {
+                            display_error(err);
+                        }
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add(egui::Button::new("Paste").shortcut_text("Ctrl+V"))
+                        .on_hover_text("Pastes the copied lines, or an image from the clipboard.")
+                        .clicked()
+                    {
+                        if let Err(err) = self.context.paste(None) {
+                            display_error(err);
+                        }
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button("View", |ui| {
+                    self.context.grid.settings_ui(ui);
+
+                    ui.separator();
+
+                    ui.label(format!("Zoom: {:.0}%", self.context.camera.zoom * 100.));
+
+                    if ui
+                        .button("Fit To Content")
+                        .on_hover_text("Moves and zooms the canvas so that everything drawn on it is visible.")
+                        .clicked()
+                    {
+                        self.context.fit_camera_to_content();
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Reset View").clicked() {
+                        self.context.reset_camera();
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    ui.label("Pan by dragging with the middle mouse button or while holding Space, zoom with the mouse wheel or by pinching.");
+                });
+
                 ui.menu_button("Workspace", |ui| {
                     ui.menu_button("Tooling", |ui| {
-                        let tree_node = self.tree.find_tab(&TabType::BrushSettings);
-                        if ui
-                            .checkbox(&mut tree_node.is_some(), "Brush settings")
-                            .clicked()
-                        {
-                            if let Some(node) = tree_node {
-                                self.tree.remove_tab(node);
-                            } else {
-                                self.tree.push_to_focused_leaf(TabType::BrushSettings);
-                            }
-                        };
+                        for (tab, label) in [
+                            (TabType::BrushSettings, "Brush settings"),
+                            (TabType::Layers, "Layers"),
+                        ] {
+                            let tree_node = self.tree.find_tab(&tab);
+                            if ui.checkbox(&mut tree_node.is_some(), label).clicked() {
+                                if let Some(node) = tree_node {
+                                    self.tree.remove_tab(node);
+                                } else {
+                                    self.tree.push_to_focused_leaf(tab);
+                                }
+                            };
+                        }
                     });
                 });
 
@@ -487,11 +812,50 @@ impl eframe::App for Application {
                                     .color(Color32::from_rgb(clamped_ping, 255 - clamped_ping, 0)),
                             );
 
+                            if ui.button("Export Server Canvas").clicked() {
+                                if let Some(save_path) = rfd::FileDialog::new()
+                                    .add_filter("Image", &["png", "svg", "pdf"])
+                                    .save_file()
+                                {
+                                    let format =
+                                        match save_path.extension().and_then(|ext| ext.to_str()) {
+                                            Some("svg") => ExportFormat::Svg,
+                                            Some("pdf") => ExportFormat::Pdf,
+                                            _ => ExportFormat::Png,
+                                        };
+
+                                    if let Err(err) = connection_session.sender_to_server.send(
+                                        common_definitions::MessageType::RequestExport(
+                                            ExportRequest {
+                                                format,
+                                                thumbnail_size: None,
+                                            },
+                                        ),
+                                    ) {
+                                        display_error(err);
+                                    } else {
+                                        self.context.connection.server_export_path =
+                                            Some(save_path);
+                                    }
+                                }
+                            }
+
                             if ui.button("Disconnect").clicked() {
                                 //Reset connection state
                                 connection_session.cancel_connection();
                                 self.context.lines.clear();
+                                self.context.images.clear();
+                                self.context.shapes.clear();
+                                self.context.texts.clear();
+                                self.context.text_draft = None;
+                                self.context.layers = Default::default();
+                                self.context.background = Default::default();
+                                self.context.set_background_image(None);
                                 self.context.connection.connected_clients.clear();
+                                self.context.connection.client_viewports.clear();
+                                self.context.connection.cursor_moved_at.clear();
+                                self.context.connection.followed_client = None;
+                                self.context.connection.sent_viewport = None;
                                 self.context.connection.session_reciver = None;
                                 self.context.connection.current_session = None;
                             }
@@ -518,6 +882,12 @@ impl eframe::App for Application {
                             }
                         });
                     }
+
+                    if self.context.connection.current_session.is_some() {
+                        ui.separator();
+
+                        self.context.participants_ui(ui, self.uuid.0);
+                    }
                 });
             });
         });
@@ -528,41 +898,61 @@ impl eframe::App for Application {
                 DockArea::new(&mut self.tree)
                     .show_window_close_buttons(true)
                     .show_inside(ui, &mut self.context);
+            });
 
-                for (_, (username, pointer_properties)) in
-                    self.context.connection.connected_clients.iter()
-                {
-                    let cursor_pos = pointer_properties.pointer_pos;
-
-                    ui.painter().circle(
-                        cursor_pos,
-                        15.,
-                        Color32::WHITE,
-                        Stroke::new(1., Color32::WHITE),
-                    );
-                    ui.painter().text(
-                        cursor_pos + Vec2::new(50., 30.),
-                        Align2::CENTER_CENTER,
-                        username,
-                        FontId::monospace(20.),
-                        Color32::GRAY,
-                    );
+        self.context.export_dialog(ctx);
+        self.context.properties_dialog(ctx);
+        self.context.background_dialog(ctx);
+        self.recent_files_dialog(ctx);
 
-                    ctx.request_repaint();
-                }
-            });
+        self.handle_close_request(ctx);
+        self.update_window_title(ctx);
+        self.autosave(ctx);
 
         if let Some(reciver) = &self.context.connection.session_reciver {
             if let Ok(val) = reciver.try_recv() {
                 self.context.connection.current_session = Some(val);
 
-                //Clear lines on successful connection
+                //Clear lines, images, shapes, texts and layers on successful connection
                 self.context.lines.clear();
+                self.context.images.clear();
+                self.context.shapes.clear();
+                self.context.texts.clear();
+                self.context.text_draft = None;
+                self.context.layers = Default::default();
+                self.context.background = Default::default();
+                self.context.set_background_image(None);
+
+                // The viewport and the pointer are sent to the new session
+                self.context.connection.sent_viewport = None;
+                self.context.connection.sent_pointer = None;
             }
         }
 
         if let Some(session) = self.context.connection.current_session.as_mut() {
+            // The camera is moved once every message has been handled
+            let mut shown_viewport = None;
+
             while let Ok(message) = session.message_reciver_from_server.try_recv() {
+                // The changes made by the other users are unsaved changes too
+                if matches!(
+                    message.msg_type,
+                    common_definitions::MessageType::AddLine(_)
+                        | common_definitions::MessageType::ModifyLine(_)
+                        | common_definitions::MessageType::TransformLine(_)
+                        | common_definitions::MessageType::SyncLine(_)
+                        | common_definitions::MessageType::ImageChunk(_)
+                        | common_definitions::MessageType::ModifyImage(_)
+                        | common_definitions::MessageType::AddShape(_)
+                        | common_definitions::MessageType::ModifyShape(_)
+                        | common_definitions::MessageType::AddText(_)
+                        | common_definitions::MessageType::ModifyText(_)
+                        | common_definitions::MessageType::ModifyLayers(_)
+                        | common_definitions::MessageType::ModifyBackground(_)
+                ) {
+                    self.context.unsaved_changes = true;
+                }
+
                 match message.msg_type {
                     common_definitions::MessageType::ClientList(clients) => {
                         self.context.connection.connected_clients =
@@ -571,13 +961,40 @@ impl eframe::App for Application {
                             }))
                     }
                     common_definitions::MessageType::CursorPosition(client_pos) => {
-                        if let Some((_, pos)) = self
-                            .context
-                            .connection
-                            .connected_clients
-                            .get_mut(&message.uuid)
-                        {
-                            *pos = client_pos;
+                        // The cursor of this client is drawn by the system
+                        if message.uuid != self.uuid.0 {
+                            if let Some((_, pos)) = self
+                                .context
+                                .connection
+                                .connected_clients
+                                .get_mut(&message.uuid)
+                            {
+                                *pos = client_pos;
+
+                                self.context
+                                    .connection
+                                    .cursor_moved_at
+                                    .insert(message.uuid, Instant::now());
+                            }
+                        }
+                    }
+                    common_definitions::MessageType::Viewport(viewport) => {
+                        // The viewport of this client is only sent to the others
+                        if message.uuid != self.uuid.0 {
+                            self.context
+                                .connection
+                                .client_viewports
+                                .insert(message.uuid, viewport);
+
+                            if self.context.connection.followed_client == Some(message.uuid) {
+                                shown_viewport = Some(viewport);
+                            }
+                        }
+                    }
+                    common_definitions::MessageType::SummonToViewport(viewport) => {
+                        if message.uuid != self.uuid.0 {
+                            self.context.connection.followed_client = None;
+                            shown_viewport = Some(viewport);
                         }
                     }
                     common_definitions::MessageType::Connecting(username) => {
@@ -585,26 +1002,40 @@ impl eframe::App for Application {
                             .connection
                             .connected_clients
                             .insert(message.uuid, (username, PointerProperties::default()));
+
+                        // The viewport is sent again, so that the new user can follow this one right away
+                        if message.uuid != self.uuid.0 {
+                            self.context.connection.sent_viewport = None;
+                        }
                     }
                     common_definitions::MessageType::Disconnecting => {
                         self.context
                             .connection
                             .connected_clients
                             .remove(&message.uuid);
+                        self.context
+                            .connection
+                            .client_viewports
+                            .remove(&message.uuid);
+                        self.context
+                            .connection
+                            .cursor_moved_at
+                            .remove(&message.uuid);
+
+                        if self.context.connection.followed_client == Some(message.uuid) {
+                            self.context.connection.followed_client = None;
+                        }
                     }
 
                     //Acknowledge keepalive message
                     common_definitions::MessageType::KeepAlive => (),
                     common_definitions::MessageType::AddLine(line_data) => {
-                        if !self.context.lines.iter().any(|line| *line == line_data) {
+                        if !self.context.lines.contains(&line_data) {
                             self.context.lines.push((line_data.0, line_data.1));
 
-                            self.context.lines.push((
-                                vec![],
-                                self.context
-                                    .paintbrush
-                                    .get_nth_brush(self.context.paintbrush.brush_type as usize),
-                            ));
+                            self.context
+                                .lines
+                                .push((vec![], self.context.paintbrush.get_current_brush()));
                         }
                     }
                     common_definitions::MessageType::ModifyLine((pos, props)) => {
@@ -618,24 +1049,172 @@ impl eframe::App for Application {
                             if let Some(line_modification) = props {
                                 self.context.lines[idx].1 = line_modification;
                             } else {
-                                self.context.lines.swap_remove(idx);
+                                // The order of the lines is kept, as it's the order they are drawn in
+                                self.context.lines.remove(idx);
                             }
-                        } else {
-                            session
+                        } else if props.is_some() {
+                            // Deleting a line which isn't on the canvas (Like the ones erased by this client) needs no syncing
+                            if let Err(err) = session
                                 .sender_to_server
-                                .try_send(common_definitions::MessageType::RequestSyncLine(Some(
-                                    pos,
-                                )))
-                                .unwrap();
+                                .send(common_definitions::MessageType::RequestSyncLine(Some(pos)))
+                            {
+                                dbg!(err);
+                            }
+                        }
+                    }
+                    common_definitions::MessageType::TransformLine((pos, transformed_pos)) => {
+                        if let Some(line) = self.context.lines.iter_mut().find(|line| line.0 == pos)
+                        {
+                            line.0 = transformed_pos.clone();
+
+                            // The line stays selected
+                            if let Some(selected) = self
+                                .context
+                                .selected_lines
+                                .iter_mut()
+                                .find(|selected| **selected == pos)
+                            {
+                                *selected = transformed_pos;
+                            }
+                        } else if !self
+                            .context
+                            .lines
+                            .iter()
+                            .any(|line| line.0 == transformed_pos)
+                        {
+                            // Lines transformed by this client are already transformed
+                            if let Err(err) = session.sender_to_server.send(
+                                common_definitions::MessageType::RequestSyncLine(Some(
+                                    transformed_pos,
+                                )),
+                            ) {
+                                dbg!(err);
+                            }
                         }
                     }
-                    common_definitions::MessageType::RequestSyncLine(_) => {
+                    common_definitions::MessageType::AddImage((id, header)) => {
+                        // Images sent by this client are already on the canvas
+                        let is_own_image = if id == BACKGROUND_IMAGE_ID {
+                            message.uuid == self.uuid.0
+                        } else {
+                            self.context.images.contains_key(&id)
+                        };
+
+                        if !is_own_image {
+                            if let Err(err) =
+                                self.context.connection.image_receiver.start(id, header)
+                            {
+                                dbg!(err);
+                            }
+                        }
+                    }
+                    common_definitions::MessageType::ImageChunk((id, offset, chunk)) => {
+                        if let Some(image) = self
+                            .context
+                            .connection
+                            .image_receiver
+                            .receive_chunk(id, offset, &chunk)
+                        {
+                            if id == BACKGROUND_IMAGE_ID {
+                                self.context.background_image = Some(image);
+                                self.context.background_image_loaded = false;
+                            } else {
+                                self.context.images.insert(id, image);
+                            }
+                        }
+                    }
+                    common_definitions::MessageType::ModifyImage((id, rect)) => {
+                        if id == BACKGROUND_IMAGE_ID {
+                            match rect {
+                                Some(rect) => {
+                                    if let Some(image) = &mut self.context.background_image {
+                                        image.rect = rect;
+                                    }
+                                }
+                                None => {
+                                    self.context.background_image = None;
+                                }
+                            }
+                        } else {
+                            match rect {
+                                Some(rect) => {
+                                    if let Some(image) = self.context.images.get_mut(&id) {
+                                        image.rect = rect;
+                                    }
+                                }
+                                None => {
+                                    self.context.images.shift_remove(&id);
+                                }
+                            }
+                        }
+                    }
+                    common_definitions::MessageType::AddShape((id, shape)) => {
+                        self.context.shapes.insert(id, shape);
+                    }
+                    common_definitions::MessageType::ModifyShape((id, shape)) => match shape {
+                        Some(shape) => {
+                            if let Some(old_shape) = self.context.shapes.get_mut(&id) {
+                                *old_shape = shape;
+                            }
+                        }
+                        None => {
+                            self.context.shapes.shift_remove(&id);
+                        }
+                    },
+                    common_definitions::MessageType::ModifyLayers(layers) => {
+                        self.context.layers = layers;
+                    }
+                    common_definitions::MessageType::ModifyBackground(background) => {
+                        self.context.background = background;
+                    }
+                    common_definitions::MessageType::AddText((id, text)) => {
+                        self.context.texts.insert(id, text);
+                    }
+                    common_definitions::MessageType::ModifyText((id, text)) => match text {
+                        Some(text) => {
+                            if let Some(old_text) = self.context.texts.get_mut(&id) {
+                                *old_text = text;
+                            }
+                        }
+                        None => {
+                            self.context.texts.shift_remove(&id);
+                        }
+                    },
+                    common_definitions::MessageType::RequestSyncLine(_)
+                    | common_definitions::MessageType::RequestExport(_) => {
                         unimplemented!("The server wont send client messages.")
                     }
+                    common_definitions::MessageType::Export((_, exported_canvas)) => {
+                        if let Some(export_path) = self.context.connection.server_export_path.take()
+                        {
+                            if let Err(err) = fs::write(export_path, exported_canvas) {
+                                display_error(err);
+                            }
+                        }
+                    }
+                    common_definitions::MessageType::ExportFailed(reason) => {
+                        if self.context.connection.server_export_path.take().is_some() {
+                            display_error(format!(
+                                "The server couldn't export the canvas: {reason}"
+                            ));
+                        }
+                    }
                     common_definitions::MessageType::SyncLine(line_sync_type) => {
                         match line_sync_type {
                             common_definitions::LineSyncType::Full(server_lines) => {
-                                self.context.lines = server_lines
+                                self.context.lines = server_lines;
+
+                                // The line which is drawn next
+                                self.context
+                                    .lines
+                                    .push((vec![], self.context.paintbrush.get_current_brush()));
+
+                                // The server sends the rest of the canvas after the lines, so everything which was deleted while the client lagged behind is removed
+                                self.context.images.clear();
+                                self.context.shapes.clear();
+                                self.context.texts.clear();
+                                self.context.background_image = None;
+                                self.context.background_image_loaded = false;
                             }
                             common_definitions::LineSyncType::Partial(line) => match line {
                                 Some(line) => {
@@ -650,16 +1229,39 @@ impl eframe::App for Application {
                 };
             }
 
-            if let Some(cur_pos) = ctx.pointer_latest_pos() {
-                if let Err(err) = session.sender_to_server.try_send(
-                    common_definitions::MessageType::CursorPosition(PointerProperties {
-                        pointer_pos: cur_pos,
-                        brush: self.context.paintbrush.get_current_brush(),
-                    }),
-                ) {
-                    dbg!(err);
+            // The pointer is sent in canvas units, and only when it's over the canvas
+            if let Some(cur_pos) = self.context.pointer_canvas_pos {
+                let brush = self.context.paintbrush.get_current_brush();
+
+                if self.context.connection.sent_pointer != Some((cur_pos, brush)) {
+                    self.context
+                        .send_message(common_definitions::MessageType::CursorPosition(
+                            PointerProperties {
+                                pointer_pos: cur_pos,
+                                brush,
+                            },
+                        ));
+
+                    self.context.connection.sent_pointer = Some((cur_pos, brush));
+                }
+            }
+
+            if let Some(viewport) = shown_viewport {
+                self.context.show_canvas_area(viewport);
+            }
 
-                    self.context.connection.current_session = None;
+            // The viewport is sent whenever the camera moves or the Canvas tab is resized
+            if self.context.visible_canvas_area != self.context.connection.sent_viewport {
+                if let Some(viewport) = self.context.visible_canvas_area {
+                    if let Some(session) = &self.context.connection.current_session {
+                        if session
+                            .sender_to_server
+                            .send(common_definitions::MessageType::Viewport(viewport))
+                            .is_ok()
+                        {
+                            self.context.connection.sent_viewport = Some(viewport);
+                        }
+                    }
                 }
             }
         };
@@ -668,20 +1270,8 @@ impl eframe::App for Application {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
     }
-}
 
-impl Application {
-    /// This function creates a new ```FileSession``` if a file is saved as or opened.
-    fn create_session(&mut self, saved_file_path: std::path::PathBuf, ctx: &Context) {
-        let project_name = saved_file_path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        self.context.file_session = Some(FileSession::create_session(
-            saved_file_path.clone(),
-            project_name.clone(),
-        ));
-        ctx.send_viewport_cmd(egui::ViewportCommand::Title(project_name));
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.finish_autosave_session();
     }
 }
@@ -0,0 +1,291 @@
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use egui::Context;
+use rfd::{MessageButtons, MessageDialogResult};
+
+use crate::{display_error, Application, ApplicationContext, DRAWING_BOARD_WORKSPACE_EXT};
+
+/// This struct contains the auto-save settings, and the time of the last auto-save.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct AutoSave {
+    /// Whether the document is saved automatically.
+    pub enabled: bool,
+    /// Whether the document is auto-saved into its open file, instead of the recovery file.
+    #[serde(default)]
+    pub save_to_file: bool,
+    /// The time between two auto-saves in seconds.
+    pub interval_secs: u64,
+    /// The time the document was last auto-saved at, or the time the application was started at.
+    #[serde(skip)]
+    last_save: Option<Instant>,
+    /// The session of this process, this is ```None``` if the recovery directory isn't available.
+    #[serde(skip)]
+    session: Option<AutoSaveSession>,
+}
+
+/// The recovery files of a running process.
+/// Every process has its own lock and recovery file, so that multiple windows don't overwrite or restore each other's recovery files.
+#[derive(Debug)]
+struct AutoSaveSession {
+    /// The identifier the session's files are named after.
+    id: String,
+    /// The lock file, which stays locked while the process is running.
+    /// The operating system releases the lock when the process exits, even if it crashes.
+    lock_file: File,
+}
+
+impl Default for AutoSave {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            save_to_file: false,
+            interval_secs: 120,
+            last_save: None,
+            session: None,
+        }
+    }
+}
+
+/// Returns the directory the recovery files are stored in.
+fn recovery_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("drawing_board"))
+}
+
+/// The extension of the lock files, the lock file of a session is named after the session's identifier.
+const LOCK_FILE_EXT: &str = "lock";
+
+/// Returns the path of the file the session's unsaved documents (Which don't have a ```FileSession```) are auto-saved into.
+fn recovery_file_path(recovery_dir: &Path, session_id: &str) -> PathBuf {
+    recovery_dir.join(format!(
+        "recovery-{session_id}.{DRAWING_BOARD_WORKSPACE_EXT}"
+    ))
+}
+
+/// Returns the path of the session's lock file, which stays locked while the session's process is running.
+/// If a lock file can be locked on startup, its process wasn't closed properly.
+fn lock_file_path(recovery_dir: &Path, session_id: &str) -> PathBuf {
+    recovery_dir.join(format!("{session_id}.{LOCK_FILE_EXT}"))
+}
+
+/// Writes the file through a temporary file next to it, which then replaces the file.
+/// This way the file isn't left half written if the application is closed or crashes while writing.
+pub fn write_file_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_file_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_file_name.push(".tmp");
+
+    let temp_file_path = path.with_file_name(temp_file_name);
+
+    let result = File::create(&temp_file_path).and_then(|mut temp_file| {
+        io::Write::write_all(&mut temp_file, contents)?;
+        temp_file.sync_all()
+    });
+
+    match result.and_then(|_| fs::rename(&temp_file_path, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            let _ = fs::remove_file(&temp_file_path);
+
+            Err(err)
+        }
+    }
+}
+
+/// Returns the identifiers of the sessions whose process exited without removing their lock file, the most recent one first.
+fn crashed_sessions(recovery_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(recovery_dir) else {
+        return vec![];
+    };
+
+    let mut sessions: Vec<(SystemTime, String)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+
+            if path.extension()? != LOCK_FILE_EXT {
+                return None;
+            }
+
+            let session_id = path.file_stem()?.to_str()?.to_string();
+
+            // The lock of a running process can't be acquired, the lock is released right away
+            File::open(&path).ok()?.try_lock().ok()?;
+
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+
+            Some((modified.unwrap_or(UNIX_EPOCH), session_id))
+        })
+        .collect();
+
+    sessions.sort_by_key(|(modified, _)| Reverse(*modified));
+
+    sessions
+        .into_iter()
+        .map(|(_, session_id)| session_id)
+        .collect()
+}
+
+impl AutoSave {
+    /// Returns the path of this session's recovery file.
+    fn recovery_file_path(&self) -> Option<PathBuf> {
+        Some(recovery_file_path(
+            &recovery_dir()?,
+            &self.session.as_ref()?.id,
+        ))
+    }
+
+    /// Removes the recovery file, this is called when the document is saved, so that an outdated version doesn't get restored.
+    pub fn remove_recovery_file(&self) {
+        if let Some(recovery_file_path) = self.recovery_file_path() {
+            let _ = fs::remove_file(recovery_file_path);
+        }
+    }
+}
+
+impl Application {
+    /// Creates and locks the lock file of this session.
+    /// If a previous session wasn't closed properly and left a recovery file, the user is asked whether it should be restored.
+    pub fn start_autosave_session(&mut self) {
+        let Some(recovery_dir) = recovery_dir() else {
+            return;
+        };
+
+        let mut restored = false;
+
+        for session_id in crashed_sessions(&recovery_dir) {
+            let recovery_file_path = recovery_file_path(&recovery_dir, &session_id);
+
+            if recovery_file_path.exists() {
+                // Only one document can be restored, the other recovery files are offered on the next start
+                if restored {
+                    continue;
+                }
+
+                let restore = rfd::MessageDialog::new()
+                    .set_title("Restore unsaved changes")
+                    .set_description(
+                        "The application wasn't closed properly last time. Do you want to restore the unsaved changes?",
+                    )
+                    .set_buttons(MessageButtons::YesNo)
+                    .show();
+
+                if restore == MessageDialogResult::Yes {
+                    match fs::read(&recovery_file_path)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|file| ApplicationContext::from_workspace_file(&file))
+                    {
+                        Ok(context) => {
+                            self.context = context;
+                            self.context.mark_unsaved();
+
+                            restored = true;
+                        }
+                        Err(err) => display_error(err),
+                    }
+                }
+
+                // The restored document is auto-saved into this session's recovery file if it doesn't get saved
+                let _ = fs::remove_file(recovery_file_path);
+            }
+
+            let _ = fs::remove_file(lock_file_path(&recovery_dir, &session_id));
+        }
+
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let session_id = format!("session-{}-{}", std::process::id(), since_epoch.as_millis());
+
+        let lock_file = fs::create_dir_all(&recovery_dir)
+            .and_then(|_| File::create(lock_file_path(&recovery_dir, &session_id)))
+            .and_then(|lock_file| {
+                lock_file.lock()?;
+
+                Ok(lock_file)
+            });
+
+        match lock_file {
+            Ok(lock_file) => {
+                self.autosave.session = Some(AutoSaveSession {
+                    id: session_id,
+                    lock_file,
+                });
+            }
+            Err(err) => {
+                dbg!(err);
+            }
+        }
+    }
+
+    /// Removes the lock and the recovery files, as the application was closed properly.
+    pub fn finish_autosave_session(&mut self) {
+        self.autosave.remove_recovery_file();
+
+        let (Some(recovery_dir), Some(session)) = (recovery_dir(), self.autosave.session.take())
+        else {
+            return;
+        };
+
+        // The lock is released before the lock file is removed, as locked files can't be removed on every platform
+        drop(session.lock_file);
+
+        let _ = fs::remove_file(lock_file_path(&recovery_dir, &session.id));
+    }
+
+    /// Saves the document if it has unsaved changes and the auto-save interval has passed.
+    /// The document is saved into the recovery file, unless saving into the open file was turned on and the document has a ```FileSession```.
+    pub fn autosave(&mut self, ctx: &Context) {
+        if !self.autosave.enabled {
+            return;
+        }
+
+        let interval = Duration::from_secs(self.autosave.interval_secs);
+        let now = Instant::now();
+        let last_save = *self.autosave.last_save.get_or_insert(now);
+
+        // Make sure that the application gets updated, even if there aren't any events
+        ctx.request_repaint_after(interval.saturating_sub(now.duration_since(last_save)));
+
+        if now.duration_since(last_save) < interval {
+            return;
+        }
+
+        self.autosave.last_save = Some(now);
+
+        if !self.context.unsaved_changes {
+            return;
+        }
+
+        let file_kind = self
+            .context
+            .file_session
+            .as_ref()
+            .map(|session| session.kind)
+            .filter(|_| self.autosave.save_to_file);
+
+        let result = match file_kind {
+            Some(kind) => self.save_document(kind, false).map(|_| ()),
+            None => self
+                .autosave
+                .recovery_file_path()
+                .ok_or_else(|| anyhow::Error::msg("The recovery directory is not available."))
+                .and_then(|recovery_file_path| {
+                    Ok(write_file_atomically(
+                        &recovery_file_path,
+                        &self.context.to_recovery_file()?,
+                    )?)
+                }),
+        };
+
+        // Auto-save is turned off, so that the error isn't displayed on every auto-save
+        if let Err(err) = result {
+            self.autosave.enabled = false;
+
+            display_error(format!("Auto-save failed, and has been turned off: {err}"));
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::fs;
+
+use common_definitions::{BackgroundStyle, CanvasImage, MessageType, BACKGROUND_IMAGE_ID};
+use drawing_board_renderer::{A4_PAGE_SIZE, DEFAULT_CANVAS_SCALE};
+use egui::{emath, vec2, Color32, Context, Painter, Pos2, Rect, Stroke, Ui};
+
+use crate::{
+    create_canvas_image, display_error, export::page_size_ui, grid::steps_in_range,
+    ApplicationContext,
+};
+
+/// The uri the background image's bytes are registered with in egui's image loaders.
+const BACKGROUND_IMAGE_URI: &str = "bytes://canvas_background";
+
+/// The smallest distance (In points) between two lines of the paper for them to be drawn, denser lines would only cover the paper.
+const MIN_PAPER_LINE_SCREEN_SPACING: f32 = 4.;
+
+/// The number of graph paper lines after which a stronger line is drawn.
+const GRAPH_MAJOR_LINE_INTERVAL: i64 = 5;
+
+impl ApplicationContext {
+    /// Replaces the background image, the new image's bytes are registered in egui's image loaders the next time the background is drawn.
+    pub fn set_background_image(&mut self, image: Option<CanvasImage>) {
+        self.background_image = image;
+        self.background_image_loaded = false;
+    }
+
+    /// Sends the background to the server if there is an open connection, and marks the canvas as modified.
+    fn background_changed(&mut self) {
+        self.send_messages(vec![MessageType::ModifyBackground(self.background)]);
+        self.mark_unsaved();
+    }
+
+    /// Imports an image file as the background, the image fills the page if the canvas is bounded to a page.
+    fn import_background_image(&mut self) -> anyhow::Result<()> {
+        let Some(image_path) = rfd::FileDialog::new()
+            .add_filter("Image", &["png", "jpg", "jpeg"])
+            .pick_file()
+        else {
+            return Ok(());
+        };
+
+        let center = self
+            .background
+            .page_rect()
+            .or(self.visible_canvas_area)
+            .map(|area| area.center())
+            .unwrap_or(Pos2::new(0.5, 0.5));
+
+        let image = create_canvas_image(fs::read(image_path)?, center)?;
+
+        self.send_messages(image.to_messages(BACKGROUND_IMAGE_ID));
+        self.set_background_image(Some(image));
+
+        self.background.style = BackgroundStyle::Image;
+        self.background_changed();
+
+        Ok(())
+    }
+
+    /// Removes the background image, the background becomes transparent if it was displaying the image.
+    fn remove_background_image(&mut self) {
+        self.send_messages(vec![MessageType::ModifyImage((BACKGROUND_IMAGE_ID, None))]);
+        self.set_background_image(None);
+
+        if self.background.style == BackgroundStyle::Image {
+            self.background.style = BackgroundStyle::Transparent;
+        }
+
+        self.background_changed();
+    }
+
+    /// Draws the background below everything placed on the canvas.
+    /// If the canvas is bounded to a page, the background is only drawn on the page and the page gets an outline.
+    pub fn draw_background(&mut self, ui: &Ui, painter: &Painter, to_screen: emath::RectTransform) {
+        let page_rect = self.background.page_rect();
+
+        // The background covers the whole visible area, unless it's bounded to a page
+        let area = page_rect.unwrap_or(*to_screen.from());
+        let screen_area = to_screen.transform_rect(area);
+
+        if let Some(page_rect) = page_rect {
+            painter.rect_filled(
+                to_screen.transform_rect(page_rect).translate(vec2(3., 3.)),
+                0.,
+                Color32::from_black_alpha(100),
+            );
+        }
+
+        if let Some(color) = self.background.fill_color() {
+            painter.rect_filled(screen_area, 0., color);
+        }
+
+        match self.background.style {
+            BackgroundStyle::Ruled | BackgroundStyle::Graph => {
+                self.draw_paper_lines(painter, area, to_screen);
+            }
+            BackgroundStyle::Image => self.draw_background_image(ui, page_rect, to_screen),
+            BackgroundStyle::Solid | BackgroundStyle::Transparent => (),
+        }
+
+        if let Some(page_rect) = page_rect {
+            painter.rect_stroke(
+                to_screen.transform_rect(page_rect),
+                0.,
+                Stroke::new(1., ui.visuals().weak_text_color()),
+            );
+        }
+    }
+
+    /// Draws the lines of the ```BackgroundStyle::Ruled``` and ```BackgroundStyle::Graph``` paper in the ```area``` (In canvas units).
+    fn draw_paper_lines(&self, painter: &Painter, area: Rect, to_screen: emath::RectTransform) {
+        let spacing = self.background.line_spacing.max(1.) / DEFAULT_CANVAS_SCALE;
+
+        if spacing * to_screen.scale().x < MIN_PAPER_LINE_SCREEN_SPACING {
+            return;
+        }
+
+        // Only the lines on the screen are drawn
+        let area = area.intersect(*to_screen.from());
+
+        if !area.is_positive() {
+            return;
+        }
+
+        let screen_area = to_screen.transform_rect(area);
+        let line_color = self.background.line_color;
+        let stroke = |position: f32| {
+            let is_major = self.background.style == BackgroundStyle::Graph
+                && (position / spacing).round() as i64 % GRAPH_MAJOR_LINE_INTERVAL == 0;
+
+            Stroke::new(if is_major { 1.5 } else { 1. }, line_color)
+        };
+
+        for y in steps_in_range(area.y_range(), spacing) {
+            let screen_y = (to_screen * Pos2::new(0., y)).y;
+
+            painter.hline(screen_area.x_range(), screen_y, stroke(y));
+        }
+
+        if self.background.style == BackgroundStyle::Graph {
+            for x in steps_in_range(area.x_range(), spacing) {
+                let screen_x = (to_screen * Pos2::new(x, 0.)).x;
+
+                painter.vline(screen_x, screen_area.y_range(), stroke(x));
+            }
+        }
+    }
+
+    /// Draws the background image, stretched onto the page if the canvas is bounded to one.
+    fn draw_background_image(
+        &mut self,
+        ui: &Ui,
+        page_rect: Option<Rect>,
+        to_screen: emath::RectTransform,
+    ) {
+        let Some(image) = &self.background_image else {
+            return;
+        };
+
+        if !self.background_image_loaded {
+            ui.ctx().forget_image(BACKGROUND_IMAGE_URI);
+            ui.ctx()
+                .include_bytes(BACKGROUND_IMAGE_URI, image.data.clone());
+
+            self.background_image_loaded = true;
+        }
+
+        let rect = to_screen.transform_rect(page_rect.unwrap_or(image.rect));
+
+        egui::Image::new(BACKGROUND_IMAGE_URI).paint_at(ui, rect);
+    }
+
+    /// Displays the background dialog, if it was opened.
+    pub fn background_dialog(&mut self, ctx: &Context) {
+        let mut dialog_open = self.background_dialog_open;
+        let mut changed = false;
+
+        egui::Window::new("Canvas Background")
+            .open(&mut dialog_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let background = &mut self.background;
+
+                ui.horizontal(|ui| {
+                    ui.label("Style");
+
+                    for style in BackgroundStyle::ALL {
+                        let style_name: &'static str = style.into();
+
+                        changed |= ui
+                            .selectable_value(&mut background.style, style, style_name)
+                            .changed();
+                    }
+                });
+
+                if background.fill_color().is_some() {
+                    ui.horizontal(|ui| {
+                        ui.label("Paper color");
+                        changed |= ui.color_edit_button_srgba(&mut background.color).changed();
+                    });
+                }
+
+                if matches!(
+                    background.style,
+                    BackgroundStyle::Ruled | BackgroundStyle::Graph
+                ) {
+                    ui.horizontal(|ui| {
+                        ui.label("Line color");
+                        changed |= ui
+                            .color_edit_button_srgba(&mut background.line_color)
+                            .changed();
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Line spacing");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut background.line_spacing)
+                                    .range(4.0..=512.0)
+                                    .suffix(" pt"),
+                            )
+                            .changed();
+                    });
+                }
+
+                if background.style == BackgroundStyle::Image {
+                    ui.horizontal(|ui| {
+                        if ui.button("Import Image").clicked() {
+                            if let Err(err) = self.import_background_image() {
+                                display_error(err);
+                            }
+                        }
+
+                        if ui
+                            .add_enabled(
+                                self.background_image.is_some(),
+                                egui::Button::new("Remove Image"),
+                            )
+                            .clicked()
+                        {
+                            self.remove_background_image();
+                        }
+                    });
+                }
+
+                ui.separator();
+
+                let background = &mut self.background;
+                let mut has_page = background.page_size.is_some();
+
+                if ui
+                    .checkbox(&mut has_page, "Page bounds")
+                    .on_hover_text("Bounds the background to a fixed size page starting at the canvas' origin, exports of the Canvas area are cropped to this page.")
+                    .changed()
+                {
+                    background.page_size = has_page.then_some(A4_PAGE_SIZE);
+                    changed = true;
+                }
+
+                if let Some(page_size) = background.page_size.as_mut() {
+                    let previous_size = *page_size;
+
+                    page_size_ui(ui, page_size);
+
+                    changed |= *page_size != previous_size;
+                }
+            });
+
+        if changed {
+            // Exporting the Page area exports the canvas' page
+            if let Some(page_size) = self.background.page_size {
+                self.export_settings.page_size = page_size;
+            }
+
+            self.background_changed();
+        }
+
+        self.background_dialog_open &= dialog_open;
+    }
+}
@@ -0,0 +1,73 @@
+use common_definitions::{brush_stamps, BrushTexture, LinePos, StampSettings};
+use egui::{
+    emath::{self, Rot2},
+    pos2, vec2, Color32, ColorImage, Context, Mesh, Rect, Shape, TextureHandle, TextureOptions,
+};
+
+use crate::{camera::canvas_zoom, ApplicationContext};
+
+/// Decodes the ```BrushTexture``` and uploads it to egui.
+fn load_brush_texture(ctx: &Context, texture: BrushTexture) -> anyhow::Result<TextureHandle> {
+    let image = image::load_from_memory(texture.png_bytes())?.to_rgba8();
+    let texture_name: &'static str = texture.into();
+
+    Ok(ctx.load_texture(
+        format!("brush_texture_{texture_name}"),
+        ColorImage::from_rgba_unmultiplied(
+            [image.width() as usize, image.height() as usize],
+            image.as_raw(),
+        ),
+        TextureOptions::LINEAR,
+    ))
+}
+
+/// Creates the ```Shape``` of a ```BrushType::Stamp``` line, which is a mesh containing a textured quad for every stamp.
+/// The texture is tinted with the line's color.
+pub fn stamp_line_shape(
+    points: &[LinePos],
+    width: f32,
+    color: Color32,
+    settings: &StampSettings,
+    texture: &TextureHandle,
+    to_screen: emath::RectTransform,
+) -> Shape {
+    let mut mesh = Mesh::with_texture(texture.id());
+    let zoom = canvas_zoom(to_screen);
+
+    for stamp in brush_stamps(points, width, settings) {
+        let center = to_screen * stamp.anchor + stamp.offset * zoom;
+        let size = stamp.size * zoom;
+
+        let mut stamp_mesh = Mesh::with_texture(texture.id());
+        stamp_mesh.add_rect_with_uv(
+            Rect::from_center_size(center, vec2(size, size)),
+            Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+            color,
+        );
+        stamp_mesh.rotate(Rot2::from_angle(stamp.rotation), center);
+
+        mesh.append(stamp_mesh);
+    }
+
+    Shape::mesh(mesh)
+}
+
+impl ApplicationContext {
+    /// Uploads the built-in ```BrushTexture```-s to egui, if they haven't been uploaded yet.
+    pub fn load_brush_textures(&mut self, ctx: &Context) {
+        for texture in BrushTexture::ALL {
+            if self.brush_textures.contains_key(&texture) {
+                continue;
+            }
+
+            match load_brush_texture(ctx, texture) {
+                Ok(handle) => {
+                    self.brush_textures.insert(texture, handle);
+                }
+                Err(err) => {
+                    dbg!(err);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use common_definitions::DEFAULT_CANVAS_SCALE;
+use egui::{emath, CursorIcon, Key, Pos2, Rect, Response, Ui, Vec2};
+
+use crate::ApplicationContext;
+
+/// The smallest and the largest zoom of the camera.
+const ZOOM_RANGE: std::ops::RangeInclusive<f32> = 0.02..=50.;
+
+/// The number of points the mouse wheel has to be scrolled to zoom by a factor of ```e```.
+const WHEEL_ZOOM_SPEED: f32 = 200.;
+
+/// The empty space (In points) left around the drawing when the camera is fit to it.
+const FIT_PADDING: f32 = 40.;
+
+/// Returns the zoom the canvas is displayed with, this is the number of points one point of a brush's width takes up on the screen.
+pub(crate) fn canvas_zoom(to_screen: emath::RectTransform) -> f32 {
+    to_screen.scale().x / DEFAULT_CANVAS_SCALE
+}
+
+/// The part of the canvas which is displayed in the Canvas tab.
+/// The lines are stored in canvas units, the camera decides where they end up on the screen, so the drawing doesn't depend on the size of the window.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// The position (In canvas units) displayed in the middle of the Canvas tab.
+    pub center: Pos2,
+    /// The zoom of the canvas, at ```1.0``` one canvas unit is ```DEFAULT_CANVAS_SCALE``` points large.
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            center: Pos2::new(0.5, 0.5),
+            zoom: 1.,
+        }
+    }
+}
+
+impl Camera {
+    /// Returns the number of points one canvas unit takes up on the screen.
+    fn points_per_unit(&self) -> f32 {
+        DEFAULT_CANVAS_SCALE * self.zoom
+    }
+
+    /// Returns the transformation from canvas units to the screen, where ```rect``` is the area of the Canvas tab.
+    pub fn to_screen(&self, rect: Rect) -> emath::RectTransform {
+        emath::RectTransform::from_to(
+            Rect::from_center_size(self.center, rect.size() / self.points_per_unit()),
+            rect,
+        )
+    }
+
+    /// Moves the camera so that the canvas follows the pointer, which was moved by ```delta``` points.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.center -= delta / self.points_per_unit();
+    }
+
+    /// Zooms the camera by ```factor```, while keeping the canvas position under ```anchor``` (In points) in place.
+    pub fn zoom_around(&mut self, factor: f32, anchor: Pos2, rect: Rect) {
+        let anchor_on_canvas = self.to_screen(rect).inverse() * anchor;
+
+        self.zoom = (self.zoom * factor).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+        self.center = anchor_on_canvas - (anchor - rect.center()) / self.points_per_unit();
+    }
+
+    /// Moves and zooms the camera so that ```bounds``` (In canvas units) fill the area of the Canvas tab, which is ```size``` points large.
+    /// ```padding``` is the empty space (In points) left around the ```bounds```.
+    pub fn fit(&mut self, bounds: Rect, size: Vec2, padding: f32) {
+        let available_size = (size - Vec2::splat(padding * 2.)).max(Vec2::splat(1.));
+        let bounds_size = bounds.size().max(Vec2::splat(f32::EPSILON));
+
+        self.center = bounds.center();
+        self.zoom = ((available_size / bounds_size).min_elem() / DEFAULT_CANVAS_SCALE)
+            .clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+    }
+}
+
+impl ApplicationContext {
+    /// Returns the area (In canvas units) covered by the lines, images, shapes and texts, this is ```None``` if the canvas is empty.
+    fn content_bounds(&self) -> Option<Rect> {
+        let line_points = self
+            .lines
+            .iter()
+            .flat_map(|(points, _)| points.iter().map(|pos| Pos2::from(*pos)));
+        let image_corners = self
+            .images
+            .values()
+            .flat_map(|image| [image.rect.min, image.rect.max]);
+        let shape_points = self.shapes.values().flat_map(|shape| shape.outline());
+        let text_points = self.texts.values().map(|text| text.position);
+
+        let bounds = line_points
+            .chain(image_corners)
+            .chain(shape_points)
+            .chain(text_points)
+            .fold(Rect::NOTHING, |rect, pos| rect.union(Rect::from_pos(pos)));
+
+        bounds.is_finite().then_some(bounds)
+    }
+
+    /// Returns the size (In points) of the Canvas tab, which is only known after the tab has been drawn.
+    fn canvas_tab_size(&self) -> Vec2 {
+        self.visible_canvas_area
+            .map(|area| area.size() * self.camera.points_per_unit())
+            .unwrap_or(Vec2::splat(DEFAULT_CANVAS_SCALE))
+    }
+
+    /// Moves the camera back to the middle of the canvas, with the default zoom.
+    pub fn reset_camera(&mut self) {
+        self.camera = Camera::default();
+        self.connection.followed_client = None;
+    }
+
+    /// Moves and zooms the camera so that the ```area``` (In canvas units) fills the Canvas tab.
+    /// This is used to show the area another user is looking at.
+    pub fn show_canvas_area(&mut self, area: Rect) {
+        let size = self.canvas_tab_size();
+
+        self.camera.fit(area, size, 0.);
+    }
+
+    /// Moves and zooms the camera so that everything placed on the canvas is visible.
+    /// The camera is reset if the canvas is empty.
+    pub fn fit_camera_to_content(&mut self) {
+        let Some(bounds) = self.content_bounds() else {
+            self.reset_camera();
+
+            return;
+        };
+
+        let size = self.canvas_tab_size();
+
+        self.camera.fit(bounds, size, FIT_PADDING);
+        self.connection.followed_client = None;
+    }
+
+    /// Pans the camera when the canvas is dragged with the middle mouse button, with two fingers, or while holding Space, and zooms it with the mouse wheel or pinching.
+    /// Returns whether the canvas is being panned, in which case the brushes shouldn't handle the pointer.
+    pub fn camera_interaction(&mut self, ui: &Ui, response: &Response) -> bool {
+        let rect = response.rect;
+
+        // Space belongs to the text being edited
+        let space_down =
+            !ui.ctx().wants_keyboard_input() && ui.input(|input| input.key_down(Key::Space));
+
+        let is_panning = response.is_pointer_button_down_on()
+            && ui.input(|input| {
+                input.pointer.middle_down() || (space_down && input.pointer.primary_down())
+            });
+
+        if is_panning {
+            self.camera.pan(response.drag_delta());
+
+            ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+        } else if space_down && response.hovered() {
+            ui.ctx().set_cursor_icon(CursorIcon::Grab);
+        }
+
+        let touch = ui.input(|input| input.multi_touch());
+
+        if let Some(touch) = touch {
+            self.camera.pan(touch.translation_delta);
+        }
+
+        if let Some(pointer_pos) = response.hover_pos() {
+            // Pinching and Ctrl + scrolling is reported as zoom, plain scrolling zooms as well
+            let factor = ui.input(|input| {
+                input.zoom_delta() * (input.smooth_scroll_delta.y / WHEEL_ZOOM_SPEED).exp()
+            });
+
+            if factor != 1. {
+                self.camera.zoom_around(factor, pointer_pos, rect);
+
+                self.connection.followed_client = None;
+            }
+        }
+
+        // Moving the camera by hand stops following the other user
+        if is_panning || touch.is_some() {
+            self.connection.followed_client = None;
+        }
+
+        is_panning || touch.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::vec2;
+
+    use super::*;
+
+    /// The area of the Canvas tab.
+    fn tab_rect() -> Rect {
+        Rect::from_min_size(Pos2::new(100., 50.), vec2(800., 600.))
+    }
+
+    fn assert_close(a: Pos2, b: Pos2) {
+        assert!(a.distance(b) < 1e-3, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn center_is_displayed_in_the_middle() {
+        let camera = Camera {
+            center: Pos2::new(2., -1.),
+            zoom: 3.,
+        };
+        let to_screen = camera.to_screen(tab_rect());
+
+        assert_close(to_screen * camera.center, tab_rect().center());
+        assert!((canvas_zoom(to_screen) - 3.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn pan_follows_the_pointer() {
+        let mut camera = Camera::default();
+        let point = Pos2::new(0.2, 0.3);
+        let screen_point = camera.to_screen(tab_rect()) * point;
+
+        camera.pan(vec2(30., -20.));
+
+        assert_close(
+            camera.to_screen(tab_rect()) * point,
+            screen_point + vec2(30., -20.),
+        );
+    }
+
+    #[test]
+    fn zoom_keeps_the_anchor_in_place() {
+        let mut camera = Camera::default();
+        let anchor = Pos2::new(250., 400.);
+        let anchor_on_canvas = camera.to_screen(tab_rect()).inverse() * anchor;
+
+        camera.zoom_around(2.5, anchor, tab_rect());
+
+        assert_eq!(camera.zoom, 2.5);
+        assert_close(camera.to_screen(tab_rect()) * anchor_on_canvas, anchor);
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut camera = Camera::default();
+
+        camera.zoom_around(1000., tab_rect().center(), tab_rect());
+        assert_eq!(camera.zoom, *ZOOM_RANGE.end());
+
+        camera.zoom_around(0.00001, tab_rect().center(), tab_rect());
+        assert_eq!(camera.zoom, *ZOOM_RANGE.start());
+    }
+
+    #[test]
+    fn fit_shows_the_bounds() {
+        let mut camera = Camera::default();
+        let bounds = Rect::from_min_max(Pos2::new(1., 1.), Pos2::new(3., 2.));
+
+        camera.fit(bounds, tab_rect().size(), 50.);
+
+        let to_screen = camera.to_screen(tab_rect());
+        let screen_bounds = to_screen.transform_rect(bounds);
+
+        // The bounds are as wide as the tab without the padding, and fit into its height
+        assert_close(screen_bounds.center(), tab_rect().center());
+        assert!((screen_bounds.width() - 700.).abs() < 1e-3);
+        assert!(screen_bounds.height() <= 500.);
+    }
+}
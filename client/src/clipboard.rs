@@ -0,0 +1,282 @@
+use std::{borrow::Cow, io::Cursor};
+
+use base64::Engine;
+use common_definitions::BrushType;
+use drawing_board_renderer::{render_pixmap, render_png, CanvasContent, RenderOptions};
+use egui::{emath, Event, Pos2, Rect, Ui, Vec2};
+
+use crate::{
+    display_error,
+    selection::{transform_line, DUPLICATE_OFFSET},
+    ApplicationContext, BrushMap,
+};
+
+/// The prefix of the clipboard text containing copied lines, so that the lines can be told apart from other texts.
+const CLIPBOARD_LINES_PREFIX: &str = "drawing_board_lines:";
+
+/// Stores the lines in a clipboard text, which can be read back with ```lines_from_clipboard_text```.
+fn lines_to_clipboard_text(lines: &BrushMap) -> anyhow::Result<String> {
+    Ok(format!(
+        "{CLIPBOARD_LINES_PREFIX}{}",
+        serde_json::to_string(lines)?
+    ))
+}
+
+/// Returns the lines stored in the clipboard text, if the text contains copied lines.
+fn lines_from_clipboard_text(text: &str) -> Option<BrushMap> {
+    serde_json::from_str(text.strip_prefix(CLIPBOARD_LINES_PREFIX)?).ok()
+}
+
+/// Encodes the image read from the clipboard into a png image.
+fn clipboard_image_to_png(image: arboard::ImageData) -> anyhow::Result<Vec<u8>> {
+    let image = image::RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .ok_or_else(|| anyhow::Error::msg("Invalid clipboard image."))?;
+
+    let mut png = Cursor::new(vec![]);
+
+    image.write_to(&mut png, image::ImageFormat::Png)?;
+
+    Ok(png.into_inner())
+}
+
+impl ApplicationContext {
+    /// Returns the system clipboard, the clipboard is opened the first time it's used.
+    /// The clipboard is kept open, as on some platforms the copied data is lost once it's closed.
+    fn clipboard(&mut self) -> anyhow::Result<&mut arboard::Clipboard> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+
+        Ok(self.clipboard.as_mut().unwrap())
+    }
+
+    /// Returns the selected lines with their brushes, in the order they are drawn in.
+    fn selected_line_data(&self) -> BrushMap {
+        self.lines
+            .iter()
+            .filter(|(points, _)| self.selected_lines.contains(points))
+            .cloned()
+            .collect()
+    }
+
+    /// Copies the selected lines onto the clipboard.
+    /// The lines are stored as text, and as an html image for the applications which can't read them, like word processors.
+    pub fn copy_selection(&mut self) -> anyhow::Result<()> {
+        let lines = self.selected_line_data();
+
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let text = lines_to_clipboard_text(&lines)?;
+        let png = render_png(
+            &CanvasContent {
+                lines: &lines,
+                ..Default::default()
+            },
+            &RenderOptions::default(),
+        )?;
+        let html = format!(
+            "<img src=\"data:image/png;base64,{}\">",
+            base64::engine::general_purpose::STANDARD.encode(png)
+        );
+
+        self.clipboard()?.set_html(html, Some(text))?;
+
+        Ok(())
+    }
+
+    /// Copies the selected lines onto the clipboard as an image, so that they can be pasted into image editors.
+    pub fn copy_selection_as_image(&mut self) -> anyhow::Result<()> {
+        let lines = self.selected_line_data();
+
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let pixmap = render_pixmap(
+            &CanvasContent {
+                lines: &lines,
+                ..Default::default()
+            },
+            &RenderOptions::default(),
+        )?;
+
+        // The clipboard expects straight alpha, while the pixmap's colors are premultiplied
+        let bytes = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect::<Vec<u8>>();
+
+        self.clipboard()?.set_image(arboard::ImageData {
+            width: pixmap.width() as usize,
+            height: pixmap.height() as usize,
+            bytes: Cow::Owned(bytes),
+        })?;
+
+        Ok(())
+    }
+
+    /// Copies the selected lines onto the clipboard, and deletes them from the canvas.
+    pub fn cut_selection(&mut self) -> anyhow::Result<()> {
+        if self.selected_lines.is_empty() {
+            return Ok(());
+        }
+
+        self.copy_selection()?;
+        self.delete_selection();
+
+        self.add_undo_point();
+        self.mark_unsaved();
+
+        Ok(())
+    }
+
+    /// Places the copied lines onto the canvas and selects them.
+    /// The lines are centered on ```center``` (In canvas units), or moved next to the copied lines if they would cover them.
+    fn paste_lines(&mut self, lines: BrushMap, center: Option<Pos2>) {
+        let bounds = lines
+            .iter()
+            .flat_map(|(points, _)| points.iter().map(|pos| Pos2::from(*pos)))
+            .fold(Rect::NOTHING, |rect, pos| rect.union(Rect::from_pos(pos)));
+
+        let mut offset = center.map_or(Vec2::ZERO, |center| center - bounds.center());
+
+        // Lines are identified by their points, so the pasted lines can't cover the existing ones
+        while lines.iter().any(|(points, _)| {
+            let pasted_points = transform_line(points, |pos| pos + offset);
+
+            self.lines.iter().any(|line| line.0 == pasted_points)
+        }) {
+            offset += Vec2::splat(DUPLICATE_OFFSET);
+        }
+
+        // The pasted lines are placed on the active layer by ```insert_lines```
+        let lines: BrushMap = lines
+            .into_iter()
+            .map(|(points, brush)| (transform_line(&points, |pos| pos + offset), brush))
+            .collect();
+
+        self.selected_lines = lines.iter().map(|(points, _)| points.clone()).collect();
+        self.insert_lines(lines);
+
+        // The pasted lines can only be moved when no brush is selected
+        self.paintbrush.brush_type = BrushType::None;
+    }
+
+    /// Pastes the lines or the image from the clipboard onto the canvas.
+    /// ```center``` is the position (In canvas units) the pasted lines are centered on, images are placed in the middle of the visible area.
+    pub fn paste(&mut self, center: Option<Pos2>) -> anyhow::Result<()> {
+        let clipboard = self.clipboard()?;
+
+        if let Some(lines) = clipboard
+            .get_text()
+            .ok()
+            .and_then(|text| lines_from_clipboard_text(&text))
+        {
+            self.paste_lines(lines, center);
+
+            return Ok(());
+        }
+
+        let image = clipboard
+            .get_image()
+            .map_err(|_| anyhow::Error::msg("The clipboard doesn't contain lines or an image."))?;
+
+        self.insert_image(clipboard_image_to_png(image)?)?;
+
+        // Images can only be moved when no brush is selected
+        self.paintbrush.brush_type = BrushType::None;
+
+        Ok(())
+    }
+
+    /// Handles the copy, cut and paste shortcuts on the canvas.
+    /// Copying and cutting work on the lines selected with the ```BrushType::None``` brush, pasted lines are centered on the pointer.
+    pub fn clipboard_interaction(&mut self, ui: &Ui, to_screen: emath::RectTransform) {
+        // The shortcuts belong to the text being edited
+        if ui.ctx().wants_keyboard_input() {
+            return;
+        }
+
+        let events = ui.input(|input| input.events.clone());
+        let pointer_pos = ui
+            .ctx()
+            .pointer_hover_pos()
+            .filter(|pos| to_screen.to().contains(*pos))
+            .map(|pos| to_screen.inverse() * pos);
+
+        for event in events {
+            let result = match event {
+                Event::Copy => self.copy_selection(),
+                Event::Cut => self.cut_selection(),
+                Event::Paste(text) => match lines_from_clipboard_text(&text) {
+                    Some(lines) => {
+                        self.paste_lines(lines, pointer_pos);
+
+                        Ok(())
+                    }
+                    // Texts copied from other applications are ignored, but they might come with an image
+                    None => self.paste(pointer_pos).or(Ok(())),
+                },
+                _ => Ok(()),
+            };
+
+            if let Err(err) = result {
+                display_error(err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_definitions::{Brush, LinePos, Uuid};
+    use egui::Color32;
+
+    use super::*;
+
+    #[test]
+    fn clipboard_text_round_trip() {
+        let lines: BrushMap = vec![
+            (
+                vec![
+                    LinePos::with_pressure(Pos2::new(0.1, 0.2), 0.5),
+                    LinePos::with_pressure(Pos2::new(0.3, 0.4), 1.),
+                ],
+                Brush {
+                    width: 4.,
+                    color: Color32::RED,
+                    brush_type: BrushType::Marker,
+                    layer: Uuid::new_v4(),
+                    ..Default::default()
+                },
+            ),
+            (vec![Pos2::new(1., 1.).into()], Brush::default()),
+        ];
+
+        let text = lines_to_clipboard_text(&lines).unwrap();
+
+        assert!(text.starts_with(CLIPBOARD_LINES_PREFIX));
+        assert_eq!(lines_from_clipboard_text(&text), Some(lines));
+    }
+
+    #[test]
+    fn other_texts_are_not_lines() {
+        assert_eq!(lines_from_clipboard_text("some text"), None);
+        assert_eq!(lines_from_clipboard_text("[]"), None);
+        assert_eq!(
+            lines_from_clipboard_text(&format!("{CLIPBOARD_LINES_PREFIX}not json")),
+            None
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use common_definitions::{BrushType, Uuid};
+use egui::{ecolor::Hsva, emath, Color32, FontId, Painter, Pos2, Rect, Shape, Stroke, Ui, Vec2};
+
+use crate::{camera::canvas_zoom, ApplicationContext};
+
+/// The time a cursor is fully visible for after it last moved.
+const CURSOR_IDLE_TIME: Duration = Duration::from_secs(3);
+
+/// The time it takes for an idle cursor to fade out.
+const CURSOR_FADE_TIME: Duration = Duration::from_secs(2);
+
+/// Returns the color the client's cursor and name are displayed with.
+/// The color is derived from the client's ```Uuid```, so that every user sees the same color for the same client.
+pub fn client_color(id: Uuid) -> Color32 {
+    let hue = (id.as_u128() % 360) as f32 / 360.;
+
+    Hsva::new(hue, 0.7, 0.9, 1.).into()
+}
+
+/// Returns the opacity of a cursor which last moved at ```moved_at```, idle cursors fade out.
+fn cursor_opacity(moved_at: Instant) -> f32 {
+    let idle_time = moved_at.elapsed().saturating_sub(CURSOR_IDLE_TIME);
+
+    1. - (idle_time.as_secs_f32() / CURSOR_FADE_TIME.as_secs_f32()).min(1.)
+}
+
+/// Returns the shape of an arrow shaped cursor whose tip is at ```tip```.
+fn cursor_arrow(tip: Pos2, color: Color32) -> Shape {
+    Shape::convex_polygon(
+        vec![
+            tip,
+            tip + Vec2::new(0., 16.),
+            tip + Vec2::new(4., 12.),
+            tip + Vec2::new(11., 11.),
+        ],
+        color,
+        Stroke::new(1., Color32::WHITE.gamma_multiply(color.a() as f32 / 255.)),
+    )
+}
+
+impl ApplicationContext {
+    /// Stores the position of the pointer in canvas units, this is what gets sent to the other clients.
+    pub fn update_pointer_canvas_pos(
+        &mut self,
+        ui: &Ui,
+        rect: Rect,
+        to_screen: emath::RectTransform,
+    ) {
+        self.pointer_canvas_pos = ui
+            .ctx()
+            .pointer_latest_pos()
+            .filter(|pos| rect.contains(*pos))
+            .map(|pos| to_screen.inverse() * pos);
+    }
+
+    /// Draws the cursors of the other connected clients with their names, and a preview of the brush they are holding.
+    /// The cursors are placed on the canvas, so they point at the same part of the drawing on every screen.
+    pub fn draw_remote_cursors(&self, ui: &Ui, painter: &Painter, to_screen: emath::RectTransform) {
+        let zoom = canvas_zoom(to_screen);
+
+        for (id, (username, pointer)) in &self.connection.connected_clients {
+            let Some(moved_at) = self.connection.cursor_moved_at.get(id) else {
+                continue;
+            };
+
+            let opacity = cursor_opacity(*moved_at);
+
+            if opacity <= 0. {
+                continue;
+            }
+
+            let pos = to_screen * pointer.pointer_pos;
+
+            if !painter.clip_rect().contains(pos) {
+                continue;
+            }
+
+            let color = client_color(*id).gamma_multiply(opacity);
+            let brush = pointer.brush;
+
+            // The outline of the brush shows how wide the user's line would be
+            if !matches!(brush.brush_type, BrushType::None | BrushType::Text) {
+                painter.circle_stroke(
+                    pos,
+                    brush.width * zoom / 2.,
+                    Stroke::new(1., brush.color.gamma_multiply(opacity)),
+                );
+            }
+
+            painter.add(cursor_arrow(pos, color));
+
+            let text_color = Color32::WHITE.gamma_multiply(opacity);
+            let galley =
+                painter.layout_no_wrap(username.clone(), FontId::proportional(14.), text_color);
+            let label_rect = Rect::from_min_size(pos + Vec2::new(12., 16.), galley.size());
+
+            painter.rect_filled(label_rect.expand(3.), 3., color);
+            painter.galley(label_rect.min, galley, text_color);
+
+            // The cursor has to be redrawn once it starts fading out, and while it's fading out
+            match CURSOR_IDLE_TIME.checked_sub(moved_at.elapsed()) {
+                Some(remaining_time) => ui.ctx().request_repaint_after(remaining_time),
+                None => ui.ctx().request_repaint(),
+            }
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::{fs, path::PathBuf};
+
+use chrono::Local;
+use common_definitions::{read_metadata, FileKind, FileMetadata, ImageFile};
+use drawing_board_renderer::{render_thumbnail, CanvasContent};
+use egui::{Context, ViewportCommand};
+use rfd::{MessageButtons, MessageDialogResult};
+
+use crate::{
+    autosave::write_file_atomically, display_error, Application, ApplicationContext, FileSession,
+    DRAWING_BOARD_IMAGE_EXT, DRAWING_BOARD_WORKSPACE_EXT,
+};
+
+/// The name of the application, displayed in the window's title.
+const APPLICATION_NAME: &str = "Draw";
+
+/// The size of the longer side of the thumbnails saved into the files' header, in pixels.
+const THUMBNAIL_SIZE: u32 = 128;
+
+/// Returns the ```FileDialog``` filter name and the extension of the ```FileKind```.
+fn file_filter(kind: FileKind) -> (&'static str, &'static str) {
+    match kind {
+        FileKind::Image => ("Image File", DRAWING_BOARD_IMAGE_EXT),
+        FileKind::Workspace => ("Project File", DRAWING_BOARD_WORKSPACE_EXT),
+    }
+}
+
+impl ApplicationContext {
+    /// Marks the canvas as modified, so that the user gets asked to save it before it's discarded.
+    pub fn mark_unsaved(&mut self) {
+        self.unsaved_changes = true;
+    }
+
+    /// Creates the metadata header of a file of the ```FileKind```, containing the project's properties and a thumbnail of the canvas.
+    /// The thumbnail is drawn on the canvas' background, and shows its page if the canvas is bounded to one.
+    pub fn file_metadata(&self, kind: FileKind) -> FileMetadata {
+        FileMetadata {
+            properties: self.properties.clone(),
+            thumbnail: render_thumbnail(
+                &CanvasContent {
+                    background: self.background,
+                    background_image: self.background_image.as_ref(),
+                    images: &self.images.values().collect::<Vec<_>>(),
+                    shapes: &self.canvas_shapes(),
+                    lines: &self.layers.visible_lines(&self.lines),
+                    texts: &self.canvas_texts(),
+                },
+                THUMBNAIL_SIZE,
+            )
+            .ok(),
+            ..FileMetadata::new(kind)
+        }
+    }
+
+    /// Serializes the document into a file of the ```FileKind```.
+    fn document_bytes(&self, kind: FileKind) -> anyhow::Result<Vec<u8>> {
+        match kind {
+            FileKind::Image => self
+                .to_image_file()
+                .to_bytes(&self.file_metadata(FileKind::Image)),
+            FileKind::Workspace => self.to_workspace_file(),
+        }
+    }
+
+    /// This function displays the project properties dialog, if it was opened.
+    pub fn properties_dialog(&mut self, ctx: &Context) {
+        let mut dialog_open = self.properties_dialog_open;
+        let mut changed = false;
+
+        egui::Window::new("Project Properties")
+            .open(&mut dialog_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("project_properties")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name");
+                        changed |= ui.text_edit_singleline(&mut self.properties.name).changed();
+                        ui.end_row();
+
+                        ui.label("Author");
+                        changed |= ui
+                            .text_edit_singleline(&mut self.properties.author)
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("Description");
+                        changed |= ui
+                            .text_edit_multiline(&mut self.properties.description)
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("Created");
+                        ui.label(
+                            self.properties
+                                .created
+                                .map(|created| created.to_string())
+                                .unwrap_or_else(|| "Not saved yet".to_string()),
+                        );
+                        ui.end_row();
+
+                        ui.label("Modified");
+                        ui.label(
+                            self.file_session
+                                .as_ref()
+                                .and_then(|session| session.last_saved)
+                                .map(|last_saved| last_saved.format("%Y-%m-%d %H:%M").to_string())
+                                .unwrap_or_else(|| "Not saved yet".to_string()),
+                        );
+                        ui.end_row();
+
+                        ui.label("File");
+                        ui.label(
+                            self.file_session
+                                .as_ref()
+                                .map(|session| session.file_path.display().to_string())
+                                .unwrap_or_else(|| "-".to_string()),
+                        );
+                        ui.end_row();
+
+                        ui.label("Strokes");
+                        ui.label(
+                            self.lines
+                                .iter()
+                                .filter(|line| !line.0.is_empty())
+                                .count()
+                                .to_string(),
+                        );
+                        ui.end_row();
+
+                        ui.label("Images");
+                        ui.label(self.images.len().to_string());
+                        ui.end_row();
+
+                        ui.label("Shapes");
+                        ui.label(self.shapes.len().to_string());
+                        ui.end_row();
+
+                        ui.label("Texts");
+                        ui.label(self.texts.len().to_string());
+                        ui.end_row();
+
+                        ui.label("Layers");
+                        ui.label(self.layers.len().to_string());
+                        ui.end_row();
+                    });
+            });
+
+        if changed {
+            self.mark_unsaved();
+        }
+
+        self.properties_dialog_open &= dialog_open;
+    }
+}
+
+impl Application {
+    /// Saves the document as a file of the ```FileKind```.
+    /// The document is saved into the open file if it's of the same kind and ```save_as``` isn't set, otherwise a ```FileDialog``` is opened.
+    /// Returns whether the document was saved.
+    pub fn save_document(&mut self, kind: FileKind, save_as: bool) -> anyhow::Result<bool> {
+        let session_path = self
+            .context
+            .file_session
+            .as_ref()
+            .filter(|session| session.kind == kind && !save_as)
+            .map(|session| session.file_path.clone());
+
+        let Some(file_path) = session_path.or_else(|| {
+            let (filter_name, extension) = file_filter(kind);
+
+            rfd::FileDialog::new()
+                .add_filter(filter_name, &[extension])
+                .save_file()
+        }) else {
+            return Ok(false);
+        };
+
+        let is_open_file = self
+            .context
+            .file_session
+            .as_ref()
+            .is_some_and(|session| session.file_path == file_path && session.kind == kind);
+
+        // The session and the properties are restored if the file can't be written
+        let previous_session = self.context.file_session.clone();
+        let previous_created = self.context.properties.created;
+
+        // The session is created before serializing, so that saved workspaces point to their own file
+        if !is_open_file {
+            self.create_session(file_path.clone(), kind);
+        }
+
+        let now = Local::now();
+
+        self.context
+            .properties
+            .created
+            .get_or_insert(now.date_naive());
+
+        if let Some(session) = &mut self.context.file_session {
+            session.last_saved = Some(now.naive_local());
+        }
+
+        let result = self
+            .context
+            .document_bytes(kind)
+            .and_then(|bytes| Ok(write_file_atomically(&file_path, &bytes)?));
+
+        if let Err(err) = result {
+            self.context.file_session = previous_session;
+            self.context.properties.created = previous_created;
+
+            return Err(err);
+        }
+
+        self.context.unsaved_changes = false;
+        self.recent_files.add(file_path, kind);
+
+        self.autosave.remove_recovery_file();
+
+        Ok(true)
+    }
+
+    /// Opens a file of the ```FileKind``` with a ```FileDialog```, after the user has confirmed that the unsaved changes can be discarded.
+    pub fn open_document(&mut self, kind: FileKind) -> anyhow::Result<()> {
+        if !self.confirm_discard() {
+            return Ok(());
+        }
+
+        let (filter_name, extension) = file_filter(kind);
+
+        let Some(file_path) = rfd::FileDialog::new()
+            .add_filter(filter_name, &[extension])
+            .pick_file()
+        else {
+            return Ok(());
+        };
+
+        self.open_file(file_path, kind)
+    }
+
+    /// Reads the file at ```file_path``` into the application, without asking the user about the unsaved changes.
+    pub fn open_file(&mut self, file_path: PathBuf, kind: FileKind) -> anyhow::Result<()> {
+        let file = fs::read(&file_path)?;
+        let metadata = read_metadata(&file)?;
+
+        match kind {
+            FileKind::Image => {
+                let image_file = ImageFile::from_bytes(&file)?;
+
+                self.context.lines = image_file.lines;
+                self.context.images = image_file.images;
+                self.context.shapes = image_file.shapes;
+                self.context.texts = image_file.texts;
+                self.context.layers = image_file.layers;
+                self.context.background = image_file.background;
+                self.context
+                    .set_background_image(image_file.background_image);
+                self.context.text_draft = None;
+                self.context.fit_camera_to_content();
+                self.context.properties = metadata
+                    .as_ref()
+                    .map(|metadata| metadata.properties.clone())
+                    .unwrap_or_default();
+            }
+            FileKind::Workspace => {
+                self.context = ApplicationContext::from_workspace_file(&file)?;
+            }
+        }
+
+        self.context.undoer = Default::default();
+        self.create_session(file_path.clone(), kind);
+        self.context.unsaved_changes = false;
+
+        if let Some(session) = &mut self.context.file_session {
+            session.last_saved = metadata.map(|metadata| metadata.saved_at);
+        }
+
+        self.recent_files.add(file_path, kind);
+
+        Ok(())
+    }
+
+    /// Starts a new, empty document, after the user has confirmed that the unsaved changes can be discarded.
+    pub fn new_document(&mut self) {
+        if self.confirm_discard() {
+            self.reset();
+        }
+    }
+
+    /// Asks the user whether the unsaved changes should be saved before the document is discarded.
+    /// Returns whether the document can be discarded, this is ```false``` if the user has cancelled the prompt or the save.
+    pub fn confirm_discard(&mut self) -> bool {
+        if !self.context.unsaved_changes {
+            return true;
+        }
+
+        match rfd::MessageDialog::new()
+            .set_title("Unsaved changes")
+            .set_description(format!(
+                "Do you want to save the changes made to {}?",
+                self.document_name()
+            ))
+            .set_buttons(MessageButtons::YesNoCancel)
+            .show()
+        {
+            MessageDialogResult::Yes => {
+                let kind = self
+                    .context
+                    .file_session
+                    .as_ref()
+                    .map(|session| session.kind)
+                    .unwrap_or_default();
+
+                match self.save_document(kind, false) {
+                    Ok(saved) => saved,
+                    Err(err) => {
+                        display_error(err);
+
+                        false
+                    }
+                }
+            }
+            MessageDialogResult::No => true,
+            _ => false,
+        }
+    }
+
+    /// Cancels closing the window if the user doesn't want to discard the unsaved changes.
+    pub fn handle_close_request(&mut self, ctx: &Context) {
+        if ctx.input(|input| input.viewport().close_requested()) && !self.confirm_discard() {
+            ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+        }
+    }
+
+    /// Updates the window's title to the name of the document, with a ```*``` if it has unsaved changes.
+    pub fn update_window_title(&mut self, ctx: &Context) {
+        let title = format!(
+            "{}{} - {APPLICATION_NAME}",
+            self.document_name(),
+            if self.context.unsaved_changes {
+                "*"
+            } else {
+                ""
+            }
+        );
+
+        if title != self.window_title {
+            ctx.send_viewport_cmd(ViewportCommand::Title(title.clone()));
+
+            self.window_title = title;
+        }
+    }
+
+    /// Returns the name of the open document, this is the project's name if it was set, otherwise the file's name.
+    fn document_name(&self) -> String {
+        if !self.context.properties.name.is_empty() {
+            return self.context.properties.name.clone();
+        }
+
+        self.context
+            .file_session
+            .as_ref()
+            .map(|session| session.project_name.clone())
+            .unwrap_or_else(|| "Untitled".to_string())
+    }
+
+    /// This function creates a new ```FileSession``` if a file is saved as or opened.
+    fn create_session(&mut self, file_path: PathBuf, kind: FileKind) {
+        let project_name = file_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        self.context.file_session =
+            Some(FileSession::create_session(file_path, project_name, kind));
+    }
+}
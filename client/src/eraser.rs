@@ -0,0 +1,359 @@
+use common_definitions::{LinePos, MessageType};
+use egui::{emath, Pos2, Ui};
+
+use crate::{
+    camera::canvas_zoom, shapes::is_shape_hit, smoothing::distance_to_segment, ApplicationContext,
+};
+
+/// The ways the ```BrushType::Eraser``` can remove the lines it touches.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum EraserMode {
+    /// Every line the eraser touches is removed as a whole.
+    #[default]
+    Stroke,
+    /// Only the parts of the lines under the eraser are removed, the lines are split at the erased region.
+    Partial,
+}
+
+impl EraserMode {
+    /// Every ```EraserMode```, with the name it's displayed with.
+    const ALL: [(Self, &'static str); 2] = [(Self::Stroke, "Stroke"), (Self::Partial, "Partial")];
+
+    /// Returns the name the ```EraserMode``` is displayed with.
+    fn name(&self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(mode, _)| mode == self)
+            .map(|(_, name)| *name)
+            .unwrap_or_default()
+    }
+
+    /// Displays the ```EraserMode``` selector.
+    pub fn settings_ui(&mut self, ui: &mut Ui) {
+        ui.label("Eraser mode");
+        egui::ComboBox::from_id_salt("eraser_mode")
+            .selected_text(self.name())
+            .show_ui(ui, |ui| {
+                for (mode, name) in Self::ALL {
+                    ui.selectable_value(self, mode, name);
+                }
+            });
+    }
+}
+
+/// Returns whether the line segments ```a``` and ```b``` cross each other.
+fn segments_intersect(a: (Pos2, Pos2), b: (Pos2, Pos2)) -> bool {
+    let cross = |origin: Pos2, end: Pos2, point: Pos2| {
+        let (end, point) = (end - origin, point - origin);
+
+        end.x * point.y - end.y * point.x
+    };
+
+    cross(b.0, b.1, a.0) * cross(b.0, b.1, a.1) < 0.
+        && cross(a.0, a.1, b.0) * cross(a.0, a.1, b.1) < 0.
+}
+
+/// Returns the distance between the line segments ```a``` and ```b```.
+pub(crate) fn segments_distance(a: (Pos2, Pos2), b: (Pos2, Pos2)) -> f32 {
+    if segments_intersect(a, b) {
+        return 0.;
+    }
+
+    [
+        distance_to_segment(a.0, b.0, b.1),
+        distance_to_segment(a.1, b.0, b.1),
+        distance_to_segment(b.0, a.0, a.1),
+        distance_to_segment(b.1, a.0, a.1),
+    ]
+    .into_iter()
+    .fold(f32::INFINITY, f32::min)
+}
+
+/// Returns whether the eraser, which was moved along ```eraser```, touches the line.
+/// The distance is checked against every segment of the line, the segments are as wide as the line was at their ends.
+/// ```width``` is the width of the line's brush, which gets scaled by the zoom of the canvas.
+pub(crate) fn is_line_hit(
+    points: &[LinePos],
+    width: f32,
+    eraser: (Pos2, Pos2),
+    eraser_radius: f32,
+    to_screen: emath::RectTransform,
+) -> bool {
+    let screen_point = |pos: &LinePos| to_screen * Pos2::from(*pos);
+    let width = width * canvas_zoom(to_screen);
+
+    match points {
+        [] => false,
+        [point] => {
+            distance_to_segment(screen_point(point), eraser.0, eraser.1)
+                <= eraser_radius + width * point.pressure() / 2.
+        }
+        points => points.windows(2).any(|segment| {
+            let half_width = width * segment[0].pressure().max(segment[1].pressure()) / 2.;
+
+            segments_distance(
+                (screen_point(&segment[0]), screen_point(&segment[1])),
+                eraser,
+            ) <= eraser_radius + half_width
+        }),
+    }
+}
+
+/// Inserts points into the line so that none of its segments are longer than ```max_length``` on the screen.
+/// The pressure of the inserted points is interpolated between the segment's ends.
+fn resample_line(
+    points: &[LinePos],
+    max_length: f32,
+    to_screen: emath::RectTransform,
+) -> Vec<LinePos> {
+    let mut resampled_points = Vec::with_capacity(points.len());
+
+    for segment in points.windows(2) {
+        let (start, end) = (Pos2::from(segment[0]), Pos2::from(segment[1]));
+        let screen_length = (to_screen * start).distance(to_screen * end);
+        let steps = (screen_length / max_length).ceil().max(1.) as usize;
+
+        resampled_points.push(segment[0]);
+
+        for step in 1..steps {
+            let t = step as f32 / steps as f32;
+
+            resampled_points.push(LinePos::with_pressure(
+                start.lerp(end, t),
+                emath::lerp(segment[0].pressure()..=segment[1].pressure(), t),
+            ));
+        }
+    }
+
+    resampled_points.extend(points.last());
+
+    resampled_points
+}
+
+/// Removes the parts of the line which are under the eraser, and returns the pieces which are left.
+/// Pieces shorter than two points are dropped, as they couldn't be drawn.
+/// Returns ```None``` if the eraser didn't remove any point of the line.
+fn split_line(
+    points: &[LinePos],
+    eraser: (Pos2, Pos2),
+    eraser_radius: f32,
+    to_screen: emath::RectTransform,
+) -> Option<Vec<Vec<LinePos>>> {
+    // The line is resampled so that the eraser cuts it close to its edge
+    let points = resample_line(points, (eraser_radius / 2.).max(0.5), to_screen);
+
+    let mut pieces = vec![];
+    let mut current_piece = vec![];
+    let mut erased_any = false;
+
+    for point in points {
+        let distance = distance_to_segment(to_screen * Pos2::from(point), eraser.0, eraser.1);
+
+        if distance <= eraser_radius {
+            erased_any = true;
+
+            pieces.push(std::mem::take(&mut current_piece));
+        } else {
+            current_piece.push(point);
+        }
+    }
+
+    pieces.push(current_piece);
+
+    erased_any.then(|| {
+        pieces
+            .into_iter()
+            .filter(|piece| piece.len() >= 2)
+            .collect()
+    })
+}
+
+impl ApplicationContext {
+    /// Erases the lines and the shapes the eraser touched while it was moved from ```eraser_start``` to ```eraser_end``` (Both are in points).
+    /// The erased lines are deleted on the server, and the pieces left of them (In ```EraserMode::Partial```) are added as new lines.
+    /// Shapes are always erased as a whole.
+    /// Returns whether anything was erased.
+    pub fn erase(
+        &mut self,
+        eraser_start: Pos2,
+        eraser_end: Pos2,
+        to_screen: emath::RectTransform,
+    ) -> bool {
+        let eraser = (eraser_start, eraser_end);
+        let eraser_radius = self.paintbrush.get_current_brush().width / 2.;
+
+        let mut messages = vec![];
+        let mut line_idx = 0;
+
+        while line_idx < self.lines.len() {
+            let (points, brush) = &self.lines[line_idx];
+
+            // The last, empty line is the one being drawn
+            if points.is_empty()
+                || !self.layers.is_editable(brush.layer)
+                || !is_line_hit(points, brush.width, eraser, eraser_radius, to_screen)
+            {
+                line_idx += 1;
+
+                continue;
+            }
+
+            let pieces = match self.paintbrush.eraser_mode {
+                EraserMode::Stroke => vec![],
+                EraserMode::Partial => match split_line(points, eraser, eraser_radius, to_screen) {
+                    Some(pieces) => pieces,
+                    None => {
+                        line_idx += 1;
+
+                        continue;
+                    }
+                },
+            };
+
+            let (points, brush) = self.lines.remove(line_idx);
+
+            messages.push(MessageType::ModifyLine((points, None)));
+
+            //Insert the pieces in place of the line, so that the order of the lines stays the same
+            for piece in pieces {
+                messages.push(MessageType::AddLine((piece.clone(), brush)));
+
+                self.lines.insert(line_idx, (piece, brush));
+
+                line_idx += 1;
+            }
+        }
+
+        let erased_shapes: Vec<_> = self
+            .shapes
+            .iter()
+            .filter(|(_, shape)| {
+                self.layers.is_editable(shape.layer)
+                    && is_shape_hit(shape, eraser, eraser_radius, to_screen)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in erased_shapes {
+            self.shapes.shift_remove(&id);
+
+            messages.push(MessageType::ModifyShape((id, None)));
+        }
+
+        if messages.is_empty() {
+            return false;
+        }
+
+        self.send_messages(messages);
+
+        self.add_undo_point();
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{vec2, Rect};
+
+    use super::*;
+
+    fn line(points: &[(f32, f32)]) -> Vec<LinePos> {
+        points
+            .iter()
+            .map(|(x, y)| Pos2::new(*x, *y).into())
+            .collect()
+    }
+
+    /// A transformation which doesn't scale or move the canvas.
+    fn identity() -> emath::RectTransform {
+        emath::RectTransform::identity(Rect::from_min_size(Pos2::ZERO, vec2(100., 100.)))
+    }
+
+    #[test]
+    fn crossing_segments_touch() {
+        let a = (Pos2::new(0., 0.), Pos2::new(10., 10.));
+        let b = (Pos2::new(0., 10.), Pos2::new(10., 0.));
+
+        assert_eq!(segments_distance(a, b), 0.);
+    }
+
+    #[test]
+    fn distance_between_separate_segments() {
+        // Parallel segments
+        let a = (Pos2::new(0., 0.), Pos2::new(10., 0.));
+        let b = (Pos2::new(0., 3.), Pos2::new(10., 3.));
+
+        assert_eq!(segments_distance(a, b), 3.);
+
+        // The closest points are the segments' ends
+        let c = (Pos2::new(13., 4.), Pos2::new(20., 4.));
+
+        assert_eq!(segments_distance(a, c), 5.);
+        assert_eq!(segments_distance(c, a), 5.);
+    }
+
+    #[test]
+    fn resampled_segments_are_short() {
+        let points = vec![
+            LinePos::with_pressure(Pos2::new(0., 0.), 0.),
+            LinePos::with_pressure(Pos2::new(10., 0.), 1.),
+        ];
+
+        let resampled = resample_line(&points, 2., identity());
+
+        assert_eq!(resampled.len(), 6);
+        assert_eq!(resampled.first(), points.first());
+        assert_eq!(resampled.last(), points.last());
+
+        for segment in resampled.windows(2) {
+            assert!(Pos2::from(segment[0]).distance(segment[1].into()) <= 2. + f32::EPSILON);
+        }
+
+        // The pressure is interpolated between the ends
+        assert!((resampled[1].pressure() - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn resampling_keeps_short_segments() {
+        let points = line(&[(0., 0.), (1., 0.), (1., 1.)]);
+
+        assert_eq!(resample_line(&points, 2., identity()), points);
+    }
+
+    #[test]
+    fn line_is_split_under_the_eraser() {
+        let points = line(&[(0., 50.), (100., 50.)]);
+        let eraser = (Pos2::new(50., 0.), Pos2::new(50., 100.));
+
+        let pieces = split_line(&points, eraser, 5., identity()).unwrap();
+
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].first(), points.first());
+        assert_eq!(pieces[1].last(), points.last());
+
+        for point in pieces.iter().flatten() {
+            assert!((Pos2::from(*point).x - 50.).abs() > 5.);
+        }
+
+        // The line is resampled to half of the eraser's radius, so it's cut within that distance of the eraser's edge
+        assert!(Pos2::from(*pieces[0].last().unwrap()).x >= 42.5);
+        assert!(Pos2::from(*pieces[1].first().unwrap()).x <= 57.5);
+    }
+
+    #[test]
+    fn untouched_line_is_not_split() {
+        let points = line(&[(0., 50.), (100., 50.)]);
+        let eraser = (Pos2::new(0., 0.), Pos2::new(100., 0.));
+
+        assert!(split_line(&points, eraser, 5., identity()).is_none());
+    }
+
+    #[test]
+    fn fully_erased_line_leaves_no_pieces() {
+        let points = line(&[(0., 50.), (10., 50.)]);
+        let eraser = (Pos2::new(0., 50.), Pos2::new(10., 50.));
+
+        assert_eq!(split_line(&points, eraser, 5., identity()), Some(vec![]));
+    }
+}
@@ -0,0 +1,365 @@
+use std::fs;
+
+use common_definitions::{BackgroundStyle, CanvasBackground, CanvasImage, ExportFormat};
+use drawing_board_renderer::{
+    pdf_page_count, render_pdf, render_png, render_svg, CanvasContent, CanvasFrame, PageLayout,
+    PdfOptions, RenderArea, RenderOptions, A4_PAGE_SIZE, LETTER_PAGE_SIZE, MAX_PDF_PAGES,
+};
+use egui::{vec2, Color32, Context, DragValue, Pos2, Rect, Ui, Vec2};
+
+use crate::{display_error, ApplicationContext};
+
+/// The area of the canvas which gets exported.
+#[derive(serde::Serialize, serde::Deserialize, Default, PartialEq, Clone, Copy, Debug)]
+pub enum ExportCrop {
+    /// The canvas' page if it is bounded to one, otherwise the area covered by the drawing.
+    #[default]
+    Canvas,
+    /// The area covered by the drawing.
+    DrawingBounds,
+    /// The area of the canvas which is visible in the Canvas tab.
+    VisibleArea,
+    /// A fixed size page, starting at the canvas' origin.
+    Page,
+}
+
+/// The background the canvas is exported with.
+#[derive(serde::Serialize, serde::Deserialize, Default, PartialEq, Clone, Copy, Debug)]
+pub enum ExportBackground {
+    /// The canvas' background, with its paper lines or image, just like in the Canvas tab.
+    #[default]
+    Canvas,
+    /// A single color, which replaces the canvas' background.
+    Color,
+    /// No background at all.
+    Transparent,
+}
+
+/// This struct contains the settings the canvas is exported with.
+/// These settings are independent of the window's size, so that the exported image always looks the same.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct ExportSettings {
+    /// The format the canvas is exported into.
+    pub format: ExportFormat,
+    /// The number of output pixels one point takes up.
+    pub scale: f32,
+    /// The background of the exported image.
+    pub background: ExportBackground,
+    /// The background color of the exported image, this is only used with ```ExportBackground::Color```.
+    pub background_color: Color32,
+    /// The area of the canvas which gets exported.
+    pub crop: ExportCrop,
+    /// The size of the page in points, this is used when exporting with ```ExportCrop::Page``` or into a pdf document.
+    pub page_size: Vec2,
+    /// The empty space left on the edges of the pdf document's pages in points.
+    pub page_margin: f32,
+    /// Whether the drawing is split across multiple pages of the pdf document, instead of being scaled to fit onto a single one.
+    pub tile_pages: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Png,
+            scale: 2.,
+            background: ExportBackground::default(),
+            background_color: Color32::WHITE,
+            crop: ExportCrop::default(),
+            page_size: A4_PAGE_SIZE,
+            page_margin: PdfOptions::default().margin,
+            tile_pages: false,
+        }
+    }
+}
+
+impl ExportSettings {
+    /// Returns the contents of the canvas which get exported.
+    /// The canvas' background is only kept when exporting with ```ExportBackground::Canvas```, but its page is always kept for ```ExportCrop::Canvas```.
+    pub fn export_content<'a>(&self, content: &CanvasContent<'a>) -> CanvasContent<'a> {
+        match self.background {
+            ExportBackground::Canvas => *content,
+            ExportBackground::Color | ExportBackground::Transparent => CanvasContent {
+                background: CanvasBackground {
+                    style: BackgroundStyle::Transparent,
+                    ..content.background
+                },
+                background_image: None,
+                ..*content
+            },
+        }
+    }
+
+    /// Creates the ```RenderOptions``` the ```content``` should be rendered with.
+    /// ```visible_area``` is the area of the canvas (In canvas units) which is visible in the Canvas tab.
+    pub fn render_options(
+        &self,
+        content: &CanvasContent,
+        visible_area: Option<Rect>,
+    ) -> RenderOptions {
+        let default_options = RenderOptions::default();
+
+        let area = match self.crop {
+            ExportCrop::Canvas => content
+                .background
+                .page_rect()
+                .map_or(RenderArea::DrawingBounds, RenderArea::Canvas),
+            ExportCrop::DrawingBounds => RenderArea::DrawingBounds,
+            ExportCrop::VisibleArea => visible_area
+                .map(RenderArea::Canvas)
+                .unwrap_or(RenderArea::DrawingBounds),
+            ExportCrop::Page => RenderArea::Canvas(Rect::from_min_size(
+                Pos2::ZERO,
+                self.page_size / default_options.canvas_scale,
+            )),
+        };
+
+        RenderOptions {
+            scale: self.scale,
+            background: (self.background == ExportBackground::Color)
+                .then_some(self.background_color),
+            area,
+            ..default_options
+        }
+    }
+
+    /// Creates the ```PdfOptions``` the pdf document should be rendered with.
+    pub fn pdf_options(&self) -> PdfOptions {
+        PdfOptions {
+            page_size: self.page_size,
+            margin: self.page_margin,
+            layout: if self.tile_pages {
+                PageLayout::Tile
+            } else {
+                PageLayout::Fit
+            },
+        }
+    }
+}
+
+/// Displays the page size and orientation settings.
+pub(crate) fn page_size_ui(ui: &mut Ui, page_size: &mut Vec2) {
+    ui.horizontal(|ui| {
+        ui.label("Page size");
+        ui.add(DragValue::new(&mut page_size.x).range(1.0..=10000.0));
+        ui.label("x");
+        ui.add(DragValue::new(&mut page_size.y).range(1.0..=10000.0));
+        ui.label("pt");
+    });
+
+    ui.horizontal(|ui| {
+        let mut landscape = page_size.x > page_size.y;
+
+        if ui.button("A4").clicked() {
+            *page_size = A4_PAGE_SIZE;
+        }
+        if ui.button("Letter").clicked() {
+            *page_size = LETTER_PAGE_SIZE;
+        }
+
+        ui.separator();
+
+        ui.selectable_value(&mut landscape, false, "Portrait");
+        ui.selectable_value(&mut landscape, true, "Landscape");
+
+        // The orientation was changed
+        if landscape != (page_size.x > page_size.y) {
+            *page_size = vec2(page_size.y, page_size.x);
+        }
+    });
+}
+
+/// Exports the contents of the canvas into the format specified in the ```ExportSettings```, returning the bytes of the exported file.
+/// ```visible_area``` is the area of the canvas (In canvas units) which is visible in the Canvas tab, it is only used when exporting with ```ExportCrop::VisibleArea```.
+pub fn export_canvas(
+    content: &CanvasContent,
+    settings: &ExportSettings,
+    visible_area: Option<Rect>,
+) -> anyhow::Result<Vec<u8>> {
+    let content = settings.export_content(content);
+    let render_options = settings.render_options(&content, visible_area);
+
+    Ok(match settings.format {
+        ExportFormat::Png => render_png(&content, &render_options)?,
+        ExportFormat::Svg => render_svg(&content, &render_options).into_bytes(),
+        ExportFormat::Pdf => render_pdf(&content, &render_options, &settings.pdf_options())?,
+    })
+}
+
+/// Exports the contents of the canvas into an svg document.
+/// Every ```BrushType``` is mapped to the svg element which looks like the line drawn on the canvas.
+pub fn export_svg(content: &CanvasContent, settings: &ExportSettings) -> String {
+    let content = settings.export_content(content);
+
+    render_svg(&content, &settings.render_options(&content, None))
+}
+
+impl ApplicationContext {
+    /// This function displays the export dialog, if it was opened.
+    /// The canvas is rendered offscreen, so the exported image doesn't depend on the window's size or contain any cursors.
+    pub fn export_dialog(&mut self, ctx: &Context) {
+        let mut dialog_open = self.export_dialog_open;
+
+        egui::Window::new("Export")
+            .open(&mut dialog_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let lines = self.layers.visible_lines(&self.lines);
+                let images: Vec<&CanvasImage> = self.images.values().collect();
+                let shapes = self.canvas_shapes();
+                let texts = self.canvas_texts();
+
+                let content = CanvasContent {
+                    background: self.background,
+                    background_image: self.background_image.as_ref(),
+                    images: &images,
+                    shapes: &shapes,
+                    lines: &lines,
+                    texts: &texts,
+                };
+                let settings = &mut self.export_settings;
+
+                ui.horizontal(|ui| {
+                    ui.label("Format");
+                    ui.selectable_value(&mut settings.format, ExportFormat::Png, "Png");
+                    ui.selectable_value(&mut settings.format, ExportFormat::Svg, "Svg");
+                    ui.selectable_value(&mut settings.format, ExportFormat::Pdf, "Pdf");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Scale");
+                    ui.add(egui::Slider::new(&mut settings.scale, 0.25..=8.0).suffix("x"));
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Background");
+                    ui.selectable_value(
+                        &mut settings.background,
+                        ExportBackground::Canvas,
+                        "Canvas",
+                    );
+                    ui.selectable_value(&mut settings.background, ExportBackground::Color, "Color");
+                    ui.selectable_value(
+                        &mut settings.background,
+                        ExportBackground::Transparent,
+                        "Transparent",
+                    );
+                    ui.add_enabled_ui(settings.background == ExportBackground::Color, |ui| {
+                        ui.color_edit_button_srgba(&mut settings.background_color);
+                    });
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Area");
+                    ui.selectable_value(&mut settings.crop, ExportCrop::Canvas, "Canvas")
+                        .on_hover_text(
+                            "The canvas' page if it is bounded to one, otherwise the drawing.",
+                        );
+                    ui.selectable_value(&mut settings.crop, ExportCrop::DrawingBounds, "Drawing");
+                    ui.selectable_value(
+                        &mut settings.crop,
+                        ExportCrop::VisibleArea,
+                        "Visible area",
+                    );
+                    ui.selectable_value(&mut settings.crop, ExportCrop::Page, "Page");
+                });
+
+                if settings.crop == ExportCrop::Page || settings.format == ExportFormat::Pdf {
+                    page_size_ui(ui, &mut settings.page_size);
+                }
+
+                if settings.format == ExportFormat::Pdf {
+                    ui.horizontal(|ui| {
+                        ui.label("Margin");
+                        ui.add(
+                            DragValue::new(&mut settings.page_margin)
+                                .range(0.0..=200.0)
+                                .suffix("pt"),
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Layout");
+                        ui.selectable_value(&mut settings.tile_pages, false, "Fit to page");
+                        ui.selectable_value(&mut settings.tile_pages, true, "Tile across pages");
+                    });
+                }
+
+                ui.separator();
+
+                let summary_key = (*settings, self.visible_canvas_area);
+
+                let summary = match &self.export_summary {
+                    Some((summary_settings, summary_area, summary))
+                        if (*summary_settings, *summary_area) == summary_key =>
+                    {
+                        summary.clone()
+                    }
+                    _ => {
+                        let export_content = settings.export_content(&content);
+                        let render_options =
+                            settings.render_options(&export_content, self.visible_canvas_area);
+
+                        let summary = if settings.format == ExportFormat::Pdf {
+                            let page_count = pdf_page_count(
+                                &export_content,
+                                &render_options,
+                                &settings.pdf_options(),
+                            );
+
+                            if page_count > MAX_PDF_PAGES {
+                                format!("Pages: {page_count} (The limit is {MAX_PDF_PAGES} pages)")
+                            } else {
+                                format!("Pages: {page_count}")
+                            }
+                        } else {
+                            let output_size =
+                                CanvasFrame::new(&export_content, &render_options).size();
+
+                            format!("Output size: {} x {} px", output_size.x, output_size.y)
+                        };
+
+                        self.export_summary = Some((summary_key.0, summary_key.1, summary.clone()));
+
+                        summary
+                    }
+                };
+
+                ui.label(summary);
+
+                if ui.button("Export").clicked() {
+                    let (filter_name, extension) = match settings.format {
+                        ExportFormat::Png => ("Image", "png"),
+                        ExportFormat::Svg => ("Vector image", "svg"),
+                        ExportFormat::Pdf => ("Document", "pdf"),
+                    };
+
+                    if let Some(save_path) = rfd::FileDialog::new()
+                        .add_filter(filter_name, &[extension])
+                        .save_file()
+                    {
+                        match export_canvas(&content, settings, self.visible_canvas_area) {
+                            Ok(exported_file) => {
+                                if let Err(err) = fs::write(save_path, exported_file) {
+                                    display_error(err);
+                                }
+                            }
+                            Err(err) => display_error(err),
+                        }
+
+                        self.export_dialog_open = false;
+                    }
+                }
+            });
+
+        self.export_dialog_open &= dialog_open;
+
+        // The drawing might change while the dialog is closed
+        if !self.export_dialog_open {
+            self.export_summary = None;
+        }
+    }
+}
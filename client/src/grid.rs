@@ -0,0 +1,474 @@
+use common_definitions::{BrushType, DEFAULT_CANVAS_SCALE};
+use egui::{emath, Align2, FontId, Painter, Pos2, Rect, Stroke, Ui, Vec2};
+
+use crate::{camera::canvas_zoom, ApplicationContext};
+
+/// The smallest distance (In points) between two displayed grid lines, the grid gets sparser when the canvas is zoomed out.
+const MIN_GRID_SCREEN_SPACING: f32 = 8.;
+
+/// The largest distance (In points) a point can be snapped to another line's endpoint from.
+const ENDPOINT_SNAP_DISTANCE: f32 = 10.;
+
+/// The width of the rulers (In points) along the edges of the Canvas tab.
+const RULER_SIZE: f32 = 24.;
+
+/// The smallest distance (In points) between two labeled ticks of the rulers.
+const MIN_RULER_LABEL_SPACING: f32 = 60.;
+
+/// The ways the grid can be displayed.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum GridStyle {
+    /// A dot is displayed at every crossing of the grid.
+    #[default]
+    Dots,
+    /// The grid is displayed as horizontal and vertical lines.
+    Lines,
+}
+
+impl GridStyle {
+    /// Every ```GridStyle```, with the name it's displayed with.
+    const ALL: [(Self, &'static str); 2] = [(Self::Dots, "Dots"), (Self::Lines, "Lines")];
+}
+
+/// The settings of the alignment aids displayed on the canvas.
+/// The grid and the rulers are only displayed in the Canvas tab, they aren't part of the exported images.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct GridSettings {
+    /// Whether the grid is displayed.
+    pub visible: bool,
+    /// The way the grid is displayed.
+    pub style: GridStyle,
+    /// The distance between two grid lines, in points at the default zoom (The same unit the brushes' width is in).
+    pub spacing: f32,
+    /// Whether the rulers are displayed along the top and the left edge of the Canvas tab.
+    pub rulers: bool,
+    /// Whether the points of the lines and the shapes are snapped to the grid.
+    pub snap_to_grid: bool,
+    /// Whether the points of the lines and the shapes are snapped to the nearby endpoints of other lines.
+    pub snap_to_endpoints: bool,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            style: GridStyle::default(),
+            spacing: 32.,
+            rulers: false,
+            snap_to_grid: false,
+            snap_to_endpoints: false,
+        }
+    }
+}
+
+impl GridSettings {
+    /// Returns the distance (In canvas units) between two displayed grid lines at the zoom of the canvas.
+    /// The spacing is doubled until the grid lines are far enough apart on the screen.
+    fn grid_step(&self, to_screen: emath::RectTransform) -> f32 {
+        let zoom = canvas_zoom(to_screen);
+        let mut spacing = self.spacing.max(1.);
+
+        while spacing * zoom < MIN_GRID_SCREEN_SPACING {
+            spacing *= 2.;
+        }
+
+        spacing / DEFAULT_CANVAS_SCALE
+    }
+
+    /// Displays the grid and snapping settings.
+    pub fn settings_ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.visible, "Show Grid");
+
+        ui.add_enabled_ui(self.visible, |ui| {
+            ui.horizontal(|ui| {
+                for (style, name) in GridStyle::ALL {
+                    ui.radio_value(&mut self.style, style, name);
+                }
+            });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Grid spacing");
+            ui.add(
+                egui::DragValue::new(&mut self.spacing)
+                    .range(4.0..=512.0)
+                    .suffix(" pt"),
+            );
+        });
+
+        ui.checkbox(&mut self.rulers, "Show Rulers");
+
+        ui.separator();
+
+        ui.checkbox(&mut self.snap_to_grid, "Snap To Grid");
+        ui.checkbox(&mut self.snap_to_endpoints, "Snap To Line Endpoints")
+            .on_hover_text("Snaps the ends of the lines and the points of the shapes to the nearby ends of other lines.");
+    }
+}
+
+/// Returns the positions (In canvas units) of the multiples of ```step``` in ```range```.
+pub(crate) fn steps_in_range(range: emath::Rangef, step: f32) -> impl Iterator<Item = f32> {
+    let first = (range.min / step).ceil() as i64;
+    let last = (range.max / step).floor() as i64;
+
+    (first..=last).map(move |idx| idx as f32 * step)
+}
+
+/// Returns the distance (In points at the default zoom) between two labeled ticks of the rulers.
+/// The distance is one, two or five times a power of ten, so that the labels are round numbers.
+fn ruler_step(zoom: f32) -> f32 {
+    let min_step = MIN_RULER_LABEL_SPACING / zoom;
+    let magnitude = 10f32.powf(min_step.log10().floor());
+
+    [1., 2., 5., 10.]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= min_step)
+        .unwrap_or(10. * magnitude)
+}
+
+impl ApplicationContext {
+    /// Returns the endpoints (In canvas units) of the visible lines, which the points can be snapped to.
+    /// The line being drawn is skipped, so that it doesn't snap onto itself.
+    fn line_endpoints(&self) -> impl Iterator<Item = Pos2> + '_ {
+        // The last line is the one being drawn with the line brushes
+        let drawn_lines = match self.paintbrush.brush_type {
+            BrushType::Graffiti | BrushType::Pencil | BrushType::Marker | BrushType::Stamp => {
+                &self.lines[..self.lines.len().saturating_sub(1)]
+            }
+            _ => &self.lines[..],
+        };
+
+        self.layers
+            .layered_lines(drawn_lines)
+            .flat_map(|(points, _)| {
+                points
+                    .first()
+                    .into_iter()
+                    .chain(points.last())
+                    .map(|pos| Pos2::from(*pos))
+            })
+    }
+
+    /// Snaps the position (In canvas units) to the nearest line endpoint or the grid, depending on the ```GridSettings```.
+    /// Endpoints are preferred, but only the ones which are close to the position on the screen are considered.
+    pub fn snap_point(&self, pos: Pos2, to_screen: emath::RectTransform) -> Pos2 {
+        if self.grid.snap_to_endpoints {
+            let screen_pos = to_screen * pos;
+
+            let endpoint = self
+                .line_endpoints()
+                .map(|endpoint| (endpoint, (to_screen * endpoint).distance(screen_pos)))
+                .filter(|(_, distance)| *distance <= ENDPOINT_SNAP_DISTANCE)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((endpoint, _)) = endpoint {
+                return endpoint;
+            }
+        }
+
+        // The grid's spacing is snapped to instead of the displayed grid lines, so that snapping doesn't depend on the zoom
+        if self.grid.snap_to_grid {
+            let step = self.grid.spacing.max(1.) / DEFAULT_CANVAS_SCALE;
+
+            return (pos / step).round() * step;
+        }
+
+        pos
+    }
+
+    /// Draws a ring around the position the pointer would be snapped to, if it's snapped.
+    pub fn draw_snap_indicator(&self, ui: &Ui, painter: &Painter, to_screen: emath::RectTransform) {
+        let Some(pos) = self.pointer_canvas_pos else {
+            return;
+        };
+
+        let snapped_pos = self.snap_point(pos, to_screen);
+
+        if snapped_pos != pos {
+            painter.circle_stroke(
+                to_screen * snapped_pos,
+                4.,
+                Stroke::new(1.5, ui.visuals().selection.stroke.color),
+            );
+        }
+    }
+
+    /// Draws the grid below everything placed on the canvas.
+    pub fn draw_grid(&self, ui: &Ui, painter: &Painter, to_screen: emath::RectTransform) {
+        if !self.grid.visible {
+            return;
+        }
+
+        let step = self.grid.grid_step(to_screen);
+        let visible_area = *to_screen.from();
+        let color = ui.visuals().weak_text_color().gamma_multiply(0.5);
+
+        match self.grid.style {
+            GridStyle::Lines => {
+                let stroke = Stroke::new(1., color);
+
+                for x in steps_in_range(visible_area.x_range(), step) {
+                    let x = (to_screen * Pos2::new(x, 0.)).x;
+
+                    painter.vline(x, to_screen.to().y_range(), stroke);
+                }
+
+                for y in steps_in_range(visible_area.y_range(), step) {
+                    let y = (to_screen * Pos2::new(0., y)).y;
+
+                    painter.hline(to_screen.to().x_range(), y, stroke);
+                }
+            }
+            GridStyle::Dots => {
+                for x in steps_in_range(visible_area.x_range(), step) {
+                    for y in steps_in_range(visible_area.y_range(), step) {
+                        painter.circle_filled(to_screen * Pos2::new(x, y), 1.5, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws the rulers along the top and the left edge of the Canvas tab, on the top of everything.
+    /// The rulers are labeled in points at the default zoom, the unit the exported images are measured in.
+    pub fn draw_rulers(&self, ui: &Ui, painter: &Painter, to_screen: emath::RectTransform) {
+        if !self.grid.rulers {
+            return;
+        }
+
+        let rect = *to_screen.to();
+        let visuals = ui.visuals();
+        let zoom = canvas_zoom(to_screen);
+        let step = ruler_step(zoom) / DEFAULT_CANVAS_SCALE;
+        let tick_stroke = Stroke::new(1., visuals.text_color());
+        let font_id = FontId::monospace(9.);
+
+        let top_ruler =
+            Rect::from_min_max(rect.min, Pos2::new(rect.max.x, rect.min.y + RULER_SIZE));
+        let left_ruler =
+            Rect::from_min_max(rect.min, Pos2::new(rect.min.x + RULER_SIZE, rect.max.y));
+
+        painter.rect_filled(top_ruler, 0., visuals.extreme_bg_color);
+        painter.rect_filled(left_ruler, 0., visuals.extreme_bg_color);
+
+        let visible_area = *to_screen.from();
+
+        // Every labeled tick is followed by four smaller ticks
+        for x in steps_in_range(visible_area.x_range(), step / 5.) {
+            let screen_x = (to_screen * Pos2::new(x, 0.)).x;
+            let is_labeled = ((x / step).round() * step - x).abs() < step / 10.;
+            let tick_length = if is_labeled {
+                RULER_SIZE
+            } else {
+                RULER_SIZE / 4.
+            };
+
+            painter.vline(
+                screen_x,
+                emath::Rangef::new(top_ruler.max.y - tick_length, top_ruler.max.y),
+                tick_stroke,
+            );
+
+            if is_labeled {
+                painter.text(
+                    Pos2::new(screen_x + 2., top_ruler.min.y),
+                    Align2::LEFT_TOP,
+                    format!("{:.0}", x * DEFAULT_CANVAS_SCALE),
+                    font_id.clone(),
+                    visuals.text_color(),
+                );
+            }
+        }
+
+        for y in steps_in_range(visible_area.y_range(), step / 5.) {
+            let screen_y = (to_screen * Pos2::new(0., y)).y;
+            let is_labeled = ((y / step).round() * step - y).abs() < step / 10.;
+            let tick_length = if is_labeled {
+                RULER_SIZE
+            } else {
+                RULER_SIZE / 4.
+            };
+
+            painter.hline(
+                emath::Rangef::new(left_ruler.max.x - tick_length, left_ruler.max.x),
+                screen_y,
+                tick_stroke,
+            );
+
+            if is_labeled {
+                painter.text(
+                    Pos2::new(left_ruler.min.x, screen_y + 2.),
+                    Align2::LEFT_TOP,
+                    format!("{:.0}", y * DEFAULT_CANVAS_SCALE),
+                    font_id.clone(),
+                    visuals.text_color(),
+                );
+            }
+        }
+
+        // The pointer's position is marked on both rulers
+        if let Some(pos) = self.pointer_canvas_pos {
+            let screen_pos = to_screen * pos;
+            let marker_stroke = Stroke::new(1., visuals.selection.stroke.color);
+
+            painter.vline(screen_pos.x, top_ruler.y_range(), marker_stroke);
+            painter.hline(left_ruler.x_range(), screen_pos.y, marker_stroke);
+        }
+
+        // The corner where the rulers meet covers their ends
+        painter.rect_filled(
+            Rect::from_min_size(rect.min, Vec2::splat(RULER_SIZE)),
+            0.,
+            visuals.extreme_bg_color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_definitions::{Brush, Layer, LinePos, Uuid};
+    use egui::vec2;
+
+    use super::*;
+
+    /// The transformation of the canvas at the default zoom.
+    fn default_zoom() -> emath::RectTransform {
+        emath::RectTransform::from_to(
+            Rect::from_min_size(Pos2::ZERO, Vec2::splat(1.)),
+            Rect::from_min_size(Pos2::ZERO, Vec2::splat(DEFAULT_CANVAS_SCALE)),
+        )
+    }
+
+    /// Converts a position in points at the default zoom into canvas units.
+    fn canvas_pos(x: f32, y: f32) -> Pos2 {
+        Pos2::new(x, y) / DEFAULT_CANVAS_SCALE
+    }
+
+    fn line(points: &[Pos2], layer: Uuid) -> (Vec<LinePos>, Brush) {
+        (
+            points.iter().map(|pos| (*pos).into()).collect(),
+            Brush {
+                layer,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn assert_close(a: Pos2, b: Pos2) {
+        assert!(a.distance(b) < 1e-6, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn snaps_to_the_grid_spacing() {
+        let mut context = ApplicationContext::default();
+        let pos = canvas_pos(45., 17.);
+
+        assert_eq!(context.snap_point(pos, default_zoom()), pos);
+
+        context.grid.snap_to_grid = true;
+        context.grid.spacing = 32.;
+
+        assert_close(
+            context.snap_point(pos, default_zoom()),
+            canvas_pos(32., 32.),
+        );
+
+        // Snapping doesn't depend on the zoom
+        let zoomed_in = emath::RectTransform::from_to(
+            Rect::from_min_size(Pos2::ZERO, Vec2::splat(1.)),
+            Rect::from_min_size(Pos2::ZERO, Vec2::splat(DEFAULT_CANVAS_SCALE * 4.)),
+        );
+
+        assert_close(context.snap_point(pos, zoomed_in), canvas_pos(32., 32.));
+    }
+
+    #[test]
+    fn endpoints_are_preferred_over_the_grid() {
+        let mut context = ApplicationContext::default();
+
+        context.grid.snap_to_grid = true;
+        context.grid.snap_to_endpoints = true;
+        context.paintbrush.brush_type = BrushType::None;
+        context.lines.push(line(
+            &[canvas_pos(100., 100.), canvas_pos(205., 203.)],
+            Uuid::nil(),
+        ));
+
+        assert_close(
+            context.snap_point(canvas_pos(200., 200.), default_zoom()),
+            canvas_pos(205., 203.),
+        );
+
+        // Endpoints further than ENDPOINT_SNAP_DISTANCE on the screen are ignored
+        assert_close(
+            context.snap_point(canvas_pos(140., 100.), default_zoom()),
+            canvas_pos(128., 96.),
+        );
+    }
+
+    #[test]
+    fn skips_hidden_layers_and_the_line_being_drawn() {
+        let mut context = ApplicationContext::default();
+        let hidden_layer = Uuid::new_v4();
+
+        context.layers.insert(
+            hidden_layer,
+            Layer {
+                visible: false,
+                ..Layer::new("Hidden")
+            },
+        );
+        context.grid.snap_to_endpoints = true;
+        context.paintbrush.brush_type = BrushType::Marker;
+        context.lines = vec![
+            line(&[canvas_pos(0., 0.), canvas_pos(50., 0.)], hidden_layer),
+            line(&[canvas_pos(0., 50.), canvas_pos(100., 50.)], Uuid::nil()),
+        ];
+
+        // The last line is the one being drawn with the marker
+        for pos in [canvas_pos(52., 0.), canvas_pos(98., 50.)] {
+            assert_eq!(context.snap_point(pos, default_zoom()), pos);
+        }
+
+        context.paintbrush.brush_type = BrushType::None;
+
+        assert_close(
+            context.snap_point(canvas_pos(98., 50.), default_zoom()),
+            canvas_pos(100., 50.),
+        );
+    }
+
+    #[test]
+    fn steps_cover_the_range() {
+        let steps: Vec<f32> = steps_in_range(emath::Rangef::new(-1.5, 2.5), 1.).collect();
+
+        assert_eq!(steps, vec![-1., 0., 1., 2.]);
+    }
+
+    #[test]
+    fn grid_gets_sparser_when_zoomed_out() {
+        let grid = GridSettings {
+            spacing: 10.,
+            ..Default::default()
+        };
+
+        assert_eq!(grid.grid_step(default_zoom()) * DEFAULT_CANVAS_SCALE, 10.);
+
+        let zoomed_out = emath::RectTransform::from_to(
+            Rect::from_min_size(Pos2::ZERO, vec2(1., 1.)),
+            Rect::from_min_size(Pos2::ZERO, Vec2::splat(DEFAULT_CANVAS_SCALE / 4.)),
+        );
+
+        assert_eq!(grid.grid_step(zoomed_out) * DEFAULT_CANVAS_SCALE, 40.);
+    }
+
+    #[test]
+    fn ruler_steps_are_round() {
+        assert_eq!(ruler_step(1.), 100.);
+        assert_eq!(ruler_step(2.), 50.);
+        assert_eq!(ruler_step(0.5), 200.);
+        assert_eq!(ruler_step(10.), 10.);
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::Cursor;
+
+use common_definitions::{CanvasImage, MessageType, Uuid};
+use drawing_board_renderer::DEFAULT_CANVAS_SCALE;
+use egui::{emath, vec2, Color32, Key, Painter, Pos2, Rect, Sense, Stroke, Ui, Vec2};
+
+use crate::ApplicationContext;
+
+/// The size (In canvas units) an inserted image's longer side is scaled down to, if it's larger.
+const MAX_INSERTED_IMAGE_SIZE: f32 = 0.5;
+
+/// The size of the handle used for resizing the selected image, in points.
+const RESIZE_HANDLE_SIZE: f32 = 10.;
+
+/// The smallest size (In canvas units) an image can be resized to.
+const MIN_IMAGE_SIZE: f32 = 0.01;
+
+/// Returns the uri the image's bytes are registered with in egui's image loaders.
+fn image_uri(id: Uuid) -> String {
+    format!("bytes://canvas_image/{id}")
+}
+
+/// Creates a ```CanvasImage``` from the encoded (png or jpeg) bytes of an image.
+/// The image is centered on ```center```, and is sized so that one pixel takes up one point, unless it's larger than ```MAX_INSERTED_IMAGE_SIZE```.
+pub fn create_canvas_image(data: Vec<u8>, center: Pos2) -> anyhow::Result<CanvasImage> {
+    let (width, height) = image::ImageReader::new(Cursor::new(&data))
+        .with_guessed_format()?
+        .into_dimensions()?;
+
+    let size = vec2(width as f32, height as f32) / DEFAULT_CANVAS_SCALE;
+    let size = size * (MAX_INSERTED_IMAGE_SIZE / size.max_elem()).min(1.);
+
+    Ok(CanvasImage {
+        rect: Rect::from_center_size(center, size),
+        data: data.into(),
+    })
+}
+
+impl ApplicationContext {
+    /// Places an image in the middle of the visible area of the canvas.
+    /// The image is also sent to the server, if there is an open connection.
+    pub fn insert_image(&mut self, data: Vec<u8>) -> anyhow::Result<()> {
+        let center = self
+            .visible_canvas_area
+            .map(|area| area.center())
+            .unwrap_or(Pos2::new(0.5, 0.5));
+
+        let image = create_canvas_image(data, center)?;
+        let id = Uuid::new_v4();
+
+        self.send_messages(image.to_messages(id));
+
+        self.images.insert(id, image);
+        self.selected_image = Some(id);
+        self.add_undo_point();
+        self.mark_unsaved();
+
+        Ok(())
+    }
+
+    /// Draws the images onto the canvas.
+    /// The images' bytes are registered in egui's image loaders the first time they are drawn, and forgotten once the image is removed.
+    pub fn draw_images(&mut self, ui: &Ui, painter: &Painter, to_screen: emath::RectTransform) {
+        self.loaded_images.retain(|id| {
+            let is_on_canvas = self.images.contains_key(id);
+
+            if !is_on_canvas {
+                ui.ctx().forget_image(&image_uri(*id));
+            }
+
+            is_on_canvas
+        });
+
+        for (id, image) in self.images.iter() {
+            if self.loaded_images.insert(*id) {
+                ui.ctx().include_bytes(image_uri(*id), image.data.clone());
+            }
+
+            let screen_rect = to_screen.transform_rect(image.rect);
+
+            if !painter.clip_rect().intersects(screen_rect) {
+                continue;
+            }
+
+            egui::Image::new(image_uri(*id)).paint_at(ui, screen_rect);
+        }
+    }
+
+    /// Handles moving, resizing and deleting the images.
+    /// An image is selected by clicking or dragging it, the selected image can be resized from its bottom right corner and deleted with the ```Delete``` key.
+    pub fn image_interaction(&mut self, ui: &mut Ui, to_screen: emath::RectTransform) {
+        let canvas_scale = to_screen.scale();
+
+        let mut modified_image: Option<Uuid> = None;
+
+        // The widgets registered last get the interactions first, just like the images drawn last are on the top
+        for (id, image) in self.images.iter_mut() {
+            let screen_rect = to_screen.transform_rect(image.rect);
+
+            let response = ui.interact(
+                screen_rect,
+                ui.id().with(("canvas_image", *id)),
+                Sense::click_and_drag(),
+            );
+
+            if response.clicked() || response.drag_started() {
+                self.selected_image = Some(*id);
+            }
+
+            if response.dragged() {
+                image.rect = image.rect.translate(response.drag_delta() / canvas_scale);
+            }
+
+            if response.drag_stopped() {
+                modified_image = Some(*id);
+            }
+        }
+
+        if let Some(selected_id) = self.selected_image {
+            match self.images.get_mut(&selected_id) {
+                Some(image) => {
+                    let screen_rect = to_screen.transform_rect(image.rect);
+
+                    let handle_response = ui.interact(
+                        Rect::from_center_size(
+                            screen_rect.right_bottom(),
+                            Vec2::splat(RESIZE_HANDLE_SIZE),
+                        ),
+                        ui.id().with(("canvas_image_resize", selected_id)),
+                        Sense::drag(),
+                    );
+
+                    if handle_response.dragged() {
+                        // The aspect ratio of the image is kept while resizing
+                        let aspect_ratio = image.rect.height() / image.rect.width();
+                        let width = (image.rect.width()
+                            + handle_response.drag_delta().x / canvas_scale.x)
+                            .max(MIN_IMAGE_SIZE);
+
+                        image.rect =
+                            Rect::from_min_size(image.rect.min, vec2(width, width * aspect_ratio));
+                    }
+
+                    if handle_response.drag_stopped() {
+                        modified_image = Some(selected_id);
+                    }
+
+                    let screen_rect = to_screen.transform_rect(image.rect);
+
+                    ui.painter()
+                        .rect_stroke(screen_rect, 0., Stroke::new(1., Color32::LIGHT_BLUE));
+                    ui.painter().rect_filled(
+                        Rect::from_center_size(
+                            screen_rect.right_bottom(),
+                            Vec2::splat(RESIZE_HANDLE_SIZE),
+                        ),
+                        1.,
+                        Color32::LIGHT_BLUE,
+                    );
+
+                    if !ui.ctx().wants_keyboard_input()
+                        && ui.input(|input| input.key_pressed(Key::Delete))
+                    {
+                        self.images.shift_remove(&selected_id);
+                        self.selected_image = None;
+                        self.add_undo_point();
+                        self.mark_unsaved();
+
+                        self.send_messages(vec![MessageType::ModifyImage((selected_id, None))]);
+                    }
+                }
+                // The selected image was deleted
+                None => self.selected_image = None,
+            }
+        }
+
+        if let Some(id) = modified_image {
+            if let Some(image) = self.images.get(&id) {
+                self.send_messages(vec![MessageType::ModifyImage((id, Some(image.rect)))]);
+                self.add_undo_point();
+                self.mark_unsaved();
+            }
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use common_definitions::{
+    Brush, BrushType, CanvasShape, LinePos, MessageType, ShapeKind, DEFAULT_LAYER,
+};
+use drawing_board_renderer::DEFAULT_CANVAS_SCALE;
+use egui::{Color32, Pos2};
+use usvg::{tiny_skia_path::PathSegment, Node, Paint};
+use uuid::Uuid;
+
+use crate::{ApplicationContext, BrushMap};
+
+/// The maximum distance (In points) between the points of a flattened curve.
+const CURVE_FLATTENING_STEP: f32 = 2.;
+
+/// The maximum number of segments a single curve is flattened into.
+const MAX_CURVE_SEGMENTS: usize = 64;
+
+/// The maximum number of lines and shapes an svg document can be imported into, as each of them is sent to the server in its own message.
+const MAX_IMPORTED_PATHS: usize = 10_000;
+
+/// The maximum number of points all of the imported lines and shapes can have together.
+const MAX_IMPORTED_POINTS: usize = 1_000_000;
+
+/// The lines and the shapes an svg document was converted into.
+#[derive(Default)]
+pub struct ImportedSvg {
+    /// The stroked paths, converted into lines.
+    pub lines: BrushMap,
+    /// The filled paths, converted into filled polygons.
+    pub shapes: Vec<CanvasShape>,
+    /// The number of points of the lines and the shapes.
+    point_count: usize,
+}
+
+impl ImportedSvg {
+    /// Returns an error if the document has more paths or points than what can be imported.
+    fn check_limits(&self) -> anyhow::Result<()> {
+        if self.lines.len() + self.shapes.len() > MAX_IMPORTED_PATHS {
+            return Err(anyhow::Error::msg(format!(
+                "The svg file has too many paths (The limit is {MAX_IMPORTED_PATHS})."
+            )));
+        }
+
+        if self.point_count > MAX_IMPORTED_POINTS {
+            return Err(anyhow::Error::msg(format!(
+                "The svg file has too many points (The limit is {MAX_IMPORTED_POINTS})."
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads an svg document and converts its paths, lines, polylines and basic shapes into lines and shapes.
+/// Curves are flattened, stroked paths become lines with a ```Brush``` derived from their stroke's color and width, and filled paths become filled polygons (One for every subpath, so holes are filled too).
+pub fn import_svg(svg_data: &[u8]) -> anyhow::Result<ImportedSvg> {
+    let tree = usvg::Tree::from_data(svg_data, &usvg::Options::default())?;
+
+    let mut imported = ImportedSvg::default();
+
+    import_group(tree.root(), &mut imported)?;
+
+    Ok(imported)
+}
+
+/// Converts every path in the ```usvg::Group``` (And its children) into lines and shapes.
+fn import_group(group: &usvg::Group, imported: &mut ImportedSvg) -> anyhow::Result<()> {
+    for node in group.children() {
+        match node {
+            Node::Group(group) => import_group(group, imported)?,
+            Node::Path(path) => {
+                if !path.is_visible() {
+                    continue;
+                }
+
+                import_path(path, imported);
+                imported.check_limits()?;
+            }
+            // Images and texts can't be converted into lines
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts the subpaths of a ```usvg::Path``` into filled polygons if the path is filled, otherwise into lines.
+fn import_path(path: &usvg::Path, imported: &mut ImportedSvg) {
+    let transform = path.abs_transform();
+    let (scale_x, scale_y) = transform.get_scale();
+    let scale = (scale_x + scale_y) / 2.;
+
+    let subpaths = flatten_path(path.data(), |x, y| {
+        let mut point = usvg::tiny_skia_path::Point::from_xy(x, y);
+        transform.map_point(&mut point);
+
+        Pos2::new(point.x, point.y)
+    });
+
+    for points in subpaths {
+        if points.len() < 2 || points.iter().any(|point| !point.is_finite()) {
+            continue;
+        }
+
+        let points: Vec<Pos2> = points
+            .into_iter()
+            .map(|point| point / DEFAULT_CANVAS_SCALE)
+            .collect();
+
+        // The outline of a polygon doesn't repeat its first point
+        let mut outline = points.clone();
+
+        if outline.first() == outline.last() {
+            outline.pop();
+        }
+
+        // Paths without an area (Like lines, which are filled by default) are only stroked
+        let fill = path
+            .fill()
+            .filter(|_| polygon_area(&outline) > f32::EPSILON);
+
+        match (fill, path.stroke()) {
+            (Some(fill), stroke) => {
+                let (stroke_width, stroke_color) =
+                    stroke.map_or((0., Color32::TRANSPARENT), |stroke| {
+                        (
+                            stroke.width().get() * scale,
+                            paint_color(stroke.paint(), stroke.opacity().get()),
+                        )
+                    });
+
+                imported.point_count += outline.len();
+                imported.shapes.push(CanvasShape {
+                    kind: ShapeKind::Polygon,
+                    points: outline,
+                    stroke_width,
+                    stroke_color,
+                    fill: Some(paint_color(fill.paint(), fill.opacity().get())),
+                    layer: DEFAULT_LAYER,
+                });
+            }
+            (None, Some(stroke)) => {
+                imported.point_count += points.len();
+                imported.lines.push((
+                    points.into_iter().map(LinePos::from).collect(),
+                    stroke_brush(stroke, scale),
+                ));
+            }
+            // Paths without a fill and a stroke aren't visible
+            (None, None) => (),
+        }
+    }
+}
+
+/// Returns the area the polygon encloses, using the shoelace formula.
+fn polygon_area(points: &[Pos2]) -> f32 {
+    let doubled_area: f32 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(point, next)| point.x * next.y - next.x * point.y)
+        .sum();
+
+    doubled_area.abs() / 2.
+}
+
+/// Creates the ```Brush``` a stroked ```usvg::Path``` should be drawn with.
+/// ```scale``` is the scale of the path's transformation, which is applied to the stroke's width.
+fn stroke_brush(stroke: &usvg::Stroke, scale: f32) -> Brush {
+    Brush {
+        width: (stroke.width().get() * scale).max(1.),
+        color: paint_color(stroke.paint(), stroke.opacity().get()),
+        brush_type: BrushType::Marker,
+        ..Default::default()
+    }
+}
+
+/// Returns the color of a stroke's or a fill's paint.
+fn paint_color(paint: &Paint, opacity: f32) -> Color32 {
+    match paint {
+        Paint::Color(color) => Color32::from_rgba_unmultiplied(
+            color.red,
+            color.green,
+            color.blue,
+            (opacity * 255.) as u8,
+        ),
+        // Gradients and patterns can't be displayed on the canvas
+        _ => Color32::BLACK.gamma_multiply(opacity),
+    }
+}
+
+/// Flattens the path into a list of subpaths.
+/// Every point is mapped with ```map_point``` before flattening, so that the curves are flattened with the final size.
+fn flatten_path(
+    path: &usvg::tiny_skia_path::Path,
+    map_point: impl Fn(f32, f32) -> Pos2,
+) -> Vec<Vec<Pos2>> {
+    let mut subpaths: Vec<Vec<Pos2>> = vec![];
+    let mut current_subpath: Vec<Pos2> = vec![];
+
+    for segment in path.segments() {
+        let last_point = current_subpath.last().copied().unwrap_or(Pos2::ZERO);
+
+        match segment {
+            PathSegment::MoveTo(point) => {
+                if !current_subpath.is_empty() {
+                    subpaths.push(std::mem::take(&mut current_subpath));
+                }
+
+                current_subpath.push(map_point(point.x, point.y));
+            }
+            PathSegment::LineTo(point) => {
+                current_subpath.push(map_point(point.x, point.y));
+            }
+            PathSegment::QuadTo(control, point) => {
+                let control = map_point(control.x, control.y);
+                let point = map_point(point.x, point.y);
+
+                flatten_curve(&mut current_subpath, last_point, [control, point], |t| {
+                    let inverse_t = 1. - t;
+
+                    (last_point.to_vec2() * inverse_t * inverse_t
+                        + control.to_vec2() * 2. * inverse_t * t
+                        + point.to_vec2() * t * t)
+                        .to_pos2()
+                });
+            }
+            PathSegment::CubicTo(first_control, second_control, point) => {
+                let first_control = map_point(first_control.x, first_control.y);
+                let second_control = map_point(second_control.x, second_control.y);
+                let point = map_point(point.x, point.y);
+
+                flatten_curve(
+                    &mut current_subpath,
+                    last_point,
+                    [first_control, second_control, point],
+                    |t| {
+                        let inverse_t = 1. - t;
+
+                        (last_point.to_vec2() * inverse_t * inverse_t * inverse_t
+                            + first_control.to_vec2() * 3. * inverse_t * inverse_t * t
+                            + second_control.to_vec2() * 3. * inverse_t * t * t
+                            + point.to_vec2() * t * t * t)
+                            .to_pos2()
+                    },
+                );
+            }
+            PathSegment::Close => {
+                if let Some(first_point) = current_subpath.first().copied() {
+                    current_subpath.push(first_point);
+                }
+            }
+        }
+    }
+
+    if !current_subpath.is_empty() {
+        subpaths.push(current_subpath);
+    }
+
+    subpaths
+}
+
+/// Flattens a curve into line segments, pushing the points into the subpath.
+/// The number of segments is estimated from the length of the curve's control polygon.
+fn flatten_curve<const N: usize>(
+    subpath: &mut Vec<Pos2>,
+    start: Pos2,
+    control_points: [Pos2; N],
+    evaluate: impl Fn(f32) -> Pos2,
+) {
+    let control_polygon_length: f32 = control_points
+        .iter()
+        .scan(start, |last_point, point| {
+            let distance = last_point.distance(*point);
+            *last_point = *point;
+
+            Some(distance)
+        })
+        .sum();
+
+    let segment_count = ((control_polygon_length / CURVE_FLATTENING_STEP).ceil() as usize)
+        .clamp(1, MAX_CURVE_SEGMENTS);
+
+    for segment in 1..=segment_count {
+        subpath.push(evaluate(segment as f32 / segment_count as f32));
+    }
+}
+
+impl ApplicationContext {
+    /// Inserts the lines and the shapes of an imported svg document into the canvas on the active layer.
+    /// They are also sent to the server, if there is an open connection.
+    pub fn insert_imported_svg(&mut self, imported: ImportedSvg) {
+        let shapes: Vec<(Uuid, CanvasShape)> = imported
+            .shapes
+            .into_iter()
+            .map(|shape| {
+                (
+                    Uuid::new_v4(),
+                    CanvasShape {
+                        layer: self.paintbrush.layer,
+                        ..shape
+                    },
+                )
+            })
+            .collect();
+
+        self.send_messages(
+            shapes
+                .iter()
+                .map(|shape| MessageType::AddShape(shape.clone()))
+                .collect(),
+        );
+
+        self.shapes.extend(shapes);
+
+        // The undo point is added by ```insert_lines```, so that the whole import is undone at once
+        self.insert_lines(imported.lines);
+    }
+
+    /// Inserts finished lines into the canvas on the active layer, before the line which is currently being drawn.
+    /// The lines are also sent to the server, if there is an open connection.
+    pub fn insert_lines(&mut self, lines: BrushMap) {
+        // The last line is the one being drawn, if it's empty
+        let insert_idx = if self.lines.last().is_some_and(|line| line.0.is_empty()) {
+            self.lines.len() - 1
+        } else {
+            self.lines.len()
+        };
+
+        // The lines' layer might not exist on this canvas (Like the layer of lines copied from another canvas)
+        let lines: BrushMap = lines
+            .into_iter()
+            .map(|(points, brush)| {
+                (
+                    points,
+                    Brush {
+                        layer: self.paintbrush.layer,
+                        ..brush
+                    },
+                )
+            })
+            .collect();
+
+        // There can be more lines than what fits into the channel at once
+        self.send_messages(
+            lines
+                .iter()
+                .map(|line| MessageType::AddLine(line.clone()))
+                .collect(),
+        );
+
+        self.lines.splice(insert_idx..insert_idx, lines);
+
+        self.add_undo_point();
+        self.mark_unsaved();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filled_paths_are_imported_as_shapes() {
+        let imported = import_svg(
+            br##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <rect x="10" y="10" width="20" height="20" fill="#ff0000"/>
+                <polyline points="0,0 50,50" fill="none" stroke="#0000ff" stroke-width="3"/>
+            </svg>"##,
+        )
+        .unwrap();
+
+        assert_eq!(imported.shapes.len(), 1);
+        assert_eq!(imported.shapes[0].kind, ShapeKind::Polygon);
+        assert_eq!(imported.shapes[0].points.len(), 4);
+        assert_eq!(imported.shapes[0].fill, Some(Color32::RED));
+        assert_eq!(imported.shapes[0].stroke_color, Color32::TRANSPARENT);
+
+        assert_eq!(imported.lines.len(), 1);
+        assert_eq!(imported.lines[0].1.color, Color32::BLUE);
+        assert_eq!(imported.lines[0].1.width, 3.);
+    }
+
+    #[test]
+    fn too_many_paths_are_refused() {
+        let paths = r#"<line x1="0" y1="0" x2="10" y2="10" stroke="black"/>"#
+            .repeat(MAX_IMPORTED_PATHS + 1);
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">{paths}</svg>"#
+        );
+
+        assert!(import_svg(svg.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use common_definitions::{Layer, Layers, MessageType, Uuid};
+use egui::Ui;
+
+use crate::ApplicationContext;
+
+impl ApplicationContext {
+    /// Makes sure that there is at least one layer, and that the active layer exists.
+    /// The active layer can disappear when it's removed by another user, or when a different canvas is loaded.
+    pub fn ensure_active_layer(&mut self) {
+        if self.layers.is_empty() {
+            self.layers = Layers::default();
+        }
+
+        if !self.layers.contains_key(&self.paintbrush.layer) {
+            self.paintbrush.layer = *self.layers.last().unwrap().0;
+        }
+    }
+
+    /// Sends the layers to the server if there is an open connection, and marks the canvas as modified.
+    fn layers_changed(&mut self) {
+        self.send_messages(vec![MessageType::ModifyLayers(self.layers.clone())]);
+        self.mark_unsaved();
+    }
+
+    /// Adds a new layer above the active layer, and makes it the active layer.
+    fn add_layer(&mut self) {
+        let id = Uuid::new_v4();
+        let idx = self
+            .layers
+            .get_index_of(&self.paintbrush.layer)
+            .map_or(self.layers.len(), |idx| idx + 1);
+
+        let name = format!("Layer {}", self.layers.len() + 1);
+
+        self.layers.shift_insert(idx, id, Layer::new(name));
+        self.paintbrush.layer = id;
+
+        self.layers_changed();
+    }
+
+    /// Removes the active layer together with its lines and shapes, the layer below it becomes the active layer.
+    /// The last layer can't be removed.
+    fn remove_active_layer(&mut self) {
+        let id = self.paintbrush.layer;
+
+        let Some(idx) = self.layers.get_index_of(&id) else {
+            return;
+        };
+
+        if self.layers.len() <= 1 {
+            return;
+        }
+
+        self.layers.shift_remove(&id);
+        self.paintbrush.layer = *self.layers.get_index(idx.saturating_sub(1)).unwrap().0;
+
+        let mut messages = vec![];
+
+        self.lines.retain(|(points, brush)| {
+            // The last, empty line is the one being drawn
+            if brush.layer != id || points.is_empty() {
+                return true;
+            }
+
+            messages.push(MessageType::ModifyLine((points.clone(), None)));
+
+            false
+        });
+
+        self.shapes.retain(|shape_id, shape| {
+            if shape.layer != id {
+                return true;
+            }
+
+            messages.push(MessageType::ModifyShape((*shape_id, None)));
+
+            false
+        });
+
+        self.send_messages(messages);
+
+        self.add_undo_point();
+
+        self.layers_changed();
+    }
+
+    /// Moves the active layer up (Towards the top) or down by one.
+    fn move_active_layer(&mut self, up: bool) {
+        let Some(idx) = self.layers.get_index_of(&self.paintbrush.layer) else {
+            return;
+        };
+
+        let target_idx = if up { idx + 1 } else { idx.wrapping_sub(1) };
+
+        if target_idx >= self.layers.len() {
+            return;
+        }
+
+        self.layers.swap_indices(idx, target_idx);
+
+        self.layers_changed();
+    }
+
+    /// Displays the layers, with the topmost layer at the top of the list.
+    /// The active layer is selected by clicking its name, and the lines drawn afterwards are placed on it.
+    pub fn layers_ui(&mut self, ui: &mut Ui) {
+        self.ensure_active_layer();
+
+        ui.horizontal(|ui| {
+            if ui.button("Add").clicked() {
+                self.add_layer();
+            }
+
+            if ui
+                .add_enabled(self.layers.len() > 1, egui::Button::new("Remove"))
+                .on_hover_text("Removes the active layer and the lines and shapes drawn on it.")
+                .clicked()
+            {
+                self.remove_active_layer();
+            }
+
+            if ui.button("Move up").clicked() {
+                self.move_active_layer(true);
+            }
+
+            if ui.button("Move down").clicked() {
+                self.move_active_layer(false);
+            }
+        });
+
+        ui.separator();
+
+        let mut changed = false;
+
+        egui::Grid::new("layers")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for (id, layer) in self.layers.iter_mut().rev() {
+                    let is_active = *id == self.paintbrush.layer;
+
+                    if is_active {
+                        changed |= ui.text_edit_singleline(&mut layer.name).changed();
+                    } else if ui.selectable_label(false, &layer.name).clicked() {
+                        self.paintbrush.layer = *id;
+                    }
+
+                    changed |= ui.checkbox(&mut layer.visible, "Visible").changed();
+                    changed |= ui.checkbox(&mut layer.locked, "Locked").changed();
+                    changed |= ui
+                        .add(egui::Slider::new(&mut layer.opacity, 0.0..=1.0).text("Opacity"))
+                        .changed();
+
+                    ui.end_row();
+                }
+            });
+
+        if changed {
+            self.layers_changed();
+        }
+    }
+}
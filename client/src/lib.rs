@@ -1,13 +1,20 @@
 pub const DRAWING_BOARD_IMAGE_EXT: &str = "dbimg";
 pub const DRAWING_BOARD_WORKSPACE_EXT: &str = "dbproject";
-use chrono::{Local, NaiveDate};
+use chrono::{Local, NaiveDate, NaiveDateTime};
 use common_definitions::CancellationToken;
-use common_definitions::{Brush, PointerProperties};
-use common_definitions::{BrushType, LinePos, Message, MessageType, TabType, BRUSH_TYPE_COUNT};
+use common_definitions::{
+    decode_file, encode_file, Brush, CanvasBackground, CanvasImage, CanvasShape, CanvasText,
+    FileKind, FileMetadata, ImageFile, ImageReceiver, IndexMap, Layers, PointerProperties,
+    ProjectProperties,
+};
+use common_definitions::{
+    BrushTexture, BrushType, LinePos, Message, MessageType, ShapeKind, StampSettings, TabType,
+    TextFont, DEFAULT_GRAFFITI_DENSITY, DEFAULT_LAYER, MAX_MESSAGE_LENGTH,
+};
 use egui::{
     ahash::{HashSet, HashSetExt},
     util::undoer::Undoer,
-    Color32,
+    Color32, Pos2, Rect, TextureHandle,
 };
 use egui_dock::{DockState, SurfaceIndex};
 use quinn::{
@@ -18,23 +25,67 @@ use quinn::{
     },
     ClientConfig, Connection, Endpoint, RecvStream, SendStream,
 };
-use serde::Deserialize;
 use std::{
-    collections::HashMap, fs, net::Ipv6Addr, path::PathBuf, str::FromStr, sync::Arc, time::Duration,
+    collections::HashMap,
+    net::Ipv6Addr,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 use tokio::{
     io::AsyncReadExt,
     select,
     sync::{
-        mpsc::{channel, Sender},
+        mpsc::{channel, unbounded_channel, UnboundedSender},
         Mutex, RwLock,
     },
 };
 use uuid::Uuid;
 mod app;
+mod autosave;
+mod background;
+mod brush_textures;
+mod camera;
+mod clipboard;
+mod cursors;
+mod document;
+mod eraser;
+mod export;
+mod grid;
+mod images;
+mod import;
+mod layers;
+mod participants;
+mod pressure;
+mod recent_files;
+mod selection;
+mod shapes;
+mod smoothing;
+mod texts;
+pub use autosave::AutoSave;
+pub use camera::Camera;
+pub use eraser::EraserMode;
+pub use export::{export_canvas, export_svg, ExportBackground, ExportCrop, ExportSettings};
+use grid::GridSettings;
+pub use images::create_canvas_image;
+pub use import::{import_svg, ImportedSvg};
+pub use recent_files::{RecentFile, RecentFiles};
+use selection::SelectionDrag;
+use shapes::default_shape_fill;
+pub use smoothing::{simplify_line, SmoothingMode, StrokeSmoothing};
+use texts::TextDraft;
 
 pub type BrushMap = Vec<(Vec<LinePos>, Brush)>;
 
+/// The parts of the canvas which the undo points are taken of, the lines, the shapes, the images and the texts.
+pub type UndoState = (
+    BrushMap,
+    IndexMap<Uuid, CanvasShape>,
+    IndexMap<Uuid, CanvasImage>,
+    IndexMap<Uuid, CanvasText>,
+);
+
 #[derive(serde::Serialize, serde::Deserialize, Default)]
 pub struct ApplicationContext {
     lines: BrushMap,
@@ -43,13 +94,128 @@ pub struct ApplicationContext {
     file_session: Option<FileSession>,
 
     #[serde(skip)]
-    undoer: Undoer<BrushMap>,
+    undoer: Undoer<UndoState>,
 
     open_tabs: HashSet<TabType>,
 
     connection: ConnectionData,
 
-    export_path: Option<PathBuf>,
+    /// The settings the canvas is exported with.
+    #[serde(default)]
+    export_settings: ExportSettings,
+
+    /// Whether the export dialog is open.
+    #[serde(skip)]
+    export_dialog_open: bool,
+
+    /// The page count or the output size shown in the export dialog, with the settings and the visible area it was measured with.
+    /// It is only measured again when those change, as measuring goes through the whole drawing.
+    #[serde(skip)]
+    export_summary: Option<(ExportSettings, Option<Rect>, String)>,
+
+    /// The area of the canvas (In canvas units) which was visible in the Canvas tab the last time it was drawn.
+    #[serde(skip)]
+    visible_canvas_area: Option<Rect>,
+
+    /// The position of the pointer (In canvas units) if it was over the Canvas tab the last time it was drawn.
+    #[serde(skip)]
+    pointer_canvas_pos: Option<Pos2>,
+
+    /// The images placed on the canvas, the images inserted last are drawn on the top.
+    #[serde(default)]
+    images: IndexMap<Uuid, CanvasImage>,
+
+    /// The ```Uuid``` of the image which is selected for moving, resizing or deleting.
+    #[serde(skip)]
+    selected_image: Option<Uuid>,
+
+    /// The images whose bytes are registered in egui's image loaders.
+    #[serde(skip)]
+    loaded_images: HashSet<Uuid>,
+
+    /// Whether the canvas was modified since the document was last saved or opened.
+    #[serde(skip)]
+    unsaved_changes: bool,
+
+    /// The properties of the project, these are also saved into the header of image files.
+    #[serde(default)]
+    properties: ProjectProperties,
+
+    /// Whether the project properties dialog is open.
+    #[serde(skip)]
+    properties_dialog_open: bool,
+
+    /// The built-in ```BrushTexture```-s uploaded to egui, these are used for drawing the ```BrushType::Stamp``` lines.
+    #[serde(skip)]
+    brush_textures: HashMap<BrushTexture, TextureHandle>,
+
+    /// The last pressure reported by the pen, this is ```None``` if the input device doesn't report pressure or the pen was lifted.
+    #[serde(skip)]
+    pen_force: Option<f32>,
+
+    /// The position (In points) the eraser was at in the last frame, this is ```None``` if the eraser isn't being dragged.
+    #[serde(skip)]
+    last_eraser_pos: Option<Pos2>,
+
+    /// The points (In canvas units) of the shape being drawn, this is ```None``` if no shape is being drawn.
+    #[serde(skip)]
+    shape_draft: Option<Vec<Pos2>>,
+
+    /// The shapes placed on the canvas, the shapes inserted last are drawn on the top.
+    #[serde(default)]
+    shapes: IndexMap<Uuid, CanvasShape>,
+
+    /// The text being written or edited, this is ```None``` if no text is being edited.
+    #[serde(skip)]
+    text_draft: Option<TextDraft>,
+
+    /// The texts placed on the canvas, the texts inserted last are drawn on the top.
+    #[serde(default)]
+    texts: IndexMap<Uuid, CanvasText>,
+
+    /// The points of the lines selected with the ```BrushType::None``` brush, the lines are identified by their points.
+    #[serde(skip)]
+    selected_lines: Vec<Vec<LinePos>>,
+
+    /// The ```Uuid``` of the shape selected with the ```BrushType::None``` brush.
+    #[serde(skip)]
+    selected_shape: Option<Uuid>,
+
+    /// The lasso being drawn or the transformation being applied to the selected lines, this is ```None``` if nothing is being dragged.
+    #[serde(skip)]
+    selection_drag: Option<SelectionDrag>,
+
+    /// The system clipboard, this is ```None``` until the clipboard is first used.
+    #[serde(skip)]
+    clipboard: Option<arboard::Clipboard>,
+
+    /// The layers the lines are drawn on.
+    #[serde(default)]
+    layers: Layers,
+
+    /// The part of the canvas which is displayed in the Canvas tab.
+    #[serde(default)]
+    camera: Camera,
+
+    /// The settings of the grid, the rulers and the snapping.
+    #[serde(default)]
+    grid: GridSettings,
+
+    /// The background of the canvas, this is saved with the document.
+    #[serde(default)]
+    background: CanvasBackground,
+
+    /// The image displayed by the ```BackgroundStyle::Image``` background.
+    #[serde(default)]
+    background_image: Option<CanvasImage>,
+
+    /// Whether the background image's bytes are registered in egui's image loaders.
+    #[serde(skip)]
+    background_image_loaded: bool,
+
+    /// Whether the background dialog is open.
+    #[serde(skip)]
+    background_dialog_open: bool,
 }
 
 /// This struct contains the information useful for the connection process (Like ```username```, ```target_address``` and ```connected_clients```), and the current session connected to the server.
@@ -70,9 +236,37 @@ pub struct ConnectionData {
     #[serde(skip)]
     connected_clients: HashMap<Uuid, (String, PointerProperties)>,
 
+    /// The area of the canvas (In canvas units) the connected clients are looking at
+    #[serde(skip)]
+    client_viewports: HashMap<Uuid, Rect>,
+
+    /// The ```Uuid``` of the client whose viewport is followed by the camera, this is ```None``` if no one is followed
+    #[serde(skip)]
+    followed_client: Option<Uuid>,
+
+    /// The viewport which was last sent to the server, the viewport is only sent again once it changes or a new user connects
+    #[serde(skip)]
+    sent_viewport: Option<Rect>,
+
+    /// The time the connected clients' cursors last moved, the cursors fade out once they haven't moved for a while
+    #[serde(skip)]
+    cursor_moved_at: HashMap<Uuid, Instant>,
+
+    /// The ```PointerProperties``` which were last sent to the server, the pointer is only sent again once it moves or the brush changes
+    #[serde(skip)]
+    sent_pointer: Option<(Pos2, Brush)>,
+
     /// The current open session to the server available at the ```target_address```
     #[serde(skip)]
     current_session: Option<ConnectionSession>,
+
+    /// The path the canvas exported by the server should be saved to, this is set when an export is requested from the server.
+    #[serde(skip)]
+    server_export_path: Option<PathBuf>,
+
+    /// Reassembles the images the server sends in chunks.
+    #[serde(skip)]
+    image_receiver: ImageReceiver,
 }
 
 /// The current connection session to the remote address/server.
@@ -92,7 +286,8 @@ pub struct ConnectionSession {
     pub recv_stream: Arc<Mutex<RecvStream>>,
 
     /// This ```Sender``` channel side is used to send ```MessageType```-s to the sender thread (Cancellable via ```connection_cancellation_token```).
-    pub sender_to_server: Sender<MessageType>,
+    /// Every outgoing message goes through this one unbounded channel, so they keep their order and a large upload (Like the chunks of an image) can't fill it up.
+    pub sender_to_server: UnboundedSender<MessageType>,
 
     /// This ```Reciver``` channel side is used to receive messages from the server (Cancellable via ```connection_cancellation_token```).
     pub message_reciver_from_server: tokio::sync::mpsc::Receiver<Message>,
@@ -107,7 +302,7 @@ impl ConnectionSession {
 
 /// This struct contains useful infromation about the current file session.
 /// This struct is initalized when opening a file, containing its properties.
-#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[derive(serde::Serialize, serde::Deserialize, Default, Clone)]
 pub struct FileSession {
     /// The ```PathBuf``` to the file.
     pub file_path: PathBuf,
@@ -115,14 +310,22 @@ pub struct FileSession {
     pub project_name: String,
     /// The date this project was created (```NaiveDate```).
     pub project_created: NaiveDate,
+    /// The kind of the document the file contains.
+    #[serde(default)]
+    pub kind: FileKind,
+    /// The local time the file was last saved at.
+    #[serde(default)]
+    pub last_saved: Option<NaiveDateTime>,
 }
 
 impl FileSession {
-    pub fn create_session(file_path: PathBuf, project_name: String) -> Self {
+    pub fn create_session(file_path: PathBuf, project_name: String, kind: FileKind) -> Self {
         Self {
             file_path,
             project_name,
             project_created: Local::now().date_naive(),
+            kind,
+            last_saved: None,
         }
     }
 }
@@ -142,6 +345,18 @@ pub struct Application {
     /// A new ```Uuid``` instance is created whenever the application is opened.
     #[serde(skip)]
     uuid: ClientIdentificator,
+
+    /// The title last set on the window, this is used so the title is only updated when it changes.
+    #[serde(skip)]
+    window_title: String,
+
+    /// The auto-save settings of the application.
+    #[serde(default)]
+    autosave: AutoSave,
+
+    /// The files which were opened or saved recently.
+    #[serde(default)]
+    recent_files: RecentFiles,
 }
 
 /// This struct wraps the Uuid so that a custom default can be implemented for it.
@@ -155,8 +370,15 @@ impl Default for ClientIdentificator {
 
 impl Application {
     /// Resets the application's state by replacing it with ```Application::default()```.
+    /// The auto-save settings and the recent files are kept, as they aren't part of the document.
     pub fn reset(&mut self) {
+        let autosave = std::mem::take(&mut self.autosave);
+        let recent_files = std::mem::take(&mut self.recent_files);
+
         *self = Application::default();
+
+        self.autosave = autosave;
+        self.recent_files = recent_files;
     }
 }
 
@@ -273,7 +495,7 @@ pub async fn connect_to_server(
         recv_stream: recv_stream.clone(),
         connection_handle: Arc::new(RwLock::new(client)),
         sender_to_server: {
-            let (msg_sender, mut msg_reciver) = channel::<MessageType>(255);
+            let (msg_sender, mut msg_reciver) = unbounded_channel::<MessageType>();
             let connection_cancellation_token_clone = connection_cancellation_token.clone();
             let send_stream = send_stream.clone();
 
@@ -311,7 +533,7 @@ pub async fn connect_to_server(
                         mut recv_stream = recv_stream.lock() => {
                             match recv_stream.read_u64().await {
                                 Ok(message_length) => {
-                                    if message_length > 128000000 {
+                                    if message_length > MAX_MESSAGE_LENGTH {
                                         println!("Incoming message length too large, refusing to acknowledge.");
 
                                         continue;
@@ -366,6 +588,9 @@ impl Default for Application {
             tree: dock_state,
             context,
             uuid: ClientIdentificator::default(),
+            window_title: String::new(),
+            autosave: AutoSave::default(),
+            recent_files: RecentFiles::default(),
         }
     }
 }
@@ -375,83 +600,367 @@ impl Default for Application {
 pub struct PaintBrushes {
     /// The current ```BrushType```.
     brush_type: BrushType,
-    /// The ```BrushType```-s' width.
-    brush_width: [f32; BRUSH_TYPE_COUNT],
-    /// The ```BrushType```-s' color.
-    brush_color: [Color32; BRUSH_TYPE_COUNT],
+    /// The ```BrushType```-s' width, the brushes without an entry have the ```DEFAULT_BRUSH_WIDTH```.
+    #[serde(deserialize_with = "deserialize_brush_settings")]
+    brush_width: HashMap<BrushType, f32>,
+    /// The ```BrushType```-s' color, the brushes without an entry have the default color.
+    #[serde(deserialize_with = "deserialize_brush_settings")]
+    brush_color: HashMap<BrushType, Color32>,
+    /// The density of the ```BrushType::Graffiti``` brush's particles.
+    #[serde(default = "default_graffiti_density")]
+    graffiti_density: u32,
+    /// The texture and the stamp settings of the ```BrushType::Stamp``` brush.
+    #[serde(default)]
+    stamp: StampSettings,
+    /// Whether the width of the lines follows the pen's pressure (Or the pointer's speed, if the pen doesn't report pressure).
+    /// This is off by default, so that the lines keep the brush's width unless the user asks otherwise.
+    #[serde(default)]
+    dynamic_width: bool,
+    /// The smoothing and simplification settings of the lines being drawn.
+    #[serde(default)]
+    smoothing: StrokeSmoothing,
+    /// The way the ```BrushType::Eraser``` removes the lines it touches.
+    #[serde(default)]
+    eraser_mode: EraserMode,
+    /// The kind of the shapes the ```BrushType::Shape``` brush places.
+    #[serde(default)]
+    shape_kind: ShapeKind,
+    /// Whether the closed shapes are filled.
+    #[serde(default)]
+    fill_shapes: bool,
+    /// The color the closed shapes are filled with.
+    #[serde(default = "default_shape_fill")]
+    shape_fill: Color32,
+    /// The size (In points) of the texts the ```BrushType::Text``` brush places.
+    #[serde(default = "default_text_size")]
+    text_size: f32,
+    /// The font of the texts the ```BrushType::Text``` brush places.
+    #[serde(default)]
+    text_font: TextFont,
+    /// The ```Uuid``` of the active layer, which the lines are drawn on.
+    #[serde(default)]
+    layer: Uuid,
+}
+
+/// The width of the brushes which weren't used yet.
+const DEFAULT_BRUSH_WIDTH: f32 = 1.;
+
+/// The ```BrushType```-s in the order their settings were stored in, when the settings were stored in arrays.
+/// The brushes added over time were inserted before the ```BrushType::Eraser```, so the order depends on the length of the array.
+fn legacy_brush_order(length: usize) -> impl Iterator<Item = BrushType> {
+    [
+        BrushType::None,
+        BrushType::Graffiti,
+        BrushType::Pencil,
+        BrushType::Marker,
+        BrushType::Stamp,
+        BrushType::Shape,
+        BrushType::Text,
+    ]
+    .into_iter()
+    .take(length.saturating_sub(1))
+    .chain([BrushType::Eraser])
+}
+
+/// Reads the settings of the brushes, which are either keyed by their ```BrushType``` or (In older workspaces and application states) stored in an array.
+fn deserialize_brush_settings<'de, D, T>(deserializer: D) -> Result<HashMap<BrushType, T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum BrushSettings<T> {
+        Keyed(HashMap<BrushType, T>),
+        Legacy(Vec<T>),
+    }
+
+    Ok(
+        match <BrushSettings<T> as serde::Deserialize>::deserialize(deserializer)? {
+            BrushSettings::Keyed(settings) => settings,
+            BrushSettings::Legacy(settings) => {
+                legacy_brush_order(settings.len()).zip(settings).collect()
+            }
+        },
+    )
+}
+
+fn default_graffiti_density() -> u32 {
+    DEFAULT_GRAFFITI_DENSITY
+}
+
+fn default_text_size() -> f32 {
+    24.
 }
 
 impl Default for PaintBrushes {
     fn default() -> Self {
         Self {
             brush_type: BrushType::default(),
-            brush_width: [1.0; BRUSH_TYPE_COUNT],
-            brush_color: Default::default(),
+            brush_width: HashMap::new(),
+            brush_color: HashMap::new(),
+            graffiti_density: DEFAULT_GRAFFITI_DENSITY,
+            stamp: StampSettings::default(),
+            dynamic_width: false,
+            smoothing: StrokeSmoothing::default(),
+            eraser_mode: EraserMode::default(),
+            shape_kind: ShapeKind::default(),
+            fill_shapes: false,
+            shape_fill: default_shape_fill(),
+            text_size: default_text_size(),
+            text_font: TextFont::default(),
+            layer: DEFAULT_LAYER,
         }
     }
 }
 
 impl PaintBrushes {
     /// Get current brush selected by the client.
-    /// This function converts the ```BrushType``` to a usize as every ```BrushType``` has its own width and color.
+    /// Every ```BrushType``` has its own width and color.
     pub fn get_current_brush(&self) -> Brush {
-        (
-            self.brush_width[self.brush_type as usize],
-            self.brush_color[self.brush_type as usize],
-            self.brush_type,
-        )
+        Brush {
+            width: self
+                .brush_width
+                .get(&self.brush_type)
+                .copied()
+                .unwrap_or(DEFAULT_BRUSH_WIDTH),
+            color: self
+                .brush_color
+                .get(&self.brush_type)
+                .copied()
+                .unwrap_or_default(),
+            brush_type: self.brush_type,
+            density: self.graffiti_density,
+            stamp: self.stamp,
+            layer: self.layer,
+        }
     }
 
     /// Get a mutable reference to the current brush and its properties.
     pub fn get_mut_current_brush(&mut self) -> (&mut f32, &mut Color32, &mut BrushType) {
         (
-            &mut self.brush_width[self.brush_type as usize],
-            &mut self.brush_color[self.brush_type as usize],
+            self.brush_width
+                .entry(self.brush_type)
+                .or_insert(DEFAULT_BRUSH_WIDTH),
+            self.brush_color.entry(self.brush_type).or_default(),
             &mut self.brush_type,
         )
     }
+}
+
+impl Application {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        // The loaders are used for displaying the images placed on the canvas
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+
+        let mut application: Self = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+
+        application.start_autosave_session();
+
+        application
+    }
+}
+
+impl ApplicationContext {
+    /// Serializes the whole ```ApplicationContext``` into a ```.dbproject``` file.
+    pub fn to_workspace_file(&self) -> anyhow::Result<Vec<u8>> {
+        encode_file(&self.file_metadata(FileKind::Workspace), self)
+    }
+
+    /// Serializes the whole ```ApplicationContext``` into a recovery file.
+    /// Recovery files are written while drawing, so they are saved without a thumbnail, which would have to be rendered.
+    pub fn to_recovery_file(&self) -> anyhow::Result<Vec<u8>> {
+        encode_file(
+            &FileMetadata {
+                properties: self.properties.clone(),
+                ..FileMetadata::new(FileKind::Workspace)
+            },
+            self,
+        )
+    }
 
-    /// Get the nth brush and its properties.
-    pub fn get_nth_brush(&self, nth: usize) -> Brush {
+    /// Reads a ```.dbproject``` file, migrating it from older format versions.
+    pub fn from_workspace_file(bytes: &[u8]) -> anyhow::Result<Self> {
+        let file = decode_file(bytes)?;
+
+        file.expect_kind(FileKind::Workspace)?;
+
+        match file.version {
+            0 => migrate_workspace_v0(&file.body),
+            _ => Ok(rmp_serde::from_slice(&file.body)?),
+        }
+    }
+
+    /// Creates the ```ImageFile``` of the canvas.
+    pub fn to_image_file(&self) -> ImageFile {
+        ImageFile {
+            lines: self.lines.clone(),
+            images: self.images.clone(),
+            shapes: self.shapes.clone(),
+            texts: self.texts.clone(),
+            layers: self.layers.clone(),
+            background: self.background,
+            background_image: self.background_image.clone(),
+        }
+    }
+
+    /// Returns the current state of the lines, the shapes, the images and the texts, which the undo points are taken of.
+    fn undo_state(&self) -> UndoState {
         (
-            self.brush_width[nth],
-            self.brush_color[nth],
-            self.brush_type,
+            self.lines.clone(),
+            self.shapes.clone(),
+            self.images.clone(),
+            self.texts.clone(),
         )
     }
-}
 
-impl Application {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        if let Some(storage) = cc.storage {
-            let data = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+    /// Adds an undo point of the current lines, shapes, images and texts, unless the ```Undoer``` is already waiting for the canvas to settle.
+    fn add_undo_point(&mut self) {
+        if !self.undoer.is_in_flux() {
+            self.undoer.add_undo(&self.undo_state());
+        }
+    }
+
+    /// Restores the lines, the shapes, the images and the texts of an undo point.
+    /// The lines, the shapes, the images and the texts which differ from the restored ones are sent to the server, if there is an open connection.
+    fn restore_undo_state(&mut self, (lines, shapes, images, texts): UndoState) {
+        // The lines are identified by their points, the empty lines are the ones which are still being drawn
+        let restored_lines: HashMap<&Vec<LinePos>, &Brush> = lines
+            .iter()
+            .filter(|(points, _)| !points.is_empty())
+            .map(|(points, brush)| (points, brush))
+            .collect();
+        let current_lines: HashMap<&Vec<LinePos>, &Brush> = self
+            .lines
+            .iter()
+            .filter(|(points, _)| !points.is_empty())
+            .map(|(points, brush)| (points, brush))
+            .collect();
+
+        let mut messages: Vec<MessageType> = current_lines
+            .keys()
+            .filter(|points| !restored_lines.contains_key(*points))
+            .map(|points| MessageType::ModifyLine(((*points).clone(), None)))
+            .collect();
+
+        // The restored lines are added in the order they are drawn in
+        for (points, brush) in lines.iter().filter(|(points, _)| !points.is_empty()) {
+            match current_lines.get(points) {
+                Some(current_brush) if *current_brush == brush => (),
+                Some(_) => messages.push(MessageType::ModifyLine((points.clone(), Some(*brush)))),
+                None => messages.push(MessageType::AddLine((points.clone(), *brush))),
+            }
+        }
 
-            return data;
+        messages.extend(
+            self.shapes
+                .keys()
+                .filter(|id| !shapes.contains_key(*id))
+                .map(|id| MessageType::ModifyShape((*id, None))),
+        );
+
+        for (id, shape) in &shapes {
+            match self.shapes.get(id) {
+                Some(current_shape) if current_shape == shape => (),
+                Some(_) => messages.push(MessageType::ModifyShape((*id, Some(shape.clone())))),
+                None => messages.push(MessageType::AddShape((*id, shape.clone()))),
+            }
         }
 
-        Self::default()
+        messages.extend(
+            self.images
+                .keys()
+                .filter(|id| !images.contains_key(*id))
+                .map(|id| MessageType::ModifyImage((*id, None))),
+        );
+
+        for (id, image) in &images {
+            match self.images.get(id) {
+                Some(current_image) if current_image == image => (),
+                // Only the area of the image needs to be sent if it was moved or resized
+                Some(current_image) if current_image.data == image.data => {
+                    messages.push(MessageType::ModifyImage((*id, Some(image.rect))))
+                }
+                _ => messages.extend(image.to_messages(*id)),
+            }
+        }
+
+        messages.extend(
+            self.texts
+                .keys()
+                .filter(|id| !texts.contains_key(*id))
+                .map(|id| MessageType::ModifyText((*id, None))),
+        );
+
+        for (id, text) in &texts {
+            match self.texts.get(id) {
+                Some(current_text) if current_text == text => (),
+                Some(_) => messages.push(MessageType::ModifyText((*id, Some(text.clone())))),
+                None => messages.push(MessageType::AddText((*id, text.clone()))),
+            }
+        }
+
+        self.send_messages(messages);
+
+        self.lines = lines;
+        self.shapes = shapes;
+        self.images = images;
+        self.texts = texts;
+        // The text being edited might have been removed
+        self.text_draft = None;
+        self.mark_unsaved();
     }
-}
 
-/// This function takes ```&mut <T>``` and extension_filter: ```&str```, so a ```FileDialog``` can be opened.
-/// This function reads the contents of the file specified by the FileDialog.
-/// After reading the contents, it automaticly ```serde::Serialize```-es to type ```<T>```.
-/// Then the ```&mut T``` is replaced with the new ```<T>``` handle.
-fn read_file_into_memory<T: for<'a> Deserialize<'a>>(
-    memory: &mut T,
-    extension_filter: &str,
-) -> anyhow::Result<()> {
-    if let Some(path) = rfd::FileDialog::new()
-        .add_filter("Supported files", &[extension_filter])
-        .pick_file()
-    {
-        let deserialized_context = fs::read(path)?;
-        *memory = rmp_serde::from_slice::<T>(&miniz_oxide::inflate::decompress_to_vec(
-            &deserialized_context,
-        )?)?;
+    /// Sends the messages to the server in order, if there is an open connection.
+    fn send_messages(&mut self, messages: Vec<MessageType>) {
+        for message in messages {
+            self.send_message(message);
+        }
+    }
+
+    /// Sends a message to the server, if there is an open connection.
+    /// The connection is only closed if the sender thread has stopped, a full channel can't happen as it's unbounded.
+    fn send_message(&mut self, message: MessageType) {
+        if let Some(current_session) = &self.connection.current_session {
+            if let Err(err) = current_session.sender_to_server.send(message) {
+                dbg!(err);
+
+                current_session.cancel_connection();
+
+                self.connection.current_session = None;
+            }
+        }
     }
+}
 
-    Ok(())
+/// The ```ApplicationContext``` of version 0 workspaces, which were serialized without field names.
+#[derive(serde::Deserialize)]
+struct WorkspaceV0 {
+    lines: BrushMap,
+    /// The settings of the brushes, which were stored in arrays.
+    paintbrush: PaintBrushes,
+    file_session: Option<FileSession>,
+    open_tabs: HashSet<TabType>,
+    connection: ConnectionData,
+    /// The path the canvas was last exported to, exports are configured by the ```ExportSettings``` since.
+    _export_path: Option<PathBuf>,
+}
+
+/// Reads the body of a version 0 workspace, which had an export path in the place of the ```ExportSettings```.
+fn migrate_workspace_v0(body: &[u8]) -> anyhow::Result<ApplicationContext> {
+    let workspace: WorkspaceV0 = rmp_serde::from_slice(body)?;
+
+    Ok(ApplicationContext {
+        lines: workspace.lines,
+        paintbrush: workspace.paintbrush,
+        file_session: workspace.file_session,
+        open_tabs: workspace.open_tabs,
+        connection: workspace.connection,
+        ..Default::default()
+    })
 }
 
 /// Displays an error ```MessageBox```
@@ -462,3 +971,159 @@ fn display_error(err: impl ToString) {
         .set_buttons(rfd::MessageButtons::Ok)
         .show();
 }
+
+#[cfg(test)]
+mod tests {
+    use common_definitions::encode_raw_file;
+
+    use super::*;
+
+    /// The lines of version 0 files, with the brushes serialized as (Width, Color, BrushType).
+    type LinesV0 = Vec<(Vec<(f32, f32)>, (f32, Color32, BrushType))>;
+
+    /// The layout of the ```ApplicationContext``` in version 0 workspaces.
+    #[derive(serde::Serialize)]
+    struct WorkspaceV0Layout {
+        lines: LinesV0,
+        paintbrush: (BrushType, [f32; 5], [Color32; 5]),
+        file_session: Option<(PathBuf, String, NaiveDate)>,
+        open_tabs: Vec<TabType>,
+        connection: (String, String),
+        export_path: Option<PathBuf>,
+    }
+
+    fn test_workspace() -> ApplicationContext {
+        let mut context = ApplicationContext::default();
+
+        context.lines.push((
+            vec![Pos2::new(0.1, 0.2).into(), Pos2::new(0.3, 0.4).into()],
+            Brush {
+                width: 4.,
+                color: Color32::RED,
+                brush_type: BrushType::Pencil,
+                ..Default::default()
+            },
+        ));
+        context.paintbrush.brush_type = BrushType::Eraser;
+        context
+            .paintbrush
+            .brush_width
+            .insert(BrushType::Eraser, 12.);
+        context.open_tabs.insert(TabType::Canvas);
+        context.connection.username = String::from("user");
+
+        context
+    }
+
+    fn assert_same_workspace(read: &ApplicationContext, written: &ApplicationContext) {
+        assert_eq!(read.lines, written.lines);
+        assert_eq!(read.paintbrush.brush_type, written.paintbrush.brush_type);
+        assert_eq!(read.paintbrush.brush_width, written.paintbrush.brush_width);
+        assert_eq!(read.paintbrush.brush_color, written.paintbrush.brush_color);
+        assert_eq!(read.open_tabs, written.open_tabs);
+        assert_eq!(read.connection.username, written.connection.username);
+    }
+
+    #[test]
+    fn workspace_round_trip() {
+        let workspace = test_workspace();
+        let bytes = workspace.to_workspace_file().unwrap();
+
+        assert_same_workspace(
+            &ApplicationContext::from_workspace_file(&bytes).unwrap(),
+            &workspace,
+        );
+    }
+
+    #[test]
+    fn workspace_v0() {
+        let workspace = WorkspaceV0Layout {
+            lines: vec![(
+                vec![(0.1, 0.2), (0.3, 0.4)],
+                (4., Color32::RED, BrushType::Pencil),
+            )],
+            paintbrush: (BrushType::Eraser, [1., 2., 3., 4., 12.], [Color32::BLUE; 5]),
+            file_session: Some((
+                PathBuf::from("drawing.dbproject"),
+                String::from("drawing"),
+                NaiveDate::default(),
+            )),
+            open_tabs: vec![TabType::Canvas],
+            connection: (String::from("[::1]:3004"), String::from("user")),
+            export_path: Some(PathBuf::from("drawing.png")),
+        };
+        let bytes = encode_raw_file(0, None, &rmp_serde::to_vec(&workspace).unwrap()).unwrap();
+
+        let context = ApplicationContext::from_workspace_file(&bytes).unwrap();
+
+        assert_eq!(context.lines, test_workspace().lines);
+        assert_eq!(context.paintbrush.brush_type, BrushType::Eraser);
+        assert_eq!(
+            context
+                .paintbrush
+                .brush_width
+                .get(&BrushType::Eraser)
+                .copied(),
+            Some(12.)
+        );
+        assert_eq!(
+            context
+                .paintbrush
+                .brush_width
+                .get(&BrushType::Marker)
+                .copied(),
+            Some(4.)
+        );
+        assert_eq!(
+            context
+                .paintbrush
+                .brush_color
+                .get(&BrushType::Stamp)
+                .copied(),
+            None
+        );
+        assert_eq!(
+            context
+                .paintbrush
+                .brush_color
+                .get(&BrushType::Pencil)
+                .copied(),
+            Some(Color32::BLUE)
+        );
+        assert_eq!(
+            context.file_session.map(|session| session.project_name),
+            Some(String::from("drawing"))
+        );
+        assert!(context.open_tabs.contains(&TabType::Canvas));
+        assert_eq!(context.connection.username, "user");
+    }
+
+    #[test]
+    fn legacy_brush_arrays() {
+        // The stamp brush was the sixth brush, inserted before the eraser
+        let legacy = (
+            BrushType::Stamp,
+            [1., 2., 3., 4., 5., 12.],
+            [Color32::RED; 6],
+        );
+        let paintbrush: PaintBrushes =
+            rmp_serde::from_slice(&rmp_serde::to_vec(&legacy).unwrap()).unwrap();
+
+        assert_eq!(paintbrush.brush_type, BrushType::Stamp);
+        assert_eq!(paintbrush.brush_width.get(&BrushType::Stamp), Some(&5.));
+        assert_eq!(paintbrush.brush_width.get(&BrushType::Eraser), Some(&12.));
+        assert_eq!(paintbrush.brush_width.get(&BrushType::Text), None);
+
+        let legacy = (
+            BrushType::Text,
+            [1., 2., 3., 4., 5., 6., 7., 12.],
+            [Color32::RED; 8],
+        );
+        let paintbrush: PaintBrushes =
+            rmp_serde::from_slice(&rmp_serde::to_vec(&legacy).unwrap()).unwrap();
+
+        assert_eq!(paintbrush.brush_width.get(&BrushType::Text), Some(&7.));
+        assert_eq!(paintbrush.brush_width.get(&BrushType::Eraser), Some(&12.));
+        assert_eq!(paintbrush.get_current_brush().width, 7.);
+    }
+}
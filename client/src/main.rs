@@ -4,10 +4,8 @@ use eframe::NativeOptions;
 #[tokio::main]
 async fn main() -> eframe::Result<()> {
     /* TODO:
-        Add textures for painting
         Create a voice call library
         Create the ability to have more boards at once
-        Make it so that exporting PNGs doesnt also export the client's cursor
     */
 
     #[cfg(debug_assertions)]
@@ -23,8 +21,6 @@ async fn main() -> eframe::Result<()> {
         Box::new(|cc| {
             let application = Application::new(cc);
 
-            // cc.egui_ctx.load_texture("_paint_graffiti", ImageData::Color(Arc::new(ColorImage::example())), TextureOptions::LINEAR_REPEAT);
-
             Ok(Box::new(application))
         }),
     )
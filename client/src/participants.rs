@@ -0,0 +1,95 @@
+use common_definitions::{MessageType, Uuid};
+use egui::{Align2, Color32, FontId, Painter, Rect, RichText, Ui, Vec2};
+
+use crate::{cursors::client_color, ApplicationContext};
+
+impl ApplicationContext {
+    /// Starts following the viewport of the client, the camera is moved to the client's last known viewport right away.
+    /// Following ```None``` stops following.
+    fn follow_client(&mut self, client: Option<Uuid>) {
+        self.connection.followed_client = client;
+
+        if let Some(viewport) = client.and_then(|id| self.connection.client_viewports.get(&id)) {
+            self.show_canvas_area(*viewport);
+        }
+    }
+
+    /// Moves the camera of every other connected client to the area of the canvas this client is looking at.
+    fn summon_to_viewport(&mut self) {
+        if let Some(viewport) = self.visible_canvas_area {
+            self.send_messages(vec![MessageType::SummonToViewport(viewport)]);
+        }
+    }
+
+    /// Displays the list of the connected clients, with the option to follow their viewport.
+    /// ```own_id``` is the ```Uuid``` of this client, which can't be followed.
+    pub fn participants_ui(&mut self, ui: &mut Ui, own_id: Uuid) {
+        ui.label("Participants");
+
+        let mut clients: Vec<(Uuid, String)> = self
+            .connection
+            .connected_clients
+            .iter()
+            .map(|(id, (username, _))| (*id, username.clone()))
+            .collect();
+
+        clients.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut followed_client = self.connection.followed_client;
+
+        for (id, username) in clients {
+            ui.horizontal(|ui| {
+                if id == own_id {
+                    ui.label(format!("{username} (You)"));
+
+                    return;
+                }
+
+                // The name has the color of the user's cursor
+                ui.label(RichText::new(&username).color(client_color(id)));
+
+                let is_followed = followed_client == Some(id);
+
+                if ui
+                    .selectable_label(is_followed, "Follow")
+                    .on_hover_text("Moves the canvas along with the user's view, until the canvas is moved by hand.")
+                    .clicked()
+                {
+                    followed_client = (!is_followed).then_some(id);
+                }
+            });
+        }
+
+        if followed_client != self.connection.followed_client {
+            self.follow_client(followed_client);
+        }
+
+        if ui
+            .button("Summon Everyone To My View")
+            .on_hover_text("Moves the canvas of every other user to the area you are looking at.")
+            .clicked()
+        {
+            self.summon_to_viewport();
+        }
+    }
+
+    /// Displays the name of the followed client at the top of the Canvas tab.
+    pub fn draw_follow_indicator(&self, painter: &Painter, rect: Rect) {
+        let Some(username) = self
+            .connection
+            .followed_client
+            .and_then(|id| self.connection.connected_clients.get(&id))
+            .map(|(username, _)| username)
+        else {
+            return;
+        };
+
+        painter.text(
+            rect.center_top() + Vec2::new(0., 10.),
+            Align2::CENTER_TOP,
+            format!("Following {username}"),
+            FontId::proportional(16.),
+            Color32::GRAY,
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use egui::{lerp, Color32, Context, Event, Mesh, Pos2, Shape, TouchPhase};
+
+use crate::ApplicationContext;
+
+/// The pointer speed (In points per second) at which the simulated pressure reaches its minimum.
+const MIN_PRESSURE_SPEED: f32 = 3000.;
+
+/// The smallest pressure simulated from the pointer's speed.
+const MIN_SIMULATED_PRESSURE: f32 = 0.3;
+
+/// How much of the previous point's pressure is kept when simulating the pressure, so that the width changes smoothly.
+const PRESSURE_SMOOTHING: f32 = 0.7;
+
+/// The width of the anti-aliased edges of the variable width strokes, in points.
+const FEATHERING: f32 = 1.;
+
+impl ApplicationContext {
+    /// Updates the last pressure reported by the pen, this is reset when the pen is lifted.
+    pub fn update_pen_force(&mut self, ctx: &Context) {
+        ctx.input(|input| {
+            for event in &input.events {
+                if let Event::Touch { phase, force, .. } = event {
+                    self.pen_force = match phase {
+                        TouchPhase::End | TouchPhase::Cancel => None,
+                        _ => force.or(self.pen_force),
+                    };
+                }
+            }
+        });
+    }
+}
+
+/// Returns the pressure of the point being drawn.
+/// The pen's pressure is used if the input device reports it, otherwise the pressure is simulated from the pointer's speed (Faster strokes are thinner).
+pub fn pointer_pressure(
+    ctx: &Context,
+    pen_force: Option<f32>,
+    previous_pressure: Option<f32>,
+) -> f32 {
+    if let Some(force) = pen_force {
+        return force.clamp(0., 1.);
+    }
+
+    let speed = ctx.input(|input| input.pointer.velocity().length());
+    let pressure = 1. - (speed / MIN_PRESSURE_SPEED).min(1.) * (1. - MIN_SIMULATED_PRESSURE);
+
+    match previous_pressure {
+        Some(previous_pressure) => lerp(pressure..=previous_pressure, PRESSURE_SMOOTHING),
+        None => pressure,
+    }
+}
+
+/// Creates the ```Shape``` of a stroke whose width changes along the line, ```widths``` contains the width at every point.
+/// The stroke is tessellated into a triangle strip, with transparent feathering on its edges for anti-aliasing.
+pub fn variable_width_stroke(points: &[Pos2], widths: &[f32], color: Color32) -> Shape {
+    let mut mesh = Mesh::default();
+
+    for (idx, (point, width)) in points.iter().zip(widths).enumerate() {
+        // The normal is perpendicular to the line connecting the neighbouring points, so that the joints are mitered
+        let previous_point = points[idx.saturating_sub(1)];
+        let next_point = points[(idx + 1).min(points.len() - 1)];
+        let normal = (next_point - previous_point).normalized().rot90();
+
+        let half_width = width / 2.;
+        let first_vertex = mesh.vertices.len() as u32;
+
+        mesh.colored_vertex(
+            *point - normal * (half_width + FEATHERING),
+            Color32::TRANSPARENT,
+        );
+        mesh.colored_vertex(*point - normal * half_width, color);
+        mesh.colored_vertex(*point + normal * half_width, color);
+        mesh.colored_vertex(
+            *point + normal * (half_width + FEATHERING),
+            Color32::TRANSPARENT,
+        );
+
+        if idx > 0 {
+            let previous_vertex = first_vertex - 4;
+
+            for offset in 0..3 {
+                mesh.add_triangle(
+                    previous_vertex + offset,
+                    previous_vertex + offset + 1,
+                    first_vertex + offset,
+                );
+                mesh.add_triangle(
+                    previous_vertex + offset + 1,
+                    first_vertex + offset + 1,
+                    first_vertex + offset,
+                );
+            }
+        }
+    }
+
+    Shape::mesh(mesh)
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use common_definitions::{read_file_metadata, FileKind, FileMetadata};
+use egui::{Context, Sense, Ui, Vec2};
+
+use crate::{display_error, Application};
+
+/// The maximum number of files the recent files list contains.
+const MAX_RECENT_FILES: usize = 10;
+
+/// The size of the thumbnails displayed in the recent files dialog, in points.
+const THUMBNAIL_DISPLAY_SIZE: f32 = 96.;
+
+/// A file which was opened or saved recently.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct RecentFile {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The kind of the document the file contains.
+    pub kind: FileKind,
+}
+
+impl RecentFile {
+    /// Returns the file's name.
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// This struct contains the list of the recent files, the most recent file being the first.
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct RecentFiles {
+    /// The recent files.
+    files: Vec<RecentFile>,
+
+    /// Whether the recent files dialog is open.
+    #[serde(skip)]
+    dialog_open: bool,
+
+    /// The metadata headers of the recent files, these are read once when the files are first displayed in the dialog.
+    #[serde(skip)]
+    previews: HashMap<PathBuf, Option<FileMetadata>>,
+}
+
+impl RecentFiles {
+    /// Moves the file to the top of the list, or inserts it if it isn't in the list.
+    pub fn add(&mut self, path: PathBuf, kind: FileKind) {
+        self.files.retain(|recent_file| recent_file.path != path);
+        self.previews.remove(&path);
+
+        self.files.insert(0, RecentFile { path, kind });
+        self.files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Removes every file from the list.
+    pub fn clear(&mut self) {
+        self.files.clear();
+        self.previews.clear();
+    }
+
+    /// Returns the metadata header of the file, reading it if it hasn't been read yet.
+    /// Returns ```None``` if the file can't be read or it was saved without a header.
+    fn preview(&mut self, path: &Path) -> Option<&FileMetadata> {
+        self.previews
+            .entry(path.to_path_buf())
+            .or_insert_with(|| read_file_metadata(path).ok().flatten())
+            .as_ref()
+    }
+}
+
+impl Application {
+    /// Displays the ```Open Recent``` menu.
+    pub fn recent_files_menu(&mut self, ui: &mut Ui) {
+        ui.menu_button("Open Recent", |ui| {
+            let mut opened_file: Option<RecentFile> = None;
+
+            if self.recent_files.files.is_empty() {
+                ui.label("No recent files");
+            }
+
+            for recent_file in &self.recent_files.files {
+                if ui
+                    .button(recent_file.file_name())
+                    .on_hover_text(recent_file.path.display().to_string())
+                    .clicked()
+                {
+                    opened_file = Some(recent_file.clone());
+                    ui.close_menu();
+                }
+            }
+
+            ui.separator();
+
+            if ui.button("Show Thumbnails").clicked() {
+                self.recent_files.dialog_open = true;
+                ui.close_menu();
+            }
+
+            if ui.button("Clear Recent Files").clicked() {
+                self.recent_files.clear();
+                ui.close_menu();
+            }
+
+            if let Some(recent_file) = opened_file {
+                self.open_recent_file(recent_file);
+            }
+        });
+    }
+
+    /// This function displays the recent files dialog with the files' thumbnails, if it was opened.
+    pub fn recent_files_dialog(&mut self, ctx: &Context) {
+        let mut dialog_open = self.recent_files.dialog_open;
+        let mut opened_file: Option<RecentFile> = None;
+
+        egui::Window::new("Recent Files")
+            .open(&mut dialog_open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for recent_file in self.recent_files.files.clone() {
+                        let preview = self.recent_files.preview(&recent_file.path);
+
+                        let response = ui
+                            .horizontal(|ui| {
+                                let (thumbnail_rect, _) = ui.allocate_exact_size(
+                                    Vec2::splat(THUMBNAIL_DISPLAY_SIZE),
+                                    Sense::hover(),
+                                );
+
+                                match preview.and_then(|metadata| {
+                                    metadata
+                                        .thumbnail
+                                        .clone()
+                                        .map(|thumbnail| (metadata.saved_at, thumbnail))
+                                }) {
+                                    Some((saved_at, thumbnail)) => {
+                                        // The save time is part of the uri, so that the thumbnail gets reloaded if the file is saved again
+                                        egui::Image::from_bytes(
+                                            format!(
+                                                "bytes://recent_file/{}/{saved_at}",
+                                                recent_file.path.display()
+                                            ),
+                                            thumbnail,
+                                        )
+                                        .paint_at(ui, thumbnail_rect);
+                                    }
+                                    None => {
+                                        ui.painter().rect_filled(
+                                            thumbnail_rect,
+                                            2.,
+                                            ui.visuals().extreme_bg_color,
+                                        );
+                                    }
+                                }
+
+                                ui.vertical(|ui| {
+                                    let name = preview
+                                        .map(|metadata| metadata.properties.name.clone())
+                                        .filter(|name| !name.is_empty())
+                                        .unwrap_or_else(|| recent_file.file_name());
+
+                                    ui.strong(name);
+                                    ui.label(match recent_file.kind {
+                                        FileKind::Image => "Image",
+                                        FileKind::Workspace => "Workspace",
+                                    });
+
+                                    if let Some(metadata) = preview {
+                                        ui.label(format!(
+                                            "Modified: {}",
+                                            metadata.saved_at.format("%Y-%m-%d %H:%M")
+                                        ));
+
+                                        if !metadata.properties.author.is_empty() {
+                                            ui.label(format!(
+                                                "Author: {}",
+                                                metadata.properties.author
+                                            ));
+                                        }
+                                    }
+
+                                    ui.weak(recent_file.path.display().to_string());
+                                });
+                            })
+                            .response
+                            .interact(Sense::click());
+
+                        if response.clicked() {
+                            opened_file = Some(recent_file);
+                        }
+
+                        ui.separator();
+                    }
+                });
+            });
+
+        self.recent_files.dialog_open &= dialog_open;
+
+        if let Some(recent_file) = opened_file {
+            self.recent_files.dialog_open = false;
+            self.open_recent_file(recent_file);
+        }
+    }
+
+    /// Opens a recent file, after the user has confirmed that the unsaved changes can be discarded.
+    /// Files which can't be opened are removed from the list.
+    fn open_recent_file(&mut self, recent_file: RecentFile) {
+        if !self.confirm_discard() {
+            return;
+        }
+
+        if let Err(err) = self.open_file(recent_file.path.clone(), recent_file.kind) {
+            self.recent_files
+                .files
+                .retain(|file| file.path != recent_file.path);
+
+            display_error(err);
+        }
+    }
+}
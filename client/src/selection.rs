@@ -0,0 +1,757 @@
+use common_definitions::{LinePos, MessageType, Uuid};
+use egui::{
+    emath::{self, Rot2},
+    vec2, Color32, CursorIcon, Key, Modifiers, Painter, Pos2, Rect, Response, Sense, Stroke, Ui,
+    Vec2,
+};
+
+use crate::{camera::canvas_zoom, eraser::is_line_hit, ApplicationContext};
+
+/// The size of the transform handles, in points.
+const HANDLE_SIZE: f32 = 10.;
+
+/// The distance of the rotation handle from the top of the selection, in points.
+const ROTATE_HANDLE_OFFSET: f32 = 24.;
+
+/// The distance (In points) from a line at which clicking still selects it.
+const SELECT_TOLERANCE: f32 = 4.;
+
+/// The offset of the duplicated lines from the original ones, in canvas units.
+pub(crate) const DUPLICATE_OFFSET: f32 = 0.02;
+
+/// The smallest factor a selection can be scaled by in a single drag.
+const MIN_SCALE: f32 = 0.01;
+
+/// The step the rotation snaps to while holding Shift (15 degrees).
+const ROTATION_SNAP: f32 = std::f32::consts::PI / 12.;
+
+/// The transformation applied to the selected lines by dragging them or one of their handles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionTransform {
+    Move,
+    /// Scaling around the corner (In canvas units) opposite to the dragged one.
+    Scale(Pos2),
+    /// Rotation around the center (In canvas units) of the selection.
+    Rotate(Pos2),
+}
+
+impl SelectionTransform {
+    /// Returns the function which transforms a point (In canvas units), when dragging from ```start``` to ```current```.
+    /// If ```snap``` is set the rotation snaps to ```ROTATION_SNAP```.
+    fn point_transform(self, start: Pos2, current: Pos2, snap: bool) -> impl Fn(Pos2) -> Pos2 {
+        let (origin, rotation, translation) = match self {
+            Self::Move => (Pos2::ZERO, Rot2::IDENTITY, current - start),
+            Self::Scale(anchor) => {
+                let start_distance = anchor.distance(start);
+
+                let scale = if start_distance > 0. {
+                    (anchor.distance(current) / start_distance).max(MIN_SCALE)
+                } else {
+                    1.
+                };
+
+                (anchor, scale * Rot2::IDENTITY, Vec2::ZERO)
+            }
+            Self::Rotate(center) => {
+                let mut angle = (current - center).angle() - (start - center).angle();
+
+                if snap {
+                    angle = (angle / ROTATION_SNAP).round() * ROTATION_SNAP;
+                }
+
+                (center, Rot2::from_angle(angle), Vec2::ZERO)
+            }
+        };
+
+        move |pos| origin + rotation * (pos - origin) + translation
+    }
+}
+
+/// The drag in progress with the ```BrushType::None``` brush.
+pub enum SelectionDrag {
+    /// The lasso being drawn around the lines to select, in canvas units.
+    Lasso(Vec<Pos2>),
+    /// The selected lines are being transformed.
+    Transform {
+        transform: SelectionTransform,
+        /// The position the drag started at, in canvas units.
+        start: Pos2,
+        /// The points of the selected lines before the drag, in the order of ```selected_lines```.
+        original_lines: Vec<Vec<LinePos>>,
+    },
+    /// The selected shape is being moved.
+    MoveShape {
+        id: Uuid,
+        /// The position the drag started at, in canvas units.
+        start: Pos2,
+        /// The points of the shape before the drag.
+        original_points: Vec<Pos2>,
+    },
+}
+
+/// Returns whether the point is inside the polygon, the polygon's edges may cross each other.
+pub(crate) fn is_in_polygon(point: Pos2, polygon: &[Pos2]) -> bool {
+    let mut inside = false;
+
+    for idx in 0..polygon.len() {
+        let (a, b) = (polygon[idx], polygon[(idx + 1) % polygon.len()]);
+
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+/// Returns the points of the line transformed with the function, the pressure of the points is kept.
+pub(crate) fn transform_line(points: &[LinePos], transform: impl Fn(Pos2) -> Pos2) -> Vec<LinePos> {
+    points
+        .iter()
+        .map(|pos| LinePos::with_pressure(transform(Pos2::from(*pos)), pos.pressure()))
+        .collect()
+}
+
+impl ApplicationContext {
+    /// Returns the area (In points) covered by the selected lines, including their width.
+    fn selection_rect(&self, to_screen: emath::RectTransform) -> Option<Rect> {
+        self.lines
+            .iter()
+            .filter(|(points, _)| self.selected_lines.contains(points))
+            .map(|(points, brush)| {
+                let width = brush.width * canvas_zoom(to_screen);
+
+                points.iter().fold(Rect::NOTHING, |rect, pos| {
+                    rect.union(Rect::from_center_size(
+                        to_screen * Pos2::from(*pos),
+                        Vec2::splat(width * pos.pressure()),
+                    ))
+                })
+            })
+            .reduce(Rect::union)
+    }
+
+    /// Returns the topmost line under the position (In points), the lines of hidden and locked layers are skipped.
+    fn line_at(&self, pos: Pos2, to_screen: emath::RectTransform) -> Option<&Vec<LinePos>> {
+        self.lines
+            .iter()
+            .rev()
+            .find(|(points, brush)| {
+                self.layers.is_editable(brush.layer)
+                    && is_line_hit(points, brush.width, (pos, pos), SELECT_TOLERANCE, to_screen)
+            })
+            .map(|(points, _)| points)
+    }
+
+    /// Replaces the points of the selected lines, and the selection with the new points.
+    /// ```lines``` contains the current and the new points of the lines.
+    fn replace_selected_lines(&mut self, lines: Vec<(Vec<LinePos>, Vec<LinePos>)>) {
+        for (points, new_points) in lines {
+            if let Some(line) = self.lines.iter_mut().find(|line| line.0 == points) {
+                line.0 = new_points.clone();
+            }
+
+            if let Some(selected) = self
+                .selected_lines
+                .iter_mut()
+                .find(|selected| **selected == points)
+            {
+                *selected = new_points;
+            }
+        }
+    }
+
+    /// Returns the messages which replace the ```original_lines``` with the selected lines (In the same order) after transforming them.
+    /// Lines which weren't changed, or which were removed by other users during the drag, aren't sent.
+    fn transform_messages(&self, original_lines: Vec<Vec<LinePos>>) -> Vec<MessageType> {
+        original_lines
+            .into_iter()
+            .zip(&self.selected_lines)
+            .filter(|(original, transformed)| {
+                original != *transformed && self.lines.iter().any(|line| line.0 == **transformed)
+            })
+            .map(|(original, transformed)| {
+                MessageType::TransformLine((original, transformed.clone()))
+            })
+            .collect()
+    }
+
+    /// Deletes the selected lines, and sends the deletion to the server if there is an open connection.
+    pub fn delete_selection(&mut self) {
+        let selected_lines = std::mem::take(&mut self.selected_lines);
+
+        self.lines.retain(|line| !selected_lines.contains(&line.0));
+
+        self.send_messages(
+            selected_lines
+                .into_iter()
+                .map(|points| MessageType::ModifyLine((points, None)))
+                .collect(),
+        );
+    }
+
+    /// Places a copy of the selected lines next to them, the copies become the selection.
+    /// The copies are sent to the server if there is an open connection.
+    fn duplicate_selection(&mut self) {
+        let copies: Vec<_> = self
+            .lines
+            .iter()
+            .filter(|(points, _)| self.selected_lines.contains(points))
+            .map(|(points, brush)| {
+                (
+                    transform_line(points, |pos| pos + Vec2::splat(DUPLICATE_OFFSET)),
+                    *brush,
+                )
+            })
+            .collect();
+
+        self.selected_lines = copies.iter().map(|(points, _)| points.clone()).collect();
+
+        self.send_messages(copies.iter().cloned().map(MessageType::AddLine).collect());
+
+        // The last, empty line is the one being drawn, so the copies are inserted before it
+        let insert_idx = self
+            .lines
+            .iter()
+            .rposition(|line| !line.0.is_empty())
+            .map_or(0, |idx| idx + 1);
+
+        self.lines.splice(insert_idx..insert_idx, copies);
+    }
+
+    /// Handles selecting lines and shapes with the ```BrushType::None``` brush, and transforming the selected lines.
+    /// Lines are selected by clicking them, or by drawing a lasso around them on the empty canvas. Holding Shift adds to the selection.
+    /// The selection is moved by dragging it, scaled with its corner handles and rotated with the handle above it (Holding Shift snaps the rotation).
+    /// Delete removes and Ctrl+D duplicates the selection, Ctrl+A selects every line and Escape clears the selection.
+    /// A shape is selected by clicking it, and moved by dragging it, the selected shape is removed with Delete.
+    /// Returns whether the lines were modified.
+    pub fn selection_interaction(
+        &mut self,
+        ui: &mut Ui,
+        response: &Response,
+        to_screen: emath::RectTransform,
+    ) -> bool {
+        let from_screen = to_screen.inverse();
+
+        // Lines removed by other users (Or by undoing), and the lines of hidden or locked layers can't stay selected
+        if !matches!(self.selection_drag, Some(SelectionDrag::Transform { .. })) {
+            let (lines, layers) = (&self.lines, &self.layers);
+
+            self.selected_lines.retain(|points| {
+                lines
+                    .iter()
+                    .any(|line| line.0 == *points && layers.is_editable(line.1.layer))
+            });
+        }
+
+        // Shapes removed by other users (Or by undoing), and the shapes of hidden or locked layers can't stay selected
+        if !matches!(self.selection_drag, Some(SelectionDrag::MoveShape { .. })) {
+            self.selected_shape = self.selected_shape.filter(|id| {
+                self.shapes
+                    .get(id)
+                    .is_some_and(|shape| self.layers.is_editable(shape.layer))
+            });
+        }
+
+        let (shift, clicked, press_origin) = ui.input(|input| {
+            (
+                input.modifiers.shift,
+                input.pointer.primary_clicked(),
+                input.pointer.press_origin(),
+            )
+        });
+
+        let mut modified = false;
+
+        if !ui.ctx().wants_keyboard_input() {
+            let (delete, duplicate, select_all, escape) = ui.input_mut(|input| {
+                (
+                    input.key_pressed(Key::Delete) || input.key_pressed(Key::Backspace),
+                    input.consume_key(Modifiers::COMMAND, Key::D),
+                    input.consume_key(Modifiers::COMMAND, Key::A),
+                    input.key_pressed(Key::Escape),
+                )
+            });
+
+            if select_all {
+                self.selected_lines = self
+                    .lines
+                    .iter()
+                    .filter(|line| !line.0.is_empty() && self.layers.is_editable(line.1.layer))
+                    .map(|line| line.0.clone())
+                    .collect();
+            }
+
+            if escape {
+                self.selected_lines.clear();
+                self.selected_shape = None;
+            }
+
+            if let Some(id) = self.selected_shape.filter(|_| delete) {
+                if self.selection_drag.is_none() {
+                    self.delete_shape(id);
+
+                    modified = true;
+                }
+            }
+
+            if !self.selected_lines.is_empty() && self.selection_drag.is_none() {
+                if delete {
+                    self.delete_selection();
+
+                    modified = true;
+                } else if duplicate {
+                    self.duplicate_selection();
+
+                    modified = true;
+                }
+            }
+        }
+
+        let selection_rect = self.selection_rect(to_screen);
+
+        // The handles are registered after the canvas, so that they get the pointer first
+        if let Some(rect) = selection_rect {
+            let center = from_screen * rect.center();
+
+            let mut handles: Vec<(Pos2, SelectionTransform, CursorIcon)> = vec![
+                (
+                    rect.left_top(),
+                    SelectionTransform::Scale(from_screen * rect.right_bottom()),
+                    CursorIcon::ResizeNwSe,
+                ),
+                (
+                    rect.right_top(),
+                    SelectionTransform::Scale(from_screen * rect.left_bottom()),
+                    CursorIcon::ResizeNeSw,
+                ),
+                (
+                    rect.left_bottom(),
+                    SelectionTransform::Scale(from_screen * rect.right_top()),
+                    CursorIcon::ResizeNeSw,
+                ),
+                (
+                    rect.right_bottom(),
+                    SelectionTransform::Scale(from_screen * rect.left_top()),
+                    CursorIcon::ResizeNwSe,
+                ),
+            ];
+
+            handles.push((
+                rect.center_top() - vec2(0., ROTATE_HANDLE_OFFSET),
+                SelectionTransform::Rotate(center),
+                CursorIcon::Alias,
+            ));
+
+            for (idx, (handle_pos, transform, cursor)) in handles.into_iter().enumerate() {
+                let handle_response = ui
+                    .interact(
+                        Rect::from_center_size(handle_pos, Vec2::splat(HANDLE_SIZE)),
+                        ui.id().with(("selection_handle", idx)),
+                        Sense::drag(),
+                    )
+                    .on_hover_cursor(cursor);
+
+                if handle_response.drag_started() {
+                    self.selection_drag = Some(SelectionDrag::Transform {
+                        transform,
+                        start: from_screen * press_origin.unwrap_or(handle_pos),
+                        original_lines: self.selected_lines.clone(),
+                    });
+                }
+            }
+
+            if response.hovered() && response.hover_pos().is_some_and(|pos| rect.contains(pos)) {
+                ui.ctx().set_cursor_icon(CursorIcon::Move);
+            }
+        }
+
+        if response.drag_started() {
+            let start = press_origin.or(response.interact_pointer_pos());
+
+            self.selection_drag = match (start, selection_rect) {
+                (Some(start), Some(rect)) if rect.contains(start) => {
+                    Some(SelectionDrag::Transform {
+                        transform: SelectionTransform::Move,
+                        start: from_screen * start,
+                        original_lines: self.selected_lines.clone(),
+                    })
+                }
+                (Some(start), _) => match self.shape_at(start, SELECT_TOLERANCE, to_screen) {
+                    Some(id) => {
+                        self.selected_lines.clear();
+                        self.selected_shape = Some(id);
+
+                        Some(SelectionDrag::MoveShape {
+                            id,
+                            start: from_screen * start,
+                            original_points: self.shapes[&id].points.clone(),
+                        })
+                    }
+                    None => Some(SelectionDrag::Lasso(vec![from_screen * start])),
+                },
+                (None, _) => None,
+            };
+        }
+
+        let pointer_pos = ui.ctx().pointer_interact_pos().map(|pos| from_screen * pos);
+
+        let drag_stopped = ui.input(|input| !input.pointer.primary_down());
+
+        match self.selection_drag.take() {
+            Some(SelectionDrag::Lasso(mut lasso)) => {
+                if let Some(pos) = pointer_pos {
+                    if lasso.last() != Some(&pos) {
+                        lasso.push(pos);
+                    }
+                }
+
+                if !drag_stopped {
+                    self.selection_drag = Some(SelectionDrag::Lasso(lasso));
+                } else {
+                    if !shift {
+                        self.selected_lines.clear();
+                        self.selected_shape = None;
+                    }
+
+                    for (points, brush) in &self.lines {
+                        if !points.is_empty()
+                            && self.layers.is_editable(brush.layer)
+                            && !self.selected_lines.contains(points)
+                            && points
+                                .iter()
+                                .all(|pos| is_in_polygon(Pos2::from(*pos), &lasso))
+                        {
+                            self.selected_lines.push(points.clone());
+                        }
+                    }
+                }
+            }
+            Some(SelectionDrag::Transform {
+                transform,
+                start,
+                original_lines,
+            }) => {
+                if let Some(pos) = pointer_pos {
+                    let point_transform = transform.point_transform(start, pos, shift);
+
+                    let lines = original_lines
+                        .iter()
+                        .zip(&self.selected_lines)
+                        .map(|(original, current)| {
+                            (current.clone(), transform_line(original, &point_transform))
+                        })
+                        .collect();
+
+                    self.replace_selected_lines(lines);
+                }
+
+                if !drag_stopped {
+                    self.selection_drag = Some(SelectionDrag::Transform {
+                        transform,
+                        start,
+                        original_lines,
+                    });
+                } else {
+                    let messages = self.transform_messages(original_lines);
+
+                    if !messages.is_empty() {
+                        self.send_messages(messages);
+
+                        modified = true;
+                    }
+                }
+            }
+            Some(SelectionDrag::MoveShape {
+                id,
+                start,
+                original_points,
+            }) => {
+                if let Some(pos) = pointer_pos {
+                    self.move_shape(id, &original_points, pos - start);
+                }
+
+                if !drag_stopped {
+                    self.selection_drag = Some(SelectionDrag::MoveShape {
+                        id,
+                        start,
+                        original_points,
+                    });
+                } else if let Some(shape) = self
+                    .shapes
+                    .get(&id)
+                    .filter(|shape| shape.points != original_points)
+                {
+                    self.send_messages(vec![MessageType::ModifyShape((id, Some(shape.clone())))]);
+
+                    modified = true;
+                }
+            }
+            None => {
+                if clicked && response.hovered() {
+                    let clicked_line = response
+                        .hover_pos()
+                        .and_then(|pos| self.line_at(pos, to_screen))
+                        .cloned();
+
+                    match clicked_line {
+                        Some(points) if shift => {
+                            match self
+                                .selected_lines
+                                .iter()
+                                .position(|selected| *selected == points)
+                            {
+                                Some(idx) => {
+                                    self.selected_lines.remove(idx);
+                                }
+                                None => self.selected_lines.push(points),
+                            }
+                        }
+                        Some(points) => {
+                            self.selected_lines = vec![points];
+                            self.selected_shape = None;
+                        }
+                        None if shift => (),
+                        None => {
+                            self.selected_lines.clear();
+                            self.selected_shape = response
+                                .hover_pos()
+                                .and_then(|pos| self.shape_at(pos, SELECT_TOLERANCE, to_screen));
+                        }
+                    }
+                }
+            }
+        }
+
+        if modified {
+            self.add_undo_point();
+        }
+
+        modified
+    }
+
+    /// Draws the outline of the line under the pointer, the selection with its handles, the selected shape's outline and the lasso being drawn.
+    pub fn draw_selection(&self, ui: &Ui, painter: &Painter, to_screen: emath::RectTransform) {
+        let selection_color = Color32::LIGHT_BLUE;
+
+        if let Some(hovered_line) = ui
+            .ctx()
+            .pointer_hover_pos()
+            .filter(|_| self.selection_drag.is_none())
+            .and_then(|pos| self.line_at(pos, to_screen))
+        {
+            let line_rect = Rect::from_points(
+                &hovered_line
+                    .iter()
+                    .map(|pos| to_screen * Pos2::from(*pos))
+                    .collect::<Vec<Pos2>>(),
+            );
+
+            painter.rect(
+                line_rect,
+                1.,
+                Color32::from_rgba_unmultiplied(0, 255, 0, 80),
+                Stroke::new(2., Color32::GREEN),
+            );
+        }
+
+        if let Some(SelectionDrag::Lasso(lasso)) = &self.selection_drag {
+            painter.add(egui::Shape::closed_line(
+                lasso.iter().map(|pos| to_screen * *pos).collect(),
+                Stroke::new(1., selection_color),
+            ));
+        }
+
+        if let Some(shape) = self.selected_shape.and_then(|id| self.shapes.get(&id)) {
+            let shape_rect = Rect::from_points(
+                &shape
+                    .outline()
+                    .iter()
+                    .map(|pos| to_screen * *pos)
+                    .collect::<Vec<Pos2>>(),
+            );
+
+            painter.rect_stroke(
+                shape_rect.expand(shape.stroke_width * canvas_zoom(to_screen) / 2.),
+                0.,
+                Stroke::new(1., selection_color),
+            );
+        }
+
+        let Some(rect) = self.selection_rect(to_screen) else {
+            return;
+        };
+
+        let stroke = Stroke::new(1., selection_color);
+        let rotate_handle = rect.center_top() - vec2(0., ROTATE_HANDLE_OFFSET);
+
+        painter.rect_stroke(rect, 0., stroke);
+        painter.line_segment([rect.center_top(), rotate_handle], stroke);
+        painter.circle_filled(rotate_handle, HANDLE_SIZE / 2., selection_color);
+
+        for corner in [
+            rect.left_top(),
+            rect.right_top(),
+            rect.left_bottom(),
+            rect.right_bottom(),
+        ] {
+            painter.rect_filled(
+                Rect::from_center_size(corner, Vec2::splat(HANDLE_SIZE)),
+                1.,
+                selection_color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use common_definitions::Brush;
+
+    use super::*;
+
+    fn line(points: &[(f32, f32)]) -> Vec<LinePos> {
+        points
+            .iter()
+            .map(|(x, y)| Pos2::new(*x, *y).into())
+            .collect()
+    }
+
+    fn assert_same_points(a: &[LinePos], b: &[LinePos]) {
+        assert_eq!(a.len(), b.len());
+
+        for (a, b) in a.iter().zip(b) {
+            assert!(Pos2::from(*a).distance(Pos2::from(*b)) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn transform_keeps_pressure() {
+        let points = vec![
+            LinePos::with_pressure(Pos2::new(0., 0.), 0.25),
+            LinePos::with_pressure(Pos2::new(1., 2.), 0.75),
+        ];
+
+        let moved = transform_line(&points, |pos| pos + Vec2::new(3., 4.));
+
+        assert_eq!(
+            moved,
+            vec![
+                LinePos::with_pressure(Pos2::new(3., 4.), 0.25),
+                LinePos::with_pressure(Pos2::new(4., 6.), 0.75),
+            ]
+        );
+    }
+
+    #[test]
+    fn point_transforms() {
+        let points = line(&[(1., 1.), (3., 1.)]);
+
+        let moved =
+            SelectionTransform::Move.point_transform(Pos2::new(0., 0.), Pos2::new(2., -1.), false);
+
+        assert_same_points(
+            &transform_line(&points, moved),
+            &line(&[(3., 0.), (5., 0.)]),
+        );
+
+        // Dragging the corner twice as far from the anchor doubles the size
+        let scaled = SelectionTransform::Scale(Pos2::new(1., 1.)).point_transform(
+            Pos2::new(3., 1.),
+            Pos2::new(5., 1.),
+            false,
+        );
+
+        assert_same_points(
+            &transform_line(&points, scaled),
+            &line(&[(1., 1.), (5., 1.)]),
+        );
+
+        let rotated = SelectionTransform::Rotate(Pos2::new(2., 1.)).point_transform(
+            Pos2::new(3., 1.),
+            Pos2::new(2., 2.),
+            false,
+        );
+
+        assert_same_points(
+            &transform_line(&points, rotated),
+            &line(&[(2., 0.), (2., 2.)]),
+        );
+    }
+
+    #[test]
+    fn rotation_snaps() {
+        let center = Pos2::ZERO;
+        let start = Pos2::new(1., 0.);
+        let current = center + Vec2::angled(FRAC_PI_2 - 0.1);
+
+        let snapped = SelectionTransform::Rotate(center).point_transform(start, current, true);
+
+        assert!(snapped(start).distance(Pos2::new(0., 1.)) < 1e-5);
+    }
+
+    #[test]
+    fn lasso_contains_points() {
+        let square = [
+            Pos2::new(0., 0.),
+            Pos2::new(10., 0.),
+            Pos2::new(10., 10.),
+            Pos2::new(0., 10.),
+        ];
+
+        assert!(is_in_polygon(Pos2::new(5., 5.), &square));
+        assert!(!is_in_polygon(Pos2::new(15., 5.), &square));
+        assert!(!is_in_polygon(Pos2::new(5., -1.), &square));
+
+        // The concave part of the lasso is outside of it
+        let concave = [
+            Pos2::new(0., 0.),
+            Pos2::new(10., 0.),
+            Pos2::new(10., 10.),
+            Pos2::new(5., 2.),
+            Pos2::new(0., 10.),
+        ];
+
+        assert!(is_in_polygon(Pos2::new(5., 1.), &concave));
+        assert!(!is_in_polygon(Pos2::new(5., 5.), &concave));
+
+        assert!(!is_in_polygon(Pos2::new(0., 0.), &[]));
+    }
+
+    #[test]
+    fn transformed_lines_are_sent() {
+        let mut context = ApplicationContext::default();
+
+        let (unchanged, original, transformed, removed) = (
+            line(&[(0., 0.), (1., 1.)]),
+            line(&[(2., 2.), (3., 3.)]),
+            line(&[(4., 4.), (5., 5.)]),
+            line(&[(6., 6.), (7., 7.)]),
+        );
+
+        context.lines = vec![
+            (unchanged.clone(), Brush::default()),
+            (transformed.clone(), Brush::default()),
+        ];
+        context.selected_lines = vec![unchanged.clone(), transformed.clone(), removed.clone()];
+
+        let messages = context.transform_messages(vec![
+            unchanged,
+            original.clone(),
+            line(&[(8., 8.), (9., 9.)]),
+        ]);
+
+        assert_eq!(messages.len(), 1);
+
+        let MessageType::TransformLine((from, to)) = &messages[0] else {
+            panic!("Expected a TransformLine message, got {:?}", messages[0]);
+        };
+
+        assert_eq!(*from, original);
+        assert_eq!(*to, transformed);
+    }
+}
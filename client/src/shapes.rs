@@ -0,0 +1,359 @@
+use common_definitions::{constrain_shape_point, CanvasShape, MessageType, ShapeKind, Uuid};
+use egui::{emath, Color32, Key, Mesh, Painter, PointerButton, Pos2, Response, Stroke, Ui};
+
+use crate::{
+    camera::canvas_zoom, eraser::segments_distance, selection::is_in_polygon, ApplicationContext,
+};
+
+/// The color shapes are filled with by default.
+pub fn default_shape_fill() -> Color32 {
+    Color32::from_rgba_unmultiplied(255, 255, 255, 64)
+}
+
+/// Returns twice the signed area of the triangle ```a```, ```b```, ```c```, the sign depends on the winding of the triangle.
+fn cross(a: Pos2, b: Pos2, c: Pos2) -> f32 {
+    let (ab, ac) = (b - a, c - a);
+
+    ab.x * ac.y - ab.y * ac.x
+}
+
+/// Returns whether the point is inside the triangle ```a```, ```b```, ```c```, or on its edges.
+fn is_in_triangle(point: Pos2, a: Pos2, b: Pos2, c: Pos2) -> bool {
+    let signs = [cross(a, b, point), cross(b, c, point), cross(c, a, point)];
+
+    !(signs.iter().any(|sign| *sign < 0.) && signs.iter().any(|sign| *sign > 0.))
+}
+
+/// Splits the polygon into triangles with ear clipping, so that concave polygons can be filled too.
+/// Returns the indices of the triangles' corners.
+fn triangulate_polygon(points: &[Pos2]) -> Vec<u32> {
+    let winding: f32 = (0..points.len())
+        .map(|idx| cross(Pos2::ZERO, points[idx], points[(idx + 1) % points.len()]))
+        .sum::<f32>()
+        .signum();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut indices = vec![];
+
+    while remaining.len() >= 3 {
+        let count = remaining.len();
+        let corners = |idx: usize| {
+            (
+                remaining[(idx + count - 1) % count],
+                remaining[idx],
+                remaining[(idx + 1) % count],
+            )
+        };
+
+        let ear = (0..count).find(|idx| {
+            let (prev, current, next) = corners(*idx);
+            let (a, b, c) = (points[prev], points[current], points[next]);
+
+            cross(a, b, c) * winding > 0.
+                && !remaining.iter().any(|other| {
+                    ![prev, current, next].contains(other)
+                        && is_in_triangle(points[*other], a, b, c)
+                })
+        });
+
+        // Self-intersecting polygons might run out of ears, their remaining corners are clipped in order
+        let ear = ear.unwrap_or(0);
+        let (prev, current, next) = corners(ear);
+
+        indices.extend([prev as u32, current as u32, next as u32]);
+
+        remaining.remove(ear);
+    }
+
+    indices
+}
+
+/// Returns whether the segment ```segment``` (In points) comes within ```radius``` of the shape's outline, or touches its inside if it's filled.
+pub(crate) fn is_shape_hit(
+    shape: &CanvasShape,
+    segment: (Pos2, Pos2),
+    radius: f32,
+    to_screen: emath::RectTransform,
+) -> bool {
+    let outline: Vec<Pos2> = shape.outline().iter().map(|pos| to_screen * *pos).collect();
+    let radius = radius + shape.stroke_width * canvas_zoom(to_screen) / 2.;
+
+    if shape.fill_color().is_some() && is_in_polygon(segment.1, &outline) {
+        return true;
+    }
+
+    let edge_count = if shape.kind.is_closed() {
+        outline.len()
+    } else {
+        outline.len().saturating_sub(1)
+    };
+
+    (0..edge_count).any(|idx| {
+        let edge = (outline[idx], outline[(idx + 1) % outline.len()]);
+
+        segments_distance(edge, segment) <= radius
+    })
+}
+
+/// Creates the ```egui::Shape``` of a ```CanvasShape```.
+pub fn shape_to_screen(shape: &CanvasShape, to_screen: emath::RectTransform) -> egui::Shape {
+    let mut outline: Vec<Pos2> = shape.outline().iter().map(|pos| to_screen * *pos).collect();
+    let zoom = canvas_zoom(to_screen);
+    let stroke = Stroke::new(shape.stroke_width * zoom, shape.stroke_color);
+
+    let mut shapes = vec![];
+
+    if let Some(fill) = shape.fill_color() {
+        let mut mesh = Mesh::default();
+
+        for pos in &outline {
+            mesh.colored_vertex(*pos, fill);
+        }
+
+        mesh.indices = triangulate_polygon(&outline);
+
+        shapes.push(egui::Shape::mesh(mesh));
+    }
+
+    let head_length = shape.arrow_head_length() * zoom / to_screen.scale().min_elem();
+
+    if let Some(head) = shape.arrow_head(head_length) {
+        let head = head.map(|pos| to_screen * pos);
+
+        // The line ends at the base of the head, so that it doesn't poke through the tip
+        if let Some(end) = outline.last_mut() {
+            *end = head[1].lerp(head[2], 0.5);
+        }
+
+        shapes.push(egui::Shape::convex_polygon(
+            head.to_vec(),
+            shape.stroke_color,
+            Stroke::NONE,
+        ));
+    }
+
+    if shape.kind.is_closed() {
+        shapes.push(egui::Shape::closed_line(outline, stroke));
+    } else {
+        shapes.push(egui::Shape::line(outline, stroke));
+    }
+
+    egui::Shape::Vec(shapes)
+}
+
+impl ApplicationContext {
+    /// Creates a ```CanvasShape``` with the current shape settings from the points (In canvas units).
+    fn create_shape(&self, points: Vec<Pos2>) -> CanvasShape {
+        let brush = self.paintbrush.get_current_brush();
+
+        CanvasShape {
+            kind: self.paintbrush.shape_kind,
+            points,
+            stroke_width: brush.width,
+            stroke_color: brush.color,
+            fill: self
+                .paintbrush
+                .fill_shapes
+                .then_some(self.paintbrush.shape_fill),
+            layer: self.paintbrush.layer,
+        }
+    }
+
+    /// Places the shape on the canvas, and sends it to the server if there is an open connection.
+    fn place_shape(&mut self, points: Vec<Pos2>) {
+        let shape = self.create_shape(points);
+        let id = Uuid::new_v4();
+
+        self.send_messages(vec![MessageType::AddShape((id, shape.clone()))]);
+
+        self.shapes.insert(id, shape);
+        self.add_undo_point();
+    }
+
+    /// Returns the topmost shape under the position (In points).
+    pub(crate) fn shape_at(
+        &self,
+        pos: Pos2,
+        tolerance: f32,
+        to_screen: emath::RectTransform,
+    ) -> Option<Uuid> {
+        self.shapes
+            .iter()
+            .rev()
+            .find(|(_, shape)| {
+                self.layers.is_editable(shape.layer)
+                    && is_shape_hit(shape, (pos, pos), tolerance, to_screen)
+            })
+            .map(|(id, _)| *id)
+    }
+
+    /// Moves the points of the shape by ```offset``` (In canvas units) from ```original_points```.
+    pub(crate) fn move_shape(&mut self, id: Uuid, original_points: &[Pos2], offset: emath::Vec2) {
+        if let Some(shape) = self.shapes.get_mut(&id) {
+            shape.points = original_points.iter().map(|pos| *pos + offset).collect();
+        }
+    }
+
+    /// Deletes the shape, and sends the deletion to the server if there is an open connection.
+    pub(crate) fn delete_shape(&mut self, id: Uuid) {
+        if self.shapes.shift_remove(&id).is_some() {
+            self.send_messages(vec![MessageType::ModifyShape((id, None))]);
+        }
+
+        if self.selected_shape == Some(id) {
+            self.selected_shape = None;
+        }
+    }
+
+    /// Handles drawing the shapes with the ```BrushType::Shape``` brush, and draws the preview of the shape being drawn.
+    /// Rectangles, ellipses, lines and arrows are dragged from corner to corner, polygons are placed vertex by vertex with clicks and finished with a double click or Enter.
+    /// Holding Shift constrains the shape, see ```constrain_shape_point```.
+    /// Returns whether a shape was placed.
+    pub fn shape_interaction(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        painter: &Painter,
+        to_screen: emath::RectTransform,
+    ) -> bool {
+        let from_screen = to_screen.inverse();
+        let kind = self.paintbrush.shape_kind;
+
+        let (constrain, escape_pressed, enter_pressed, clicked, double_clicked) =
+            ui.input(|input| {
+                (
+                    input.modifiers.shift,
+                    input.key_pressed(Key::Escape),
+                    input.key_pressed(Key::Enter),
+                    input.pointer.primary_clicked(),
+                    input.pointer.button_double_clicked(PointerButton::Primary),
+                )
+            });
+
+        if escape_pressed {
+            self.shape_draft = None;
+        }
+
+        // Polygon edges are constrained to the last vertex, the other shapes to the point the drag started at
+        let anchor = match (kind, &self.shape_draft) {
+            (ShapeKind::Polygon, Some(draft)) => draft.last().copied(),
+            (_, Some(draft)) => draft.first().copied(),
+            _ => None,
+        };
+
+        let pointer_pos = response.hover_pos().or(response.interact_pointer_pos());
+        let constrained_pos = pointer_pos.map(|pos| {
+            let pos = self.snap_point(from_screen * pos, to_screen);
+
+            match anchor {
+                Some(anchor) if constrain => constrain_shape_point(kind, anchor, pos),
+                _ => pos,
+            }
+        });
+
+        let mut placed_shape = false;
+
+        if kind == ShapeKind::Polygon {
+            if double_clicked || enter_pressed {
+                if let Some(vertices) = self.shape_draft.take() {
+                    if vertices.len() >= 3 {
+                        self.place_shape(vertices);
+
+                        placed_shape = true;
+                    }
+                }
+            } else if clicked && response.hovered() {
+                if let Some(pos) = constrained_pos {
+                    self.shape_draft.get_or_insert_with(Vec::new).push(pos);
+                }
+            }
+        } else {
+            if response.drag_started() {
+                self.shape_draft = constrained_pos.map(|pos| vec![pos, pos]);
+            }
+
+            if response.dragged() {
+                if let (Some(draft), Some(pos)) = (self.shape_draft.as_mut(), constrained_pos) {
+                    draft.truncate(1);
+                    draft.push(pos);
+                }
+            }
+
+            if response.drag_stopped() {
+                if let Some(points) = self.shape_draft.take() {
+                    // Shapes without a size would be invisible
+                    if points.first() != points.last() {
+                        self.place_shape(points);
+
+                        placed_shape = true;
+                    }
+                }
+            }
+        }
+
+        if let Some(draft) = &self.shape_draft {
+            let mut preview_points = draft.clone();
+
+            // The polygon's next edge follows the pointer
+            if kind == ShapeKind::Polygon {
+                preview_points.extend(constrained_pos);
+            }
+
+            painter.add(shape_to_screen(
+                &self.create_shape(preview_points),
+                to_screen,
+            ));
+        }
+
+        placed_shape
+    }
+
+    /// Displays the settings of the ```BrushType::Shape``` brush.
+    pub fn shape_settings(&mut self, ui: &mut Ui) {
+        let kind_name: &'static str = self.paintbrush.shape_kind.into();
+
+        ui.label("Shape");
+        egui::ComboBox::from_id_salt("shape_kind")
+            .selected_text(kind_name)
+            .show_ui(ui, |ui| {
+                for kind in ShapeKind::ALL {
+                    let kind_name: &'static str = kind.into();
+
+                    if ui
+                        .selectable_value(&mut self.paintbrush.shape_kind, kind, kind_name)
+                        .changed()
+                    {
+                        self.shape_draft = None;
+                    }
+                }
+            });
+
+        if self.paintbrush.shape_kind.is_closed() {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.paintbrush.fill_shapes, "Fill");
+                ui.add_enabled_ui(self.paintbrush.fill_shapes, |ui| {
+                    ui.color_edit_button_srgba(&mut self.paintbrush.shape_fill);
+                });
+            });
+        }
+
+        ui.label(match self.paintbrush.shape_kind {
+            ShapeKind::Polygon => "Click to place the vertices, double click or press Enter to finish. Hold Shift to snap the edges to 45 degrees.",
+            _ => "Drag to draw the shape, hold Shift to constrain its proportions.",
+        });
+    }
+
+    /// Returns the visible shapes placed on the canvas with the opacity of their layers applied, in the order they are drawn in.
+    pub fn canvas_shapes(&self) -> Vec<CanvasShape> {
+        self.layers.layered_shapes(self.shapes.values()).collect()
+    }
+
+    /// Draws the shapes placed on the visible layers.
+    pub fn draw_shapes(&self, painter: &Painter, to_screen: emath::RectTransform) {
+        painter.extend(
+            self.layers
+                .layered_shapes(self.shapes.values())
+                .map(|shape| shape_to_screen(&shape, to_screen)),
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use common_definitions::LinePos;
+use egui::{Pos2, Ui};
+
+/// The largest radius of the ```SmoothingMode::LazyBrush``` string, in points.
+const MAX_LAZY_BRUSH_RADIUS: f32 = 40.;
+
+/// The ways the pointer's position can be smoothed while drawing.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SmoothingMode {
+    /// Every pointer sample is drawn as is.
+    Off,
+    /// Every new point is moved towards the pointer by a fraction of the distance.
+    #[default]
+    Exponential,
+    /// The brush is pulled behind the pointer on a string, so it only moves once the pointer is further away than the string's length.
+    LazyBrush,
+}
+
+impl SmoothingMode {
+    /// Every ```SmoothingMode```, with the name it's displayed with.
+    const ALL: [(Self, &'static str); 3] = [
+        (Self::Off, "Off"),
+        (Self::Exponential, "Exponential"),
+        (Self::LazyBrush, "Lazy brush"),
+    ];
+
+    /// Returns the name the ```SmoothingMode``` is displayed with.
+    fn name(&self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(mode, _)| mode == self)
+            .map(|(_, name)| *name)
+            .unwrap_or_default()
+    }
+}
+
+/// The smoothing and simplification settings of the lines being drawn.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct StrokeSmoothing {
+    /// The way the pointer's position is smoothed.
+    pub mode: SmoothingMode,
+    /// The strength of the smoothing, in the range of ```0.0..=1.0```.
+    pub strength: f32,
+    /// The largest distance (In points) a removed point can be from the simplified line, ```0.0``` turns simplification off.
+    pub simplify_tolerance: f32,
+}
+
+impl Default for StrokeSmoothing {
+    fn default() -> Self {
+        Self {
+            mode: SmoothingMode::default(),
+            strength: 0.4,
+            simplify_tolerance: 0.5,
+        }
+    }
+}
+
+impl StrokeSmoothing {
+    /// Returns the position the next point of the line should be placed at, all of the positions are in points.
+    /// Returns ```None``` if no point should be placed, because the lazy brush hasn't been pulled.
+    pub fn smooth(&self, last_point: Option<Pos2>, pointer_pos: Pos2) -> Option<Pos2> {
+        let Some(last_point) = last_point else {
+            return Some(pointer_pos);
+        };
+
+        match self.mode {
+            SmoothingMode::Off => Some(pointer_pos),
+            SmoothingMode::Exponential => {
+                Some(last_point.lerp(pointer_pos, 1. - self.strength * 0.9))
+            }
+            SmoothingMode::LazyBrush => {
+                let radius = self.strength * MAX_LAZY_BRUSH_RADIUS;
+                let offset = pointer_pos - last_point;
+
+                (offset.length() > radius).then(|| pointer_pos - offset.normalized() * radius)
+            }
+        }
+    }
+
+    /// Displays the smoothing settings.
+    pub fn settings_ui(&mut self, ui: &mut Ui) {
+        ui.label("Smoothing");
+        egui::ComboBox::from_id_salt("stroke_smoothing")
+            .selected_text(self.mode.name())
+            .show_ui(ui, |ui| {
+                for (mode, name) in SmoothingMode::ALL {
+                    ui.selectable_value(&mut self.mode, mode, name);
+                }
+            });
+
+        if self.mode != SmoothingMode::Off {
+            ui.label("Smoothing strength");
+            ui.add(egui::Slider::new(&mut self.strength, 0.0..=1.0));
+        }
+
+        ui.label("Simplification tolerance");
+        ui.add(egui::Slider::new(&mut self.simplify_tolerance, 0.0..=5.0).suffix(" pt"))
+            .on_hover_text("The points which are closer to the simplified line than this are removed once the line is finished.");
+    }
+}
+
+/// Returns the distance of the point from the line segment between ```start``` and ```end```.
+pub(crate) fn distance_to_segment(point: Pos2, start: Pos2, end: Pos2) -> f32 {
+    let segment = end - start;
+    let length_sq = segment.length_sq();
+
+    let t = if length_sq > 0. {
+        ((point - start).dot(segment) / length_sq).clamp(0., 1.)
+    } else {
+        0.
+    };
+
+    point.distance(start + segment * t)
+}
+
+/// Simplifies the line with the Ramer-Douglas-Peucker algorithm, removing the points which are closer than ```tolerance``` to the simplified line.
+/// The tolerance is in the same unit as the points, the first and the last points are always kept.
+pub fn simplify_line(points: &[LinePos], tolerance: f32) -> Vec<LinePos> {
+    if points.len() <= 2 || tolerance <= 0. {
+        return points.to_vec();
+    }
+
+    let last_idx = points.len() - 1;
+
+    let mut kept_points = vec![false; points.len()];
+    kept_points[0] = true;
+    kept_points[last_idx] = true;
+
+    // The ranges are processed with a stack instead of recursion, so that long lines can't overflow the stack
+    let mut ranges = vec![(0, last_idx)];
+
+    while let Some((start_idx, end_idx)) = ranges.pop() {
+        let (start, end) = (Pos2::from(points[start_idx]), Pos2::from(points[end_idx]));
+
+        let farthest_point = (start_idx + 1..end_idx)
+            .map(|idx| (idx, distance_to_segment(points[idx].into(), start, end)))
+            .max_by(|(_, distance), (_, other_distance)| distance.total_cmp(other_distance));
+
+        if let Some((idx, distance)) = farthest_point {
+            if distance > tolerance {
+                kept_points[idx] = true;
+
+                ranges.push((start_idx, idx));
+                ranges.push((idx, end_idx));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(kept_points)
+        .filter_map(|(point, is_kept)| is_kept.then_some(*point))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(points: &[(f32, f32)]) -> Vec<LinePos> {
+        points
+            .iter()
+            .map(|(x, y)| Pos2::new(*x, *y).into())
+            .collect()
+    }
+
+    /// A long, wavy line with small waves on a large curve.
+    fn wavy_line() -> Vec<LinePos> {
+        (0..200)
+            .map(|idx| {
+                let x = idx as f32;
+
+                Pos2::new(x, (x / 7.).sin() * 3. + (x / 50.).cos() * 20.).into()
+            })
+            .collect()
+    }
+
+    /// Returns the distance of the point from the closest segment of the line.
+    fn distance_to_line(point: Pos2, line: &[LinePos]) -> f32 {
+        line.windows(2)
+            .map(|segment| distance_to_segment(point, segment[0].into(), segment[1].into()))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn collinear_points_collapse() {
+        let points = line(&[(0., 0.), (1., 1.), (2., 2.), (5., 5.), (10., 10.)]);
+
+        assert_eq!(simplify_line(&points, 0.1), line(&[(0., 0.), (10., 10.)]));
+    }
+
+    #[test]
+    fn stays_within_tolerance() {
+        let points = wavy_line();
+
+        for tolerance in [0.5, 1., 2.5, 10.] {
+            let simplified = simplify_line(&points, tolerance);
+
+            assert!(simplified.len() < points.len());
+
+            for point in &points {
+                assert!(distance_to_line((*point).into(), &simplified) <= tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_endpoints() {
+        let points = wavy_line();
+
+        for tolerance in [0.5, 100.] {
+            let simplified = simplify_line(&points, tolerance);
+
+            assert_eq!(simplified.first(), points.first());
+            assert_eq!(simplified.last(), points.last());
+        }
+
+        // A closed line keeps its start, which is also its end
+        let closed = line(&[(0., 0.), (5., 0.), (5., 5.), (0., 0.)]);
+
+        assert_eq!(simplify_line(&closed, 100.), line(&[(0., 0.), (0., 0.)]));
+    }
+
+    #[test]
+    fn zero_tolerance_keeps_every_point() {
+        let points = wavy_line();
+
+        assert_eq!(simplify_line(&points, 0.), points);
+
+        let collinear = line(&[(0., 0.), (1., 1.), (2., 2.)]);
+
+        assert_eq!(simplify_line(&collinear, 0.), collinear);
+    }
+}
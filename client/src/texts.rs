@@ -0,0 +1,252 @@
+use common_definitions::{BrushType, CanvasText, MessageType, TextFont, Uuid};
+use egui::{
+    emath, Align2, FontFamily, FontId, Id, Margin, Order, Painter, PointerButton, Pos2, Rect,
+    Response, Ui,
+};
+
+use crate::{camera::canvas_zoom, ApplicationContext};
+
+/// The text being written or edited on the canvas.
+pub struct TextDraft {
+    /// The ```Uuid``` of the text.
+    id: Uuid,
+    /// The text with the changes made since the editing started.
+    text: CanvasText,
+    /// Whether the text is new, or an existing text is being edited.
+    is_new: bool,
+    /// Whether the text editor should get the keyboard focus, this is set when the editing starts.
+    request_focus: bool,
+}
+
+/// Returns the ```FontId``` egui draws the text with, at the zoom of the canvas.
+fn font_id(text: &CanvasText, zoom: f32) -> FontId {
+    let family = match text.font {
+        TextFont::Proportional => FontFamily::Proportional,
+        TextFont::Monospace => FontFamily::Monospace,
+    };
+
+    FontId::new(text.size * zoom, family)
+}
+
+impl ApplicationContext {
+    /// Returns the texts placed on the canvas, in the order they are drawn in.
+    pub fn canvas_texts(&self) -> Vec<CanvasText> {
+        self.texts.values().cloned().collect()
+    }
+
+    /// Returns the ```Uuid``` of the topmost text under the position (In points).
+    fn text_at(&self, ui: &Ui, pos: Pos2, to_screen: emath::RectTransform) -> Option<Uuid> {
+        let zoom = canvas_zoom(to_screen);
+
+        self.texts.iter().rev().find_map(|(id, text)| {
+            let size = ui.fonts(|fonts| {
+                fonts
+                    .layout_no_wrap(text.text.clone(), font_id(text, zoom), text.color)
+                    .size()
+            });
+
+            Rect::from_min_size(to_screen * text.position, size)
+                .contains(pos)
+                .then_some(*id)
+        })
+    }
+
+    /// Starts writing a new text, or editing an existing one if there is one under the pointer.
+    /// New texts are only placed with the ```BrushType::Text``` brush, existing texts can also be edited by double clicking them with the ```BrushType::None``` brush.
+    pub fn text_interaction(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        to_screen: emath::RectTransform,
+    ) {
+        let Some(pointer_pos) = response.hover_pos() else {
+            return;
+        };
+
+        let (clicked, double_clicked) = ui.input(|input| {
+            (
+                input.pointer.primary_clicked(),
+                input.pointer.button_double_clicked(PointerButton::Primary),
+            )
+        });
+
+        if !clicked {
+            return;
+        }
+
+        let is_text_brush = self.paintbrush.brush_type == BrushType::Text;
+
+        match self.text_at(ui, pointer_pos, to_screen) {
+            Some(id) if is_text_brush || double_clicked => {
+                // The text brush takes the style of the edited text, so that the settings show (And change) it
+                if is_text_brush {
+                    let text = &self.texts[&id];
+
+                    self.paintbrush.text_size = text.size;
+                    self.paintbrush.text_font = text.font;
+                    *self.paintbrush.get_mut_current_brush().1 = text.color;
+                }
+
+                self.text_draft = Some(TextDraft {
+                    id,
+                    text: self.texts[&id].clone(),
+                    is_new: false,
+                    request_focus: true,
+                });
+            }
+            None if is_text_brush => {
+                self.text_draft = Some(TextDraft {
+                    id: Uuid::new_v4(),
+                    text: CanvasText {
+                        text: String::new(),
+                        position: to_screen.inverse() * pointer_pos,
+                        size: self.paintbrush.text_size,
+                        color: self.paintbrush.get_current_brush().color,
+                        font: self.paintbrush.text_font,
+                    },
+                    is_new: true,
+                    request_focus: true,
+                });
+            }
+            _ => (),
+        }
+    }
+
+    /// Displays the editor of the text being written, and finishes the editing once the editor loses the focus (Like when clicking outside of it, or pressing Escape).
+    /// Returns whether the canvas was modified.
+    pub fn edit_text_draft(&mut self, ui: &Ui, to_screen: emath::RectTransform) -> bool {
+        let Some(draft) = self.text_draft.as_mut() else {
+            return false;
+        };
+
+        let zoom = canvas_zoom(to_screen);
+        let font_id = font_id(&draft.text, zoom);
+        let edit_id = Id::new("canvas_text_edit");
+
+        // The editor is a bit wider than the text, so that there is space for the cursor
+        let width = ui.fonts(|fonts| {
+            fonts
+                .layout_no_wrap(draft.text.text.clone(), font_id.clone(), draft.text.color)
+                .size()
+                .x
+        }) + draft.text.size * zoom;
+
+        let response = egui::Area::new(edit_id.with("area"))
+            .fixed_pos(to_screen * draft.text.position)
+            .order(Order::Foreground)
+            .show(ui.ctx(), |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut draft.text.text)
+                        .id(edit_id)
+                        .font(font_id)
+                        .text_color(draft.text.color)
+                        .frame(false)
+                        .margin(Margin::ZERO)
+                        .desired_rows(1)
+                        .desired_width(width),
+                )
+            })
+            .inner;
+
+        if draft.request_focus {
+            response.request_focus();
+
+            draft.request_focus = false;
+        }
+
+        if response.lost_focus() {
+            return self.finish_text_draft();
+        }
+
+        false
+    }
+
+    /// Places the text being written onto the canvas, and sends it to the server if there is an open connection.
+    /// Texts left empty are deleted.
+    /// Returns whether the canvas was modified.
+    fn finish_text_draft(&mut self) -> bool {
+        let Some(TextDraft {
+            id, text, is_new, ..
+        }) = self.text_draft.take()
+        else {
+            return false;
+        };
+
+        let is_empty = text.text.trim().is_empty();
+
+        match (is_new, is_empty) {
+            (true, true) => return false,
+            (true, false) => {
+                self.send_messages(vec![MessageType::AddText((id, text.clone()))]);
+
+                self.texts.insert(id, text);
+            }
+            (false, true) => {
+                self.send_messages(vec![MessageType::ModifyText((id, None))]);
+
+                self.texts.shift_remove(&id);
+            }
+            (false, false) => {
+                if self.texts.get(&id) == Some(&text) {
+                    return false;
+                }
+
+                self.send_messages(vec![MessageType::ModifyText((id, Some(text.clone())))]);
+
+                self.texts.insert(id, text);
+            }
+        }
+
+        self.add_undo_point();
+
+        true
+    }
+
+    /// Draws the texts placed on the canvas, except for the one being edited as the editor displays that.
+    pub fn draw_texts(&self, painter: &Painter, to_screen: emath::RectTransform) {
+        let edited_text = self.text_draft.as_ref().map(|draft| draft.id);
+        let zoom = canvas_zoom(to_screen);
+
+        for (id, text) in &self.texts {
+            if Some(*id) == edited_text {
+                continue;
+            }
+
+            painter.text(
+                to_screen * text.position,
+                Align2::LEFT_TOP,
+                &text.text,
+                font_id(text, zoom),
+                text.color,
+            );
+        }
+    }
+
+    /// Displays the settings of the ```BrushType::Text``` brush.
+    /// The settings are also applied to the text being edited.
+    pub fn text_settings(&mut self, ui: &mut Ui) {
+        let font_name: &'static str = self.paintbrush.text_font.into();
+
+        ui.label("Size");
+        ui.add(egui::Slider::new(&mut self.paintbrush.text_size, 6.0..=200.0).suffix(" pt"));
+
+        ui.label("Font");
+        egui::ComboBox::from_id_salt("text_font")
+            .selected_text(font_name)
+            .show_ui(ui, |ui| {
+                for font in TextFont::ALL {
+                    let font_name: &'static str = font.into();
+
+                    ui.selectable_value(&mut self.paintbrush.text_font, font, font_name);
+                }
+            });
+
+        ui.label("Click on the canvas to place a text, or on a text to edit it. Texts can also be edited by double clicking them without a brush.");
+
+        if let Some(draft) = self.text_draft.as_mut() {
+            draft.text.size = self.paintbrush.text_size;
+            draft.text.font = self.paintbrush.text_font;
+            draft.text.color = self.paintbrush.get_current_brush().color;
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use egui::{Color32, Pos2, Rect, Vec2};
+use strum::IntoStaticStr;
+use uuid::Uuid;
+
+use crate::DEFAULT_CANVAS_SCALE;
+
+/// The ```Uuid``` the background image is sent with, the background image is sent in ```ImageChunk```-s like the images placed on the canvas.
+pub const BACKGROUND_IMAGE_ID: Uuid = Uuid::max();
+
+/// The ways the background of the canvas can be displayed.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, IntoStaticStr, serde::Serialize, serde::Deserialize,
+)]
+pub enum BackgroundStyle {
+    /// The canvas is filled with a single color.
+    Solid,
+    /// The canvas has no background, this is how the canvas looked before backgrounds were added.
+    #[default]
+    Transparent,
+    /// The canvas is filled with a color, and horizontal lines are drawn on it like on ruled paper.
+    Ruled,
+    /// The canvas is filled with a color, and a grid of lines is drawn on it like on graph paper.
+    Graph,
+    /// An imported image is displayed below the drawing.
+    Image,
+}
+
+impl BackgroundStyle {
+    /// Every ```BackgroundStyle```, in the order they are displayed in.
+    pub const ALL: [Self; 5] = [
+        Self::Solid,
+        Self::Transparent,
+        Self::Ruled,
+        Self::Graph,
+        Self::Image,
+    ];
+}
+
+/// The background of the canvas, which is saved with the document and synced to the other users.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CanvasBackground {
+    /// The way the background is displayed.
+    pub style: BackgroundStyle,
+    /// The color of the paper, this is used by the ```BackgroundStyle::Solid```, ```BackgroundStyle::Ruled``` and ```BackgroundStyle::Graph``` styles.
+    pub color: Color32,
+    /// The color of the lines of the ```BackgroundStyle::Ruled``` and ```BackgroundStyle::Graph``` styles.
+    pub line_color: Color32,
+    /// The distance between the lines of the ```BackgroundStyle::Ruled``` and ```BackgroundStyle::Graph``` styles, in points.
+    pub line_spacing: f32,
+    /// The size of the page in points, if the canvas is bounded to a page.
+    /// The page starts at the canvas' origin, the background is only displayed on the page.
+    pub page_size: Option<Vec2>,
+}
+
+impl Default for CanvasBackground {
+    fn default() -> Self {
+        Self {
+            style: BackgroundStyle::default(),
+            color: Color32::WHITE,
+            line_color: Color32::from_rgb(170, 200, 230),
+            line_spacing: 32.,
+            page_size: None,
+        }
+    }
+}
+
+impl CanvasBackground {
+    /// Returns the area of the page in canvas units, or ```None``` if the canvas isn't bounded to a page.
+    pub fn page_rect(&self) -> Option<Rect> {
+        self.page_size
+            .map(|size| Rect::from_min_size(Pos2::ZERO, size / DEFAULT_CANVAS_SCALE))
+    }
+
+    /// Returns the color the background is filled with, this is ```None``` if the background is transparent or an image.
+    pub fn fill_color(&self) -> Option<Color32> {
+        match self.style {
+            BackgroundStyle::Solid | BackgroundStyle::Ruled | BackgroundStyle::Graph => {
+                Some(self.color)
+            }
+            BackgroundStyle::Transparent | BackgroundStyle::Image => None,
+        }
+    }
+}
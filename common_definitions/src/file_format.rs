@@ -0,0 +1,500 @@
+use std::{fs::File, io::Read, path::Path};
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use indexmap::IndexMap;
+use miniz_oxide::inflate::TINFLStatus;
+use uuid::Uuid;
+
+use crate::{Brush, CanvasBackground, CanvasImage, CanvasShape, CanvasText, Layers, LinePos};
+
+/// The bytes every ```.dbimg``` and ```.dbproject``` file starts with.
+pub const FILE_MAGIC: [u8; 4] = *b"DBRD";
+
+/// The version of the file format the application saves files with.
+/// Files saved before the container was introduced (Which are only the compressed body) are version 0.
+/// The bodies of version 0 files are serialized without field names, version 2 bodies are serialized with them, so that fields can be added without breaking older files.
+/// Version 1 was never released, so files of that version aren't read.
+pub const CURRENT_FORMAT_VERSION: u16 = 2;
+
+/// The unreleased format version, which serialized the container's body without field names.
+const UNRELEASED_FORMAT_VERSION: u16 = 1;
+
+/// The length of the magic bytes, the format version (```u16```) and the metadata header's length (```u32```).
+const PREAMBLE_LENGTH: usize = FILE_MAGIC.len() + 2 + 4;
+
+/// The length of the largest metadata header which is read, the header only contains the properties and a small thumbnail.
+const MAX_HEADER_LENGTH: usize = 16 * 1024 * 1024;
+
+/// The length of the largest decompressed body which is read, so that a small corrupted file can't decompress into an unbounded amount of memory.
+const MAX_BODY_LENGTH: usize = 1024 * 1024 * 1024;
+
+/// The kinds of files the application can save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum FileKind {
+    /// A ```.dbimg``` file, containing an ```ImageFile```.
+    #[default]
+    Image,
+    /// A ```.dbproject``` file, containing the whole workspace.
+    Workspace,
+}
+
+/// The metadata header of a file, this can be read without decompressing the body.
+/// The header is serialized with its field names, so that fields can be added without breaking older files.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FileMetadata {
+    /// The kind of the file.
+    pub kind: FileKind,
+    /// The version of the application which saved the file.
+    pub app_version: String,
+    /// The local time the file was saved at.
+    pub saved_at: NaiveDateTime,
+    /// The properties of the project.
+    #[serde(default)]
+    pub properties: ProjectProperties,
+    /// A small png rendering of the canvas.
+    #[serde(default)]
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+impl FileMetadata {
+    pub fn new(kind: FileKind) -> Self {
+        Self {
+            kind,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            saved_at: Local::now().naive_local(),
+            properties: ProjectProperties::default(),
+            thumbnail: None,
+        }
+    }
+}
+
+/// The properties of a project, which are editable by the user.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ProjectProperties {
+    /// The name of the project, if this is empty the file's name is used.
+    pub name: String,
+    /// The author of the project.
+    pub author: String,
+    /// The description of the project.
+    pub description: String,
+    /// The date the project was first saved at.
+    pub created: Option<NaiveDate>,
+}
+
+/// A file read from the container, the body is already decompressed but not deserialized, as it depends on the version.
+#[derive(Debug, Clone)]
+pub struct DecodedFile {
+    /// The format version the file was saved with.
+    pub version: u16,
+    /// The metadata header of the file, files saved with version 0 don't have one.
+    pub metadata: Option<FileMetadata>,
+    /// The decompressed, ```rmp_serde``` serialized body of the file.
+    pub body: Vec<u8>,
+}
+
+impl DecodedFile {
+    /// Returns an error if the file is of a different ```FileKind```.
+    /// Files saved with version 0 don't have a header, so their kind can only be checked by deserializing their body.
+    pub fn expect_kind(&self, kind: FileKind) -> anyhow::Result<()> {
+        match &self.metadata {
+            Some(metadata) if metadata.kind != kind => {
+                Err(anyhow::Error::msg(match metadata.kind {
+                    FileKind::Image => {
+                        "This file is an image, it should be opened with \"Open Image\"."
+                    }
+                    FileKind::Workspace => {
+                        "This file is a workspace, it should be opened with \"Open Workspace\"."
+                    }
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Wraps the serialized ```body``` into the container: the magic bytes, the format version, the metadata header and the compressed body.
+pub fn encode_file<T: serde::Serialize>(
+    metadata: &FileMetadata,
+    body: &T,
+) -> anyhow::Result<Vec<u8>> {
+    encode_raw_file(
+        CURRENT_FORMAT_VERSION,
+        Some(metadata),
+        &rmp_serde::to_vec_named(body)?,
+    )
+}
+
+/// Wraps an already serialized ```body``` into the container of the format ```version```.
+/// Version 0 files don't have a container, so they are only the compressed body, and the ```metadata``` is ignored.
+pub fn encode_raw_file(
+    version: u16,
+    metadata: Option<&FileMetadata>,
+    body: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let compressed_body = miniz_oxide::deflate::compress_to_vec(body, 10);
+
+    if version == 0 {
+        return Ok(compressed_body);
+    }
+
+    let metadata = metadata.ok_or_else(|| anyhow::Error::msg("The file's metadata is missing."))?;
+    let header = rmp_serde::to_vec_named(metadata)?;
+
+    let mut file = Vec::with_capacity(PREAMBLE_LENGTH + header.len() + compressed_body.len());
+
+    file.extend_from_slice(&FILE_MAGIC);
+    file.extend_from_slice(&version.to_be_bytes());
+    file.extend_from_slice(&(header.len() as u32).to_be_bytes());
+    file.extend_from_slice(&header);
+    file.extend(compressed_body);
+
+    Ok(file)
+}
+
+/// Reads the container, returning the version, the metadata header and the decompressed body of the file.
+/// Files without the magic bytes are read as version 0 files.
+pub fn decode_file(bytes: &[u8]) -> anyhow::Result<DecodedFile> {
+    let Some(container) = bytes.strip_prefix(&FILE_MAGIC) else {
+        return Ok(DecodedFile {
+            version: 0,
+            metadata: None,
+            body: decompress_body(bytes)?,
+        });
+    };
+
+    let (version, header, body) = split_container(container)?;
+
+    if version > CURRENT_FORMAT_VERSION {
+        return Err(anyhow::Error::msg(format!(
+            "The file was saved by a newer version of the application (Format version: {version}, supported version: {CURRENT_FORMAT_VERSION})."
+        )));
+    }
+
+    if version == UNRELEASED_FORMAT_VERSION {
+        return Err(anyhow::Error::msg(format!(
+            "The file was saved with an unsupported format version ({version})."
+        )));
+    }
+
+    Ok(DecodedFile {
+        version,
+        metadata: Some(rmp_serde::from_slice(header)?),
+        body: decompress_body(body)?,
+    })
+}
+
+/// Reads only the metadata header of the file, without decompressing its body.
+/// Returns ```None``` for files saved with version 0.
+pub fn read_metadata(bytes: &[u8]) -> anyhow::Result<Option<FileMetadata>> {
+    let Some(container) = bytes.strip_prefix(&FILE_MAGIC) else {
+        return Ok(None);
+    };
+
+    let (_, header, _) = split_container(container)?;
+
+    Ok(Some(rmp_serde::from_slice(header)?))
+}
+
+/// Reads only the metadata header of the file at ```path```, without reading the rest of the file.
+/// Returns ```None``` for files saved with version 0.
+pub fn read_file_metadata(path: &Path) -> anyhow::Result<Option<FileMetadata>> {
+    let mut file = File::open(path)?;
+    let mut preamble = [0; PREAMBLE_LENGTH];
+
+    // Version 0 files can be shorter than the preamble
+    if file.read_exact(&mut preamble).is_err() || !preamble.starts_with(&FILE_MAGIC) {
+        return Ok(None);
+    }
+
+    let (_, header_length) = parse_preamble(&preamble[FILE_MAGIC.len()..])?;
+
+    if header_length > MAX_HEADER_LENGTH {
+        return Err(anyhow::Error::msg(format!(
+            "The file's header is too large ({header_length} bytes, the limit is {MAX_HEADER_LENGTH} bytes)."
+        )));
+    }
+
+    // The header is read as it arrives, so its length alone can't allocate a large buffer
+    let mut header = Vec::new();
+    file.take(header_length as u64).read_to_end(&mut header)?;
+
+    if header.len() != header_length {
+        return Err(anyhow::Error::msg("The file is truncated."));
+    }
+
+    Ok(Some(rmp_serde::from_slice(&header)?))
+}
+
+/// Splits the container (Without the magic bytes) into the format version, the metadata header and the compressed body.
+fn split_container(container: &[u8]) -> anyhow::Result<(u16, &[u8], &[u8])> {
+    let (version, header_length) = parse_preamble(container)?;
+
+    let header = container
+        .get(6..6 + header_length)
+        .ok_or_else(|| anyhow::Error::msg("The file is truncated."))?;
+
+    Ok((version, header, &container[6 + header_length..]))
+}
+
+/// Reads the format version and the length of the metadata header from the start of the container (Without the magic bytes).
+fn parse_preamble(container: &[u8]) -> anyhow::Result<(u16, usize)> {
+    let (Some(version), Some(header_length)) = (container.get(0..2), container.get(2..6)) else {
+        return Err(anyhow::Error::msg("The file is truncated."));
+    };
+
+    Ok((
+        u16::from_be_bytes([version[0], version[1]]),
+        u32::from_be_bytes([
+            header_length[0],
+            header_length[1],
+            header_length[2],
+            header_length[3],
+        ]) as usize,
+    ))
+}
+
+/// Decompresses the body of a file, which can't be longer than ```MAX_BODY_LENGTH``` bytes.
+fn decompress_body(body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(body, MAX_BODY_LENGTH).map_err(|err| {
+        anyhow::Error::msg(match err.status {
+            TINFLStatus::HasMoreOutput => format!(
+                "The file is too large (The limit is {MAX_BODY_LENGTH} bytes decompressed)."
+            ),
+            _ => String::from("The file is not a drawing board file, or it is corrupted."),
+        })
+    })
+}
+
+/// The contents of a ```.dbimg``` file.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ImageFile {
+    /// The lines drawn on the canvas.
+    pub lines: Vec<(Vec<LinePos>, Brush)>,
+    /// The images placed on the canvas.
+    #[serde(default)]
+    pub images: IndexMap<Uuid, CanvasImage>,
+    /// The shapes placed on the canvas.
+    #[serde(default)]
+    pub shapes: IndexMap<Uuid, CanvasShape>,
+    /// The texts placed on the canvas.
+    #[serde(default)]
+    pub texts: IndexMap<Uuid, CanvasText>,
+    /// The layers the lines are drawn on.
+    #[serde(default)]
+    pub layers: Layers,
+    /// The background of the canvas.
+    #[serde(default)]
+    pub background: CanvasBackground,
+    /// The image displayed with the ```BackgroundStyle::Image``` background.
+    #[serde(default)]
+    pub background_image: Option<CanvasImage>,
+}
+
+impl ImageFile {
+    /// Serializes the image into a ```.dbimg``` file, the kind of the ```FileMetadata``` should be ```FileKind::Image```.
+    pub fn to_bytes(&self, metadata: &FileMetadata) -> anyhow::Result<Vec<u8>> {
+        encode_file(metadata, self)
+    }
+
+    /// Reads a ```.dbimg``` file, migrating it from older format versions.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let file = decode_file(bytes)?;
+
+        file.expect_kind(FileKind::Image)?;
+
+        // Bodies serialized with and without field names are both read by ```rmp_serde```
+        match file.version {
+            0 => migrate_image_file_v0(&file.body),
+            _ => Ok(rmp_serde::from_slice(&file.body)?),
+        }
+    }
+}
+
+/// Version 0 image files contain either only the lines, or (If they were saved after images were added) an ```ImageFile```.
+fn migrate_image_file_v0(body: &[u8]) -> anyhow::Result<ImageFile> {
+    if let Ok(image_file) = rmp_serde::from_slice::<ImageFile>(body) {
+        return Ok(image_file);
+    }
+
+    Ok(ImageFile {
+        lines: rmp_serde::from_slice(body)?,
+        images: IndexMap::new(),
+        shapes: IndexMap::new(),
+        texts: IndexMap::new(),
+        layers: Layers::default(),
+        background: CanvasBackground::default(),
+        background_image: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Color32, Pos2, Rect};
+
+    use super::*;
+    use crate::BrushType;
+
+    /// The lines of the first version 0 files, with the brushes serialized as (Width, Color, BrushType).
+    type LinesV0 = Vec<(Vec<(f32, f32)>, (f32, Color32, BrushType))>;
+
+    fn test_lines() -> Vec<(Vec<LinePos>, Brush)> {
+        vec![(
+            vec![Pos2::new(0.1, 0.2).into(), Pos2::new(0.3, 0.4).into()],
+            Brush {
+                width: 4.,
+                color: Color32::RED,
+                brush_type: BrushType::Pencil,
+                ..Default::default()
+            },
+        )]
+    }
+
+    fn test_image_file() -> ImageFile {
+        let mut image_file = ImageFile {
+            lines: test_lines(),
+            ..Default::default()
+        };
+
+        image_file.images.insert(
+            Uuid::new_v4(),
+            CanvasImage {
+                rect: Rect::from_min_max(Pos2::ZERO, Pos2::new(0.5, 0.5)),
+                data: vec![1, 2, 3].into(),
+            },
+        );
+
+        image_file
+    }
+
+    fn assert_same_image(read: &ImageFile, written: &ImageFile) {
+        assert_eq!(read.lines, written.lines);
+        assert_eq!(read.images, written.images);
+        assert_eq!(read.shapes.len(), written.shapes.len());
+        assert_eq!(read.texts.len(), written.texts.len());
+        assert_eq!(read.background, written.background);
+    }
+
+    #[test]
+    fn image_file_round_trip() {
+        let image_file = test_image_file();
+        let bytes = image_file
+            .to_bytes(&FileMetadata::new(FileKind::Image))
+            .unwrap();
+
+        assert_eq!(decode_file(&bytes).unwrap().version, CURRENT_FORMAT_VERSION);
+        assert_same_image(&ImageFile::from_bytes(&bytes).unwrap(), &image_file);
+    }
+
+    #[test]
+    fn image_file_v0() {
+        let image_file = test_image_file();
+        let bytes = encode_raw_file(0, None, &rmp_serde::to_vec(&image_file).unwrap()).unwrap();
+
+        assert!(read_metadata(&bytes).unwrap().is_none());
+        assert_same_image(&ImageFile::from_bytes(&bytes).unwrap(), &image_file);
+    }
+
+    #[test]
+    fn image_file_v0_lines_only() {
+        // The first version 0 files only contained the lines
+        let lines: LinesV0 = vec![(
+            vec![(0.1, 0.2), (0.3, 0.4)],
+            (4., Color32::RED, BrushType::Pencil),
+        )];
+        let bytes = encode_raw_file(0, None, &rmp_serde::to_vec(&lines).unwrap()).unwrap();
+
+        let image_file = ImageFile::from_bytes(&bytes).unwrap();
+
+        assert_eq!(image_file.lines, test_lines());
+        assert!(image_file.images.is_empty());
+    }
+
+    #[test]
+    fn workspace_container_round_trip() {
+        let body = vec![String::from("workspace")];
+        let bytes = encode_file(&FileMetadata::new(FileKind::Workspace), &body).unwrap();
+
+        let file = decode_file(&bytes).unwrap();
+
+        assert!(file.expect_kind(FileKind::Workspace).is_ok());
+        assert!(file.expect_kind(FileKind::Image).is_err());
+        assert!(ImageFile::from_bytes(&bytes).is_err());
+        assert_eq!(
+            rmp_serde::from_slice::<Vec<String>>(&file.body).unwrap(),
+            body
+        );
+    }
+
+    #[test]
+    fn workspace_container_v0() {
+        let body = rmp_serde::to_vec(&vec![String::from("workspace")]).unwrap();
+
+        let v0 = decode_file(&encode_raw_file(0, None, &body).unwrap()).unwrap();
+
+        assert_eq!(v0.version, 0);
+        assert!(v0.metadata.is_none());
+        assert_eq!(v0.body, body);
+    }
+
+    #[test]
+    fn bad_magic() {
+        // Files without the magic bytes are read as version 0 files, which have to be valid compressed bodies
+        assert!(decode_file(b"DBRX\x00\x01\x00\x00\x00\x00garbage").is_err());
+        assert!(ImageFile::from_bytes(b"not a drawing board file").is_err());
+    }
+
+    #[test]
+    fn newer_version() {
+        let bytes = encode_raw_file(
+            CURRENT_FORMAT_VERSION + 1,
+            Some(&FileMetadata::new(FileKind::Image)),
+            &rmp_serde::to_vec_named(&test_image_file()).unwrap(),
+        )
+        .unwrap();
+
+        assert!(decode_file(&bytes).is_err());
+        assert!(ImageFile::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn unreleased_version() {
+        let bytes = encode_raw_file(
+            UNRELEASED_FORMAT_VERSION,
+            Some(&FileMetadata::new(FileKind::Image)),
+            &rmp_serde::to_vec(&test_image_file()).unwrap(),
+        )
+        .unwrap();
+
+        assert!(decode_file(&bytes).is_err());
+    }
+
+    #[test]
+    fn truncated_header() {
+        let bytes = test_image_file()
+            .to_bytes(&FileMetadata::new(FileKind::Image))
+            .unwrap();
+
+        // The preamble is cut off, and then the header
+        for length in [FILE_MAGIC.len() + 3, PREAMBLE_LENGTH + 2] {
+            assert!(decode_file(&bytes[..length]).is_err());
+            assert!(read_metadata(&bytes[..length]).is_err());
+        }
+    }
+
+    #[test]
+    fn oversized_header_is_refused() {
+        let mut bytes = FILE_MAGIC.to_vec();
+        bytes.extend_from_slice(&CURRENT_FORMAT_VERSION.to_be_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+
+        let path = std::env::temp_dir().join(format!("oversized_header_{}.dbimg", Uuid::new_v4()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let metadata = read_file_metadata(&path);
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(metadata.is_err());
+    }
+}
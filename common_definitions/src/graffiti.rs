@@ -0,0 +1,91 @@
+use std::{f32::consts::TAU, ops::RangeInclusive};
+
+use egui::{Pos2, Vec2};
+
+use crate::LinePos;
+
+/// The default number of particles sprayed along every segment of a ```BrushType::Graffiti``` line.
+pub const DEFAULT_GRAFFITI_DENSITY: u32 = 30;
+
+/// The densities a ```BrushType::Graffiti``` line can have, this keeps a single line from spraying an unbounded number of particles.
+pub const GRAFFITI_DENSITY_RANGE: RangeInclusive<u32> = 1..=200;
+
+/// Used for deriving a different seed for every segment of a line.
+const SEGMENT_SEED_MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A single particle of a ```BrushType::Graffiti``` line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraffitiParticle {
+    /// The point of the line the particle was sprayed around, in canvas units.
+    pub anchor: Pos2,
+    /// The offset of the particle from its anchor, in points.
+    pub offset: Vec2,
+    /// The radius of the particle, in points.
+    pub radius: f32,
+}
+
+/// A small deterministic random number generator (SplitMix64), so that every client scatters the particles the same way.
+pub(crate) struct SprayRng(u64);
+
+impl SprayRng {
+    /// Creates the generator of the ```idx```-th part (Segment or stamp) of the line with the ```seed```.
+    pub(crate) fn new(seed: u64, idx: usize) -> Self {
+        Self(seed ^ (idx as u64).wrapping_mul(SEGMENT_SEED_MULTIPLIER))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(SEGMENT_SEED_MULTIPLIER);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in the range of ```0.0..1.0```.
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Returns the seed of the line.
+/// The seed is derived from the first point of the line, so that the particles don't move while the line is being drawn.
+pub(crate) fn line_seed(points: &[LinePos]) -> u64 {
+    points
+        .first()
+        .map(|pos| {
+            let pos = Pos2::from(*pos);
+
+            ((pos.x.to_bits() as u64) << 32) | pos.y.to_bits() as u64
+        })
+        .unwrap_or_default()
+}
+
+/// Scatters the particles of a ```BrushType::Graffiti``` line within ```radius``` points (Multiplied by the pressure of the points) of the line.
+/// ```density``` particles (Clamped to the ```GRAFFITI_DENSITY_RANGE```) are sprayed along every segment, and they are denser near the line just like with a spray can.
+/// The particles only depend on the line's points, so the line looks the same on every client and in every export.
+pub fn graffiti_particles(points: &[LinePos], radius: f32, density: u32) -> Vec<GraffitiParticle> {
+    let seed = line_seed(points);
+    let density = density.clamp(
+        *GRAFFITI_DENSITY_RANGE.start(),
+        *GRAFFITI_DENSITY_RANGE.end(),
+    );
+    let particle_radius = (radius / 16.).clamp(0.5, 2.);
+
+    points
+        .windows(2)
+        .enumerate()
+        .flat_map(|(idx, segment)| {
+            let (start, end) = (Pos2::from(segment[0]), Pos2::from(segment[1]));
+            let radius = radius * (segment[0].pressure() + segment[1].pressure()) / 2.;
+            let mut rng = SprayRng::new(seed, idx);
+
+            (0..density).map(move |_| GraffitiParticle {
+                anchor: start.lerp(end, rng.next_f32()),
+                offset: Vec2::angled(rng.next_f32() * TAU) * rng.next_f32() * radius,
+                radius: particle_radius,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,214 @@
+use std::ops::{Deref, DerefMut};
+
+use indexmap::IndexMap;
+use uuid::Uuid;
+
+use crate::{Brush, CanvasShape, LinePos};
+
+/// The ```Uuid``` of the layer every canvas starts with, lines saved before layers were added belong to this layer.
+pub const DEFAULT_LAYER: Uuid = Uuid::nil();
+
+/// A named layer of the canvas, which the lines and shapes are drawn on.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Layer {
+    /// The name of the layer.
+    pub name: String,
+    /// Whether the lines of the layer are drawn.
+    pub visible: bool,
+    /// Whether the lines of the layer are protected from being drawn on, erased or selected.
+    pub locked: bool,
+    /// The opacity the lines of the layer are drawn with, in the range of ```0.0..=1.0```.
+    /// Received opacities are clamped to this range, and the ones which aren't finite are made opaque.
+    #[serde(deserialize_with = "deserialize_layer_opacity")]
+    pub opacity: f32,
+}
+
+fn deserialize_layer_opacity<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<f32, D::Error> {
+    let opacity = <f32 as serde::Deserialize>::deserialize(deserializer)?;
+
+    Ok(if opacity.is_finite() {
+        opacity.clamp(0., 1.)
+    } else {
+        1.
+    })
+}
+
+impl Layer {
+    /// Creates a visible, unlocked and opaque layer.
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+            locked: false,
+            opacity: 1.,
+        }
+    }
+}
+
+/// The layers of the canvas, keyed by their ```Uuid```.
+/// The layers are stored in the order they are drawn in, the last layer is on the top.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Layers(pub IndexMap<Uuid, Layer>);
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self(IndexMap::from([(DEFAULT_LAYER, Layer::new("Layer 1"))]))
+    }
+}
+
+impl Deref for Layers {
+    type Target = IndexMap<Uuid, Layer>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Layers {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Layers {
+    /// Returns whether the lines and shapes of the layer can be drawn on, erased or selected.
+    /// Lines and shapes of unknown layers (Like the ones received before their layer) are editable.
+    pub fn is_editable(&self, layer: Uuid) -> bool {
+        self.get(&layer)
+            .is_none_or(|layer| layer.visible && !layer.locked)
+    }
+
+    /// Returns the lines in the order they are drawn in, which is the order of their layers and then the order they were drawn in.
+    /// The lines of hidden layers are skipped, and the opacity of the layers is applied to the lines' color.
+    /// Lines of unknown layers are drawn below every layer.
+    pub fn layered_lines<'a>(
+        &'a self,
+        lines: &'a [(Vec<LinePos>, Brush)],
+    ) -> impl Iterator<Item = (&'a Vec<LinePos>, Brush)> + 'a {
+        let unknown_layer_lines = lines
+            .iter()
+            .filter(|(_, brush)| !self.contains_key(&brush.layer))
+            .map(|(points, brush)| (points, *brush));
+
+        let layer_lines =
+            self.iter()
+                .filter(|(_, layer)| layer.visible)
+                .flat_map(move |(id, layer)| {
+                    lines
+                        .iter()
+                        .filter(move |(_, brush)| brush.layer == *id)
+                        .map(move |(points, brush)| {
+                            (
+                                points,
+                                Brush {
+                                    color: brush.color.gamma_multiply(layer.opacity),
+                                    ..*brush
+                                },
+                            )
+                        })
+                });
+
+        unknown_layer_lines.chain(layer_lines)
+    }
+
+    /// Returns the shapes in the order they are drawn in, which is the order of their layers and then the order they were placed in.
+    /// The shapes of hidden layers are skipped, and the opacity of the layers is applied to the shapes' colors.
+    /// Shapes of unknown layers are drawn below every layer.
+    pub fn layered_shapes<'a, I>(&'a self, shapes: I) -> impl Iterator<Item = CanvasShape> + 'a
+    where
+        I: IntoIterator<Item = &'a CanvasShape>,
+        I::IntoIter: Clone + 'a,
+    {
+        let shapes = shapes.into_iter();
+
+        let unknown_layer_shapes = shapes
+            .clone()
+            .filter(|shape| !self.contains_key(&shape.layer))
+            .cloned();
+
+        let layer_shapes =
+            self.iter()
+                .filter(|(_, layer)| layer.visible)
+                .flat_map(move |(id, layer)| {
+                    shapes
+                        .clone()
+                        .filter(move |shape| shape.layer == *id)
+                        .map(move |shape| CanvasShape {
+                            stroke_color: shape.stroke_color.gamma_multiply(layer.opacity),
+                            fill: shape.fill.map(|fill| fill.gamma_multiply(layer.opacity)),
+                            ..shape.clone()
+                        })
+                });
+
+        unknown_layer_shapes.chain(layer_shapes)
+    }
+
+    /// Returns the visible lines with the opacity of their layers applied, in the order they are drawn in.
+    /// This is what the renderer draws when exporting the canvas.
+    pub fn visible_lines(&self, lines: &[(Vec<LinePos>, Brush)]) -> Vec<(Vec<LinePos>, Brush)> {
+        self.layered_lines(lines)
+            .map(|(points, brush)| (points.clone(), brush))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_of_hidden_layers_are_skipped() {
+        let mut layers = Layers::default();
+        let hidden_layer = Uuid::new_v4();
+
+        layers.insert(
+            hidden_layer,
+            Layer {
+                visible: false,
+                ..Layer::new("Hidden")
+            },
+        );
+        layers[&DEFAULT_LAYER].opacity = 0.5;
+
+        let shape = CanvasShape {
+            kind: crate::ShapeKind::Rectangle,
+            points: vec![egui::Pos2::ZERO, egui::Pos2::new(1., 1.)],
+            stroke_width: 1.,
+            stroke_color: egui::Color32::WHITE,
+            fill: None,
+            layer: DEFAULT_LAYER,
+        };
+        let hidden_shape = CanvasShape {
+            layer: hidden_layer,
+            ..shape.clone()
+        };
+
+        let shapes: Vec<CanvasShape> = layers.layered_shapes(&[shape, hidden_shape]).collect();
+
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(
+            shapes[0].stroke_color,
+            egui::Color32::WHITE.gamma_multiply(0.5)
+        );
+    }
+
+    #[test]
+    fn received_layer_opacity_is_clamped() {
+        for (opacity, received_opacity) in
+            [(-1., 0.), (2., 1.), (f32::NAN, 1.), (f32::INFINITY, 1.)]
+        {
+            let layer = Layer {
+                opacity,
+                ..Layer::new("Layer")
+            };
+
+            let received_layer: Layer =
+                rmp_serde::from_slice(&rmp_serde::to_vec(&layer).unwrap()).unwrap();
+
+            assert_eq!(received_layer.opacity, received_opacity);
+        }
+    }
+}
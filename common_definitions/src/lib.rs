@@ -1,14 +1,87 @@
-use egui::{Color32, Pos2};
+use egui::{Color32, Pos2, Rect};
 pub use indexmap::IndexMap;
-use std::{fmt::Display, str::FromStr};
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
 use strum::{EnumCount, IntoStaticStr};
+mod background;
+mod file_format;
+mod graffiti;
+mod layer;
+mod shape;
+mod stamp;
+mod text;
+pub use background::{BackgroundStyle, CanvasBackground, BACKGROUND_IMAGE_ID};
+pub use file_format::{
+    decode_file, encode_file, encode_raw_file, read_file_metadata, read_metadata, DecodedFile,
+    FileKind, FileMetadata, ImageFile, ProjectProperties, CURRENT_FORMAT_VERSION, FILE_MAGIC,
+};
+pub use graffiti::{
+    graffiti_particles, GraffitiParticle, DEFAULT_GRAFFITI_DENSITY, GRAFFITI_DENSITY_RANGE,
+};
+pub use layer::{Layer, Layers, DEFAULT_LAYER};
+pub use shape::{constrain_shape_point, CanvasShape, ShapeKind};
+pub use stamp::{brush_stamps, BrushStamp, BrushTexture, StampSettings};
+pub use text::{CanvasText, TextFont};
 // Reimports
 pub use tokio_util::sync::CancellationToken;
 pub use typed_floats::NonNaN;
 pub use uuid::Uuid;
 
-// Type definitions
-pub type Brush = (f32, Color32, BrushType);
+/// The size of one canvas unit in points.
+/// Lines are stored in canvas units, so they need to be scaled up to get rendered.
+pub const DEFAULT_CANVAS_SCALE: f32 = 1024.;
+
+/// The properties a line is painted with.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Brush {
+    /// The width of the line in points, for ```BrushType::Graffiti``` this is the diameter of the sprayed area.
+    pub width: f32,
+    /// The color of the line.
+    pub color: Color32,
+    /// The type of the brush the line is painted with.
+    pub brush_type: BrushType,
+    /// The number of particles sprayed along every segment of a ```BrushType::Graffiti``` line.
+    /// Lines saved before this field was added get the default density, received densities are clamped to the ```GRAFFITI_DENSITY_RANGE```.
+    #[serde(
+        default = "default_graffiti_density",
+        deserialize_with = "deserialize_graffiti_density"
+    )]
+    pub density: u32,
+    /// The texture and the stamp settings of a ```BrushType::Stamp``` line.
+    #[serde(default)]
+    pub stamp: StampSettings,
+    /// The ```Uuid``` of the layer the line is drawn on.
+    /// Lines saved before layers were added are on the ```DEFAULT_LAYER```.
+    #[serde(default)]
+    pub layer: Uuid,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            width: 0.,
+            color: Color32::default(),
+            brush_type: BrushType::default(),
+            density: DEFAULT_GRAFFITI_DENSITY,
+            stamp: StampSettings::default(),
+            layer: DEFAULT_LAYER,
+        }
+    }
+}
+
+fn default_graffiti_density() -> u32 {
+    DEFAULT_GRAFFITI_DENSITY
+}
+
+fn deserialize_graffiti_density<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u32, D::Error> {
+    let density = <u32 as serde::Deserialize>::deserialize(deserializer)?;
+
+    Ok(density.clamp(
+        *GRAFFITI_DENSITY_RANGE.start(),
+        *GRAFFITI_DENSITY_RANGE.end(),
+    ))
+}
 
 /// The message types the client and the server can send.
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -17,6 +90,11 @@ pub enum MessageType {
     ClientList(Vec<(String, Uuid)>),
     /// This enum contains the connected user's PointerProperties
     CursorPosition(PointerProperties),
+    /// This enum contains the area of the canvas (In canvas units) the connected user is looking at.
+    /// It is sent whenever the user's camera moves, so that the other users can follow it.
+    Viewport(Rect),
+    /// This enum is used to move the camera of every other user to the area of the canvas (In canvas units) the sender is looking at.
+    SummonToViewport(Rect),
     /// This enum contains the username of the user who has connected to the server.
     Connecting(String),
     /// This enum indicated a user disconnect
@@ -26,14 +104,179 @@ pub enum MessageType {
 
     AddLine((Vec<LinePos>, Brush)),
     ModifyLine((Vec<LinePos>, Option<Brush>)),
+    /// This enum is used to move, scale or rotate a line, the line's points (First) are replaced with the new points (Second) while its ```Brush``` is kept.
+    TransformLine((Vec<LinePos>, Vec<LinePos>)),
     RequestSyncLine(Option<Vec<LinePos>>),
 
     SyncLine(LineSyncType),
+
+    /// This enum is used to request a rendered image of the server's canvas.
+    RequestExport(ExportRequest),
+    /// This enum contains the canvas rendered by the server in the requested ```ExportFormat```.
+    Export((ExportFormat, Vec<u8>)),
+    /// This enum contains the reason the server couldn't render the requested export.
+    ExportFailed(String),
+
+    /// This enum indicates that an image was placed on the canvas.
+    /// The image's bytes are sent in ```ImageChunk```-s after this message.
+    AddImage((Uuid, ImageHeader)),
+    /// This enum contains a chunk of an image's bytes, and the offset of the chunk.
+    ImageChunk((Uuid, usize, Vec<u8>)),
+    /// This enum is used to move or resize an image, if the new ```Rect``` is ```None``` the image gets deleted.
+    ModifyImage((Uuid, Option<Rect>)),
+
+    /// This enum indicates that a shape was placed on the canvas.
+    AddShape((Uuid, CanvasShape)),
+    /// This enum is used to change a shape, if the new ```CanvasShape``` is ```None``` the shape gets deleted.
+    ModifyShape((Uuid, Option<CanvasShape>)),
+
+    /// This enum indicates that a text was placed on the canvas.
+    AddText((Uuid, CanvasText)),
+    /// This enum is used to edit or move a text, if the new ```CanvasText``` is ```None``` the text gets deleted.
+    ModifyText((Uuid, Option<CanvasText>)),
+
+    /// This enum contains every layer of the canvas in drawing order, the layers replace the previous ones.
+    /// It is sent whenever a layer is added, removed, reordered or changed.
+    ModifyLayers(Layers),
+
+    /// This enum contains the background of the canvas, which replaces the previous one.
+    /// The background image is sent separately, as an image with the ```BACKGROUND_IMAGE_ID```.
+    ModifyBackground(CanvasBackground),
+}
+
+/// The maximum number of bytes an ```ImageChunk``` can contain.
+pub const IMAGE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The length of the largest message the server and the clients accept, in bytes.
+pub const MAX_MESSAGE_LENGTH: u64 = 128_000_000;
+
+/// The length of the largest image which can be sent in chunks, images can't be larger than the largest message.
+pub const MAX_IMAGE_LENGTH: usize = MAX_MESSAGE_LENGTH as usize;
+
+/// An image placed on the canvas.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CanvasImage {
+    /// The area the image covers, in canvas units.
+    pub rect: Rect,
+    /// The encoded (png or jpeg) bytes of the image.
+    /// The bytes are shared, so that the image can be cloned cheaply (Like into the undo points).
+    pub data: Arc<[u8]>,
 }
 
+/// This struct contains the properties of an image, which is sent in chunks.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ImageHeader {
+    /// The area the image covers, in canvas units.
+    pub rect: Rect,
+    /// The length of the image's encoded bytes.
+    pub data_length: usize,
+}
+
+impl CanvasImage {
+    /// Splits the image into an ```AddImage``` message and the ```ImageChunk```-s containing its bytes.
+    pub fn to_messages(&self, id: Uuid) -> Vec<MessageType> {
+        let mut messages = vec![MessageType::AddImage((
+            id,
+            ImageHeader {
+                rect: self.rect,
+                data_length: self.data.len(),
+            },
+        ))];
+
+        messages.extend(
+            self.data
+                .chunks(IMAGE_CHUNK_SIZE)
+                .enumerate()
+                .map(|(idx, chunk)| {
+                    MessageType::ImageChunk((id, idx * IMAGE_CHUNK_SIZE, chunk.to_vec()))
+                }),
+        );
+
+        messages
+    }
+}
+
+/// This struct reassembles the images which are sent in chunks.
+#[derive(Default, Debug)]
+pub struct ImageReceiver {
+    /// The images whose chunks haven't all arrived yet, with the bytes which have already arrived.
+    pending_images: HashMap<Uuid, (ImageHeader, Vec<u8>)>,
+}
+
+impl ImageReceiver {
+    /// Starts receiving an image, replacing the image with the same ```Uuid``` if it's still being received.
+    /// Returns an error if the image is larger than ```MAX_IMAGE_LENGTH```.
+    pub fn start(&mut self, id: Uuid, header: ImageHeader) -> anyhow::Result<()> {
+        if header.data_length > MAX_IMAGE_LENGTH {
+            self.pending_images.remove(&id);
+
+            return Err(anyhow::Error::msg(format!(
+                "The image is too large ({} bytes, the limit is {MAX_IMAGE_LENGTH} bytes).",
+                header.data_length
+            )));
+        }
+
+        // The bytes are allocated as the chunks arrive, so the header alone can't allocate a large buffer
+        self.pending_images.insert(id, (header, Vec::new()));
+
+        Ok(())
+    }
+
+    /// Appends the chunk to the image it belongs to, the chunks of an image arrive in order.
+    /// Returns the whole image if this was its last chunk, or ```None``` if the image is still incomplete or it wasn't started.
+    /// Images with a chunk which doesn't fit are dropped.
+    pub fn receive_chunk(&mut self, id: Uuid, offset: usize, chunk: &[u8]) -> Option<CanvasImage> {
+        let (header, data) = self.pending_images.get_mut(&id)?;
+
+        let end = offset.checked_add(chunk.len());
+
+        if offset != data.len() || end.is_none_or(|end| end > header.data_length) {
+            self.pending_images.remove(&id);
+
+            return None;
+        }
+
+        data.extend_from_slice(chunk);
+
+        // The last chunk is the one ending at the end of the image
+        if data.len() != header.data_length {
+            return None;
+        }
+
+        let (header, data) = self.pending_images.remove(&id)?;
+
+        Some(CanvasImage {
+            rect: header.rect,
+            data: data.into(),
+        })
+    }
+}
+
+/// The file formats the server can render its canvas into.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum ExportFormat {
+    Png,
+    Svg,
+    Pdf,
+}
+
+/// This struct contains the properties of a requested export.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct ExportRequest {
+    /// The format the canvas should be rendered into.
+    pub format: ExportFormat,
+    /// If this is set, the canvas is rendered as a thumbnail whose longer side is this many pixels long.
+    /// This is only used when rendering into ```ExportFormat::Png```.
+    pub thumbnail_size: Option<u32>,
+}
+
+/// This struct contains the position of a connected user's pointer, and the brush the user is holding.
 #[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct PointerProperties {
+    /// The position of the pointer on the canvas, in canvas units.
+    /// Canvas units don't depend on the window or the camera, so every client can display the pointer over the same part of the drawing.
     pub pointer_pos: Pos2,
+    /// The brush the user is holding, this is displayed around the pointer.
     pub brush: Brush,
 }
 
@@ -43,6 +286,30 @@ pub struct PointerProperties {
 pub struct LinePos {
     pub x: NonNaN<f32>,
     pub y: NonNaN<f32>,
+    /// The pressure the point was drawn with, the width of the line at this point is the brush's width multiplied by this.
+    /// The pressure is in the range of ```0.0..=1.0```, points saved before this field was added have full pressure.
+    #[serde(default = "full_pressure")]
+    pub pressure: NonNaN<f32>,
+}
+
+fn full_pressure() -> NonNaN<f32> {
+    NonNaN::<f32>::new(1.).unwrap()
+}
+
+impl LinePos {
+    /// Creates a ```LinePos``` which was drawn with the ```pressure```.
+    pub fn with_pressure(pos: Pos2, pressure: f32) -> Self {
+        Self {
+            pressure: NonNaN::<f32>::new(pressure.clamp(0., 1.))
+                .unwrap_or_else(|_| full_pressure()),
+            ..pos.into()
+        }
+    }
+
+    /// Returns the pressure the point was drawn with.
+    pub fn pressure(&self) -> f32 {
+        self.pressure.into()
+    }
 }
 
 impl From<Pos2> for LinePos {
@@ -50,6 +317,7 @@ impl From<Pos2> for LinePos {
         Self {
             x: NonNaN::<f32>::new(value.x).unwrap(),
             y: NonNaN::<f32>::new(value.y).unwrap(),
+            pressure: full_pressure(),
         }
     }
 }
@@ -82,6 +350,8 @@ pub struct LineData {
     serde::Deserialize,
     Default,
     PartialEq,
+    Eq,
+    Hash,
     Clone,
     Copy,
     EnumCount,
@@ -94,6 +364,9 @@ pub enum BrushType {
     Pencil,
     #[default]
     Marker,
+    Stamp,
+    Shape,
+    Text,
     Eraser,
 }
 
@@ -108,6 +381,8 @@ pub enum TabType {
     Canvas,
     /// Used for displaying the Brush's settings the user can paint on the canvas with.
     BrushSettings,
+    /// Used for managing the layers the lines are drawn on.
+    Layers,
 }
 
 /// The message wrapper.
@@ -158,3 +433,89 @@ impl Message {
         message_header
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_image(data_length: usize) -> CanvasImage {
+        CanvasImage {
+            rect: Rect::from_min_max(Pos2::ZERO, Pos2::new(0.5, 0.5)),
+            data: (0..data_length).map(|idx| idx as u8).collect(),
+        }
+    }
+
+    #[test]
+    fn image_chunks_reassemble() {
+        let image = test_image(IMAGE_CHUNK_SIZE * 2 + 10);
+        let id = Uuid::new_v4();
+        let mut receiver = ImageReceiver::default();
+        let mut received_image = None;
+
+        for message in image.to_messages(id) {
+            match message {
+                MessageType::AddImage((id, header)) => receiver.start(id, header).unwrap(),
+                MessageType::ImageChunk((id, offset, chunk)) => {
+                    assert!(received_image.is_none());
+
+                    received_image = receiver.receive_chunk(id, offset, &chunk);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        assert_eq!(received_image, Some(image));
+    }
+
+    #[test]
+    fn oversized_image_is_refused() {
+        let mut receiver = ImageReceiver::default();
+        let id = Uuid::new_v4();
+        let header = ImageHeader {
+            rect: Rect::ZERO,
+            data_length: usize::MAX,
+        };
+
+        assert!(receiver.start(id, header).is_err());
+        assert!(receiver.receive_chunk(id, 0, &[0; 16]).is_none());
+        assert!(receiver.pending_images.is_empty());
+    }
+
+    #[test]
+    fn bad_chunks_drop_the_image() {
+        let mut receiver = ImageReceiver::default();
+        let id = Uuid::new_v4();
+        let header = ImageHeader {
+            rect: Rect::ZERO,
+            data_length: 32,
+        };
+
+        // The offset overflows
+        receiver.start(id, header).unwrap();
+        assert!(receiver.receive_chunk(id, usize::MAX, &[0; 16]).is_none());
+        assert!(receiver.pending_images.is_empty());
+
+        // The chunk doesn't fit into the image
+        receiver.start(id, header).unwrap();
+        assert!(receiver.receive_chunk(id, 0, &[0; 48]).is_none());
+        assert!(receiver.pending_images.is_empty());
+
+        // The chunk skips a part of the image
+        receiver.start(id, header).unwrap();
+        assert!(receiver.receive_chunk(id, 16, &[0; 16]).is_none());
+        assert!(receiver.pending_images.is_empty());
+    }
+
+    #[test]
+    fn received_graffiti_density_is_clamped() {
+        let brush = Brush {
+            density: u32::MAX,
+            ..Default::default()
+        };
+
+        let received_brush: Brush =
+            rmp_serde::from_slice(&rmp_serde::to_vec(&brush).unwrap()).unwrap();
+
+        assert_eq!(received_brush.density, *GRAFFITI_DENSITY_RANGE.end());
+    }
+}
@@ -0,0 +1,142 @@
+use std::f32::consts::{FRAC_PI_4, TAU};
+
+use egui::{vec2, Color32, Pos2, Vec2};
+use strum::IntoStaticStr;
+use uuid::Uuid;
+
+/// The geometric primitives the shape tools can create.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, IntoStaticStr, serde::Serialize, serde::Deserialize,
+)]
+pub enum ShapeKind {
+    #[default]
+    Rectangle,
+    Ellipse,
+    Line,
+    Arrow,
+    Polygon,
+}
+
+impl ShapeKind {
+    /// Every ```ShapeKind```, in the order they are displayed in.
+    pub const ALL: [Self; 5] = [
+        Self::Rectangle,
+        Self::Ellipse,
+        Self::Line,
+        Self::Arrow,
+        Self::Polygon,
+    ];
+
+    /// Returns whether the shape encloses an area, which can be filled.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::Rectangle | Self::Ellipse | Self::Polygon)
+    }
+}
+
+/// A geometric shape placed on the canvas.
+/// Shapes are stored by their defining points, so that they stay exact when they are moved or resized.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CanvasShape {
+    /// The kind of the shape.
+    pub kind: ShapeKind,
+    /// The points defining the shape, in canvas units.
+    /// For ```ShapeKind::Rectangle``` and ```ShapeKind::Ellipse``` these are two opposite corners of the bounding box, for ```ShapeKind::Line``` and ```ShapeKind::Arrow``` the start and the end of the line, and for ```ShapeKind::Polygon``` the vertices.
+    pub points: Vec<Pos2>,
+    /// The width of the outline in points.
+    pub stroke_width: f32,
+    /// The color of the outline.
+    pub stroke_color: Color32,
+    /// The color the shape is filled with, this is ignored for the shapes which aren't closed.
+    pub fill: Option<Color32>,
+    /// The ```Uuid``` of the layer the shape is placed on.
+    /// Shapes saved before they were placed on layers are on the ```DEFAULT_LAYER```.
+    #[serde(default)]
+    pub layer: Uuid,
+}
+
+/// The number of segments an ellipse's outline is approximated with.
+const ELLIPSE_SEGMENTS: usize = 64;
+
+/// The length of an arrow's head relative to the width of the arrow's line.
+const ARROW_HEAD_SCALE: f32 = 4.;
+
+/// The shortest an arrow's head can be, in points.
+const MIN_ARROW_HEAD_LENGTH: f32 = 10.;
+
+impl CanvasShape {
+    /// Returns the outline of the shape in canvas units, the outline of the closed shapes doesn't repeat its first point.
+    /// The head of a ```ShapeKind::Arrow``` isn't part of the outline, see ```arrow_head```.
+    pub fn outline(&self) -> Vec<Pos2> {
+        match (self.kind, self.points.as_slice()) {
+            (ShapeKind::Rectangle, [start, end, ..]) => vec![
+                *start,
+                Pos2::new(end.x, start.y),
+                *end,
+                Pos2::new(start.x, end.y),
+            ],
+            (ShapeKind::Ellipse, [start, end, ..]) => {
+                let center = start.lerp(*end, 0.5);
+                let radius = (*end - *start).abs() / 2.;
+
+                (0..ELLIPSE_SEGMENTS)
+                    .map(|idx| {
+                        let angle = idx as f32 / ELLIPSE_SEGMENTS as f32 * TAU;
+
+                        center + vec2(angle.cos() * radius.x, angle.sin() * radius.y)
+                    })
+                    .collect()
+            }
+            (ShapeKind::Line | ShapeKind::Arrow, [start, end, ..]) => vec![*start, *end],
+            _ => self.points.clone(),
+        }
+    }
+
+    /// Returns the color the shape is filled with, or ```None``` if it isn't filled.
+    pub fn fill_color(&self) -> Option<Color32> {
+        self.fill.filter(|_| self.kind.is_closed())
+    }
+
+    /// Returns the length of a ```ShapeKind::Arrow```'s head in points, the head grows with the width of the arrow's line.
+    pub fn arrow_head_length(&self) -> f32 {
+        (self.stroke_width * ARROW_HEAD_SCALE).max(MIN_ARROW_HEAD_LENGTH)
+    }
+
+    /// Returns the three corners of the head of a ```ShapeKind::Arrow``` (The tip first), the head's size is given in canvas units.
+    /// Returns ```None``` for the other shapes, or if the arrow has no length.
+    pub fn arrow_head(&self, head_length: f32) -> Option<[Pos2; 3]> {
+        let (ShapeKind::Arrow, [start, end, ..]) = (self.kind, self.points.as_slice()) else {
+            return None;
+        };
+
+        let direction = (*end - *start).normalized();
+
+        if direction == Vec2::ZERO {
+            return None;
+        }
+
+        let back = *end - direction * head_length;
+        let side = direction.rot90() * head_length / 2.;
+
+        Some([*end, back + side, back - side])
+    }
+}
+
+/// Constrains the point being placed relative to the ```anchor```, this is used when the shape is drawn while holding Shift.
+/// Rectangles and ellipses become squares and circles, lines and polygon edges snap to multiples of 45 degrees.
+pub fn constrain_shape_point(kind: ShapeKind, anchor: Pos2, pos: Pos2) -> Pos2 {
+    let offset = pos - anchor;
+
+    match kind {
+        ShapeKind::Rectangle | ShapeKind::Ellipse => {
+            let side = offset.x.abs().max(offset.y.abs());
+
+            anchor + vec2(side.copysign(offset.x), side.copysign(offset.y))
+        }
+        ShapeKind::Line | ShapeKind::Arrow | ShapeKind::Polygon => {
+            let step = FRAC_PI_4;
+            let angle = (offset.angle() / step).round() * step;
+
+            anchor + Vec2::angled(angle) * offset.length()
+        }
+    }
+}
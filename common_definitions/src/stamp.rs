@@ -0,0 +1,175 @@
+use std::f32::consts::TAU;
+
+use egui::{lerp, Pos2, Vec2};
+use strum::IntoStaticStr;
+
+use crate::{
+    graffiti::{line_seed, SprayRng},
+    LinePos, DEFAULT_CANVAS_SCALE,
+};
+
+/// The smallest distance between two stamps in points, so that thin brushes don't place an excessive amount of stamps.
+const MIN_STAMP_SPACING: f32 = 0.5;
+
+/// The largest number of stamps a line can have, this keeps a single long and thin line from placing an unbounded number of stamps.
+const MAX_STAMPS_PER_LINE: usize = 10_000;
+
+/// The textures bundled with the application, which the ```BrushType::Stamp``` brush can paint with.
+/// Only the texture's identity is sent over the network, every client has the same textures.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    IntoStaticStr,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum BrushTexture {
+    #[default]
+    Chalk,
+    Charcoal,
+    Splatter,
+    Bristle,
+}
+
+impl BrushTexture {
+    /// Every built-in texture.
+    pub const ALL: [Self; 4] = [Self::Chalk, Self::Charcoal, Self::Splatter, Self::Bristle];
+
+    /// Returns the png image of the texture.
+    /// The textures are white, their shape is stored in their alpha channel so that they can be tinted with the brush's color.
+    pub fn png_bytes(&self) -> &'static [u8] {
+        match self {
+            Self::Chalk => include_bytes!("../textures/chalk.png"),
+            Self::Charcoal => include_bytes!("../textures/charcoal.png"),
+            Self::Splatter => include_bytes!("../textures/splatter.png"),
+            Self::Bristle => include_bytes!("../textures/bristle.png"),
+        }
+    }
+}
+
+/// The settings of the ```BrushType::Stamp``` brush, which paints by stamping its texture along the line.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct StampSettings {
+    /// The texture which gets stamped.
+    pub texture: BrushTexture,
+    /// The distance between two stamps, relative to the brush's width.
+    pub spacing: f32,
+    /// The largest random rotation of a stamp in radians.
+    pub rotation_jitter: f32,
+    /// The largest random distance of a stamp from the line, relative to the brush's width.
+    pub scatter: f32,
+}
+
+impl Default for StampSettings {
+    fn default() -> Self {
+        Self {
+            texture: BrushTexture::default(),
+            spacing: 0.25,
+            rotation_jitter: 0.5,
+            scatter: 0.1,
+        }
+    }
+}
+
+/// A single stamp of a ```BrushType::Stamp``` line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrushStamp {
+    /// The point of the line the stamp was placed at, in canvas units.
+    pub anchor: Pos2,
+    /// The offset of the stamp's center from its anchor, in points.
+    pub offset: Vec2,
+    /// The rotation of the stamp in radians.
+    pub rotation: f32,
+    /// The width and height of the stamp, in points.
+    pub size: f32,
+}
+
+/// Places the stamps of a ```BrushType::Stamp``` line with the ```width``` of the brush, the stamps are scaled by the pressure of the line.
+/// The stamps are spaced evenly along the line in canvas units (As if the canvas was ```DEFAULT_CANVAS_SCALE``` points large), and their jitter only depends on the line's points, so the line looks the same on every client.
+/// The spacing is widened if the line would have more than ```MAX_STAMPS_PER_LINE``` stamps, and segments with infinite coordinates are skipped.
+pub fn brush_stamps(points: &[LinePos], width: f32, settings: &StampSettings) -> Vec<BrushStamp> {
+    let seed = line_seed(points);
+
+    let segment_length = |segment: &[LinePos]| Pos2::from(segment[0]).distance(segment[1].into());
+    let line_length: f32 = points
+        .windows(2)
+        .map(segment_length)
+        .filter(|length| length.is_finite())
+        .sum();
+
+    let spacing = ((width * settings.spacing).max(MIN_STAMP_SPACING) / DEFAULT_CANVAS_SCALE)
+        .max(line_length / MAX_STAMPS_PER_LINE as f32);
+
+    let mut stamps: Vec<BrushStamp> = Vec::new();
+    let mut distance_to_next = 0.;
+
+    for segment in points.windows(2) {
+        let (start, end) = (Pos2::from(segment[0]), Pos2::from(segment[1]));
+        let length = segment_length(segment);
+
+        if !length.is_finite() {
+            continue;
+        }
+
+        let mut distance = distance_to_next;
+
+        while distance <= length && stamps.len() < MAX_STAMPS_PER_LINE {
+            let mut rng = SprayRng::new(seed, stamps.len());
+
+            let t = if length > 0. { distance / length } else { 0. };
+
+            stamps.push(BrushStamp {
+                anchor: start.lerp(end, t),
+                offset: Vec2::angled(rng.next_f32() * TAU)
+                    * rng.next_f32()
+                    * settings.scatter
+                    * width,
+                rotation: (rng.next_f32() * 2. - 1.) * settings.rotation_jitter,
+                size: width * lerp(segment[0].pressure()..=segment[1].pressure(), t),
+            });
+
+            distance += spacing;
+        }
+
+        distance_to_next = distance - length;
+    }
+
+    stamps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_thin_line_is_capped() {
+        let points = [
+            LinePos::from(Pos2::ZERO),
+            LinePos::from(Pos2::new(1_000_000., 0.)),
+        ];
+
+        let stamps = brush_stamps(&points, 0.01, &StampSettings::default());
+
+        assert!(!stamps.is_empty());
+        assert!(stamps.len() <= MAX_STAMPS_PER_LINE);
+    }
+
+    #[test]
+    fn infinite_segments_are_skipped() {
+        let points = [
+            LinePos::from(Pos2::ZERO),
+            LinePos::from(Pos2::new(f32::INFINITY, 0.)),
+            LinePos::from(Pos2::new(0., 1.)),
+        ];
+
+        let stamps = brush_stamps(&points, 1., &StampSettings::default());
+
+        assert!(stamps.is_empty());
+    }
+}
@@ -0,0 +1,32 @@
+use egui::{Color32, Pos2};
+use strum::IntoStaticStr;
+
+/// The fonts a text can be written with, these are the fonts bundled with egui.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, IntoStaticStr, serde::Serialize, serde::Deserialize,
+)]
+pub enum TextFont {
+    #[default]
+    Proportional,
+    Monospace,
+}
+
+impl TextFont {
+    /// Every ```TextFont```, in the order they are displayed in.
+    pub const ALL: [Self; 2] = [Self::Proportional, Self::Monospace];
+}
+
+/// A text label placed on the canvas.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CanvasText {
+    /// The text, which can contain multiple lines.
+    pub text: String,
+    /// The top left corner of the text, in canvas units.
+    pub position: Pos2,
+    /// The size of the font in points.
+    pub size: f32,
+    /// The color of the text.
+    pub color: Color32,
+    /// The font the text is written with.
+    pub font: TextFont,
+}
@@ -0,0 +1,536 @@
+use common_definitions::{
+    brush_stamps, graffiti_particles, BackgroundStyle, Brush, BrushStamp, BrushType,
+    CanvasBackground, CanvasImage, CanvasShape, CanvasText, GraffitiParticle, LinePos,
+    StampSettings,
+};
+use egui::{emath::Rangef, Color32, Pos2, Rect, Vec2};
+use std::f32::consts::{FRAC_1_SQRT_2, TAU};
+
+mod pdf;
+mod raster;
+mod svg;
+mod text;
+
+pub use pdf::{
+    pdf_page_count, render_pdf, PageLayout, PdfOptions, A4_PAGE_SIZE, LETTER_PAGE_SIZE,
+    MAX_PDF_PAGES,
+};
+pub use raster::{render_pixmap, render_png, render_thumbnail};
+pub use svg::render_svg;
+
+pub use common_definitions::DEFAULT_CANVAS_SCALE;
+
+/// The number of segments the discs of variable width strokes are approximated with.
+const VARIABLE_WIDTH_JOIN_SEGMENTS: usize = 16;
+
+/// The largest width or height (In pixels) a rendered image can have.
+pub const MAX_RENDER_SIZE: f32 = 16384.;
+
+/// The width (In points) of the lines of the ```BackgroundStyle::Ruled``` and ```BackgroundStyle::Graph``` paper.
+const PAPER_LINE_WIDTH: f32 = 1.;
+
+/// The width (In points) of every ```GRAPH_MAJOR_LINE_INTERVAL```-th line of the ```BackgroundStyle::Graph``` paper.
+const MAJOR_PAPER_LINE_WIDTH: f32 = 1.5;
+
+/// The number of graph paper lines after which a stronger line is drawn.
+const GRAPH_MAJOR_LINE_INTERVAL: i64 = 5;
+
+/// The contents of the canvas which get rendered.
+/// The background is drawn first, then the images, the shapes, the lines and the texts, just like on the canvas.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanvasContent<'a> {
+    /// The background of the canvas, which is only drawn on the canvas' page if it is bounded to one.
+    pub background: CanvasBackground,
+    /// The image displayed by the ```BackgroundStyle::Image``` background.
+    pub background_image: Option<&'a CanvasImage>,
+    /// The images placed on the canvas, these are borrowed as their encoded bytes can be large.
+    pub images: &'a [&'a CanvasImage],
+    /// The shapes placed on the canvas.
+    pub shapes: &'a [CanvasShape],
+    /// The lines, in the order they are drawn in.
+    pub lines: &'a [(Vec<LinePos>, Brush)],
+    /// The texts placed on the canvas.
+    pub texts: &'a [CanvasText],
+}
+
+/// This struct contains the options a canvas is rendered with.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    /// The size of one canvas unit in points.
+    pub canvas_scale: f32,
+    /// The number of output pixels one point takes up.
+    pub scale: f32,
+    /// The background color of the rendered image, if it is ```None``` the background is transparent.
+    pub background: Option<Color32>,
+    /// The empty space left around the drawing in points.
+    /// This is only used when rendering the ```RenderArea::DrawingBounds```.
+    pub padding: f32,
+    /// The area of the canvas which gets rendered.
+    pub area: RenderArea,
+    /// The largest width or height (In pixels) the rendered image can have, it can't be larger than ```MAX_RENDER_SIZE```.
+    pub max_size: f32,
+}
+
+/// The area of the canvas which gets rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderArea {
+    /// The area covered by the drawing, with the padding around it.
+    DrawingBounds,
+    /// A fixed area of the canvas in canvas units, everything outside of it gets cropped.
+    Canvas(Rect),
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            canvas_scale: DEFAULT_CANVAS_SCALE,
+            scale: 1.,
+            background: None,
+            padding: 10.,
+            area: RenderArea::DrawingBounds,
+            max_size: MAX_RENDER_SIZE,
+        }
+    }
+}
+
+/// The properties of a stroke, shared by every renderer so that the output looks the same as ```draw_line_to_screen_with_brush```.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeStyle {
+    /// The width of the stroke in points.
+    pub width: f32,
+    /// The color of the stroke.
+    pub color: Color32,
+    /// The dash and gap lengths of the stroke in points, if it is dashed.
+    pub dash: Option<(f32, f32)>,
+    /// The density of the sprayed particles, if the line is sprayed (```BrushType::Graffiti```) instead of stroked.
+    pub spray: Option<u32>,
+    /// The stamp settings, if the line is stamped with a texture (```BrushType::Stamp```) instead of stroked.
+    pub stamp: Option<StampSettings>,
+}
+
+impl StrokeStyle {
+    /// Creates the ```StrokeStyle``` a ```Brush``` paints with.
+    /// Returns ```None``` if the ```BrushType``` doesn't paint anything.
+    pub fn from_brush(brush: &Brush) -> Option<Self> {
+        let Brush {
+            width,
+            color,
+            brush_type,
+            density,
+            stamp,
+            ..
+        } = *brush;
+
+        match brush_type {
+            BrushType::Pencil => Some(Self {
+                width,
+                color,
+                dash: Some((width, width)),
+                spray: None,
+                stamp: None,
+            }),
+            BrushType::Marker => Some(Self {
+                width,
+                color,
+                dash: None,
+                spray: None,
+                stamp: None,
+            }),
+            BrushType::Graffiti => Some(Self {
+                width,
+                color,
+                dash: None,
+                spray: Some(density),
+                stamp: None,
+            }),
+            BrushType::Stamp => Some(Self {
+                width,
+                color,
+                dash: None,
+                spray: None,
+                stamp: Some(stamp),
+            }),
+            BrushType::Shape | BrushType::Text | BrushType::Eraser | BrushType::None => None,
+        }
+    }
+
+    /// Returns the particles of a sprayed line, or ```None``` if the line is stroked.
+    pub fn particles(&self, points: &[LinePos]) -> Option<Vec<GraffitiParticle>> {
+        self.spray
+            .map(|density| graffiti_particles(points, self.width / 2., density))
+    }
+
+    /// Returns whether the line is drawn with a variable width, because its points were drawn with less than full pressure.
+    /// Only solid strokes are drawn with a variable width, dashed strokes keep the brush's width.
+    pub fn is_variable_width(&self, points: &[LinePos]) -> bool {
+        self.dash.is_none()
+            && self.spray.is_none()
+            && self.stamp.is_none()
+            && points.iter().any(|pos| pos.pressure() < 1.)
+    }
+
+    /// Returns how far (In points) the painted area reaches from the points of the line.
+    pub fn extent(&self) -> f32 {
+        match (self.spray, self.stamp) {
+            // The particles' radius is at most 2 points
+            (Some(_), _) => self.width / 2. + 2.,
+            // The corners of rotated and scattered stamps reach the furthest
+            (_, Some(stamp)) => self.width * (FRAC_1_SQRT_2 + stamp.scatter),
+            (None, None) => self.width / 2.,
+        }
+    }
+
+    /// Returns the stamps of a stamped line, or ```None``` if the line is stroked.
+    pub fn stamps(&self, points: &[LinePos]) -> Option<Vec<BrushStamp>> {
+        self.stamp
+            .map(|settings| brush_stamps(points, self.width, &settings))
+    }
+}
+
+/// Returns the lines which are visible, with the ```StrokeStyle``` they are drawn with.
+pub(crate) fn visible_lines(
+    lines: &[(Vec<LinePos>, Brush)],
+) -> impl Iterator<Item = (&[LinePos], StrokeStyle)> {
+    lines
+        .iter()
+        .filter(|line| line.0.len() >= 2)
+        .filter_map(|line| Some((line.0.as_slice(), StrokeStyle::from_brush(&line.1)?)))
+}
+
+/// The outline of a ```CanvasShape``` in the rendered image.
+pub(crate) struct ShapeOutline {
+    /// The points of the outline, which is stroked with the shape's stroke width and filled with its fill color.
+    pub points: Vec<Pos2>,
+    /// Whether the outline connects its last point to its first one.
+    pub closed: bool,
+    /// The corners of an arrow's head, which is filled with the shape's stroke color.
+    pub arrow_head: Option<[Pos2; 3]>,
+}
+
+/// Returns the outline of the shape in the rendered image, the same way ```shape_to_screen``` draws it on the canvas.
+pub(crate) fn shape_outline(shape: &CanvasShape, frame: &CanvasFrame) -> ShapeOutline {
+    let mut points: Vec<Pos2> = shape
+        .outline()
+        .iter()
+        .map(|pos| frame.to_output((*pos).into()))
+        .collect();
+
+    let arrow_head = shape
+        .arrow_head(shape.arrow_head_length() / frame.canvas_scale)
+        .map(|head| head.map(|pos| frame.to_output(pos.into())));
+
+    // The line ends at the base of the head, so that it doesn't poke through the tip
+    if let (Some(head), Some(end)) = (arrow_head, points.last_mut()) {
+        *end = head[1].lerp(head[2], 0.5);
+    }
+
+    ShapeOutline {
+        points,
+        closed: shape.kind.is_closed(),
+        arrow_head,
+    }
+}
+
+/// The background of the canvas in the rendered image, the same way ```draw_background``` draws it on the canvas.
+pub(crate) struct BackgroundLayout<'a> {
+    /// The area covered by the background, this is the canvas' page if it is bounded to one, otherwise the whole image.
+    pub area: Rect,
+    /// The color the area is filled with, if the background isn't transparent.
+    pub fill: Option<Color32>,
+    /// The endpoints and the width of the ruled or graph paper's lines.
+    pub lines: Vec<([Pos2; 2], f32)>,
+    /// The color of the paper's lines.
+    pub line_color: Color32,
+    /// The background image, with the area it's stretched onto.
+    pub image: Option<(&'a CanvasImage, Rect)>,
+}
+
+/// Returns the positions of the multiples of ```step``` in ```range```.
+fn steps_in_range(range: Rangef, step: f32) -> impl Iterator<Item = f32> {
+    let first = (range.min / step).ceil() as i64;
+    let last = (range.max / step).floor() as i64;
+
+    (first..=last).map(move |idx| idx as f32 * step)
+}
+
+/// Returns the background of the canvas in the rendered image.
+pub(crate) fn background_layout<'a>(
+    content: &CanvasContent<'a>,
+    frame: &CanvasFrame,
+) -> BackgroundLayout<'a> {
+    let background = &content.background;
+    let page_rect = background.page_rect();
+
+    // The rendered area in canvas units, the background only covers the part of it which is on the page
+    let rendered_area = Rect::from_min_max(
+        frame.area.min / frame.canvas_scale,
+        frame.area.max / frame.canvas_scale,
+    );
+    let area = page_rect.map_or(rendered_area, |page_rect| {
+        page_rect.intersect(rendered_area)
+    });
+
+    let image = content
+        .background_image
+        .filter(|_| background.style == BackgroundStyle::Image)
+        .map(|image| (image, frame.rect_to_output(page_rect.unwrap_or(image.rect))));
+
+    if !area.is_positive() {
+        return BackgroundLayout {
+            area: Rect::NOTHING,
+            fill: None,
+            lines: Vec::new(),
+            line_color: background.line_color,
+            image,
+        };
+    }
+
+    let spacing = background.line_spacing.max(1.) / DEFAULT_CANVAS_SCALE;
+    let line_width = |position: f32| {
+        let is_major = background.style == BackgroundStyle::Graph
+            && (position / spacing).round() as i64 % GRAPH_MAJOR_LINE_INTERVAL == 0;
+
+        if is_major {
+            MAJOR_PAPER_LINE_WIDTH * frame.scale
+        } else {
+            PAPER_LINE_WIDTH * frame.scale
+        }
+    };
+
+    let mut lines = Vec::new();
+
+    if matches!(
+        background.style,
+        BackgroundStyle::Ruled | BackgroundStyle::Graph
+    ) {
+        for y in steps_in_range(area.y_range(), spacing) {
+            lines.push((
+                [
+                    frame.to_output(Pos2::new(area.min.x, y).into()),
+                    frame.to_output(Pos2::new(area.max.x, y).into()),
+                ],
+                line_width(y),
+            ));
+        }
+    }
+
+    if background.style == BackgroundStyle::Graph {
+        for x in steps_in_range(area.x_range(), spacing) {
+            lines.push((
+                [
+                    frame.to_output(Pos2::new(x, area.min.y).into()),
+                    frame.to_output(Pos2::new(x, area.max.y).into()),
+                ],
+                line_width(x),
+            ));
+        }
+    }
+
+    BackgroundLayout {
+        area: frame.rect_to_output(area),
+        fill: background.fill_color(),
+        lines,
+        line_color: background.line_color,
+        image,
+    }
+}
+
+/// Decodes the encoded (png or jpeg) bytes of a ```CanvasImage``` into rgba pixels.
+pub(crate) fn decode_image(image: &CanvasImage) -> anyhow::Result<image::RgbaImage> {
+    Ok(image::load_from_memory(&image.data)?.to_rgba8())
+}
+
+/// Returns the outline of a variable width stroke in the rendered image, as polygons which have to be filled with the nonzero rule.
+/// The outline consists of a disc at every point and a quad connecting the neighbouring points, all of them wound in the same direction so that they get filled as one shape.
+pub(crate) fn variable_width_outline(
+    points: &[LinePos],
+    style: &StrokeStyle,
+    frame: &CanvasFrame,
+) -> Vec<Vec<Pos2>> {
+    let output_points: Vec<(Pos2, f32)> = points
+        .iter()
+        .map(|pos| {
+            (
+                frame.to_output(*pos),
+                style.width * pos.pressure() * frame.scale() / 2.,
+            )
+        })
+        .collect();
+
+    let discs = output_points.iter().map(|(center, radius)| {
+        (0..VARIABLE_WIDTH_JOIN_SEGMENTS)
+            .map(|idx| {
+                *center
+                    + Vec2::angled(idx as f32 / VARIABLE_WIDTH_JOIN_SEGMENTS as f32 * TAU) * *radius
+            })
+            .collect::<Vec<Pos2>>()
+    });
+
+    let quads = output_points.windows(2).filter_map(|segment| {
+        let [(start, start_radius), (end, end_radius)] = [segment[0], segment[1]];
+        let normal = (end - start).normalized().rot90();
+
+        if normal == Vec2::ZERO {
+            return None;
+        }
+
+        let quad = vec![
+            start + normal * start_radius,
+            end + normal * end_radius,
+            end - normal * end_radius,
+            start - normal * start_radius,
+        ];
+
+        // The discs have a positive signed area, the quads are reversed if they're wound the other way
+        Some(if signed_area(&quad) < 0. {
+            quad.into_iter().rev().collect()
+        } else {
+            quad
+        })
+    });
+
+    discs.chain(quads).collect()
+}
+
+/// Returns the signed area of the polygon (Shoelace formula).
+fn signed_area(polygon: &[Pos2]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum::<f32>()
+        / 2.
+}
+
+/// Returns the area (In points) the contents of the canvas cover, including the width of the strokes.
+/// Returns ```Rect::NOTHING``` if there aren't any images, shapes, visible lines or texts.
+pub fn drawing_bounds(content: &CanvasContent, canvas_scale: f32) -> Rect {
+    let image_bounds = content.images.iter().fold(Rect::NOTHING, |bounds, image| {
+        bounds.union(Rect::from_min_max(
+            image.rect.min * canvas_scale,
+            image.rect.max * canvas_scale,
+        ))
+    });
+
+    let shape_bounds = content.shapes.iter().fold(image_bounds, |bounds, shape| {
+        let head_length = shape.arrow_head_length() / canvas_scale;
+        let points: Vec<Pos2> = shape
+            .outline()
+            .into_iter()
+            .chain(shape.arrow_head(head_length).into_iter().flatten())
+            .map(|pos| pos * canvas_scale)
+            .collect();
+
+        bounds.union(Rect::from_points(&points).expand(shape.stroke_width / 2.))
+    });
+
+    let text_bounds = content.texts.iter().fold(shape_bounds, |bounds, text| {
+        bounds.union(Rect::from_min_size(
+            text.position * canvas_scale,
+            text::text_size(text),
+        ))
+    });
+
+    visible_lines(content.lines).fold(text_bounds, |bounds, (points, style)| {
+        let line_rect = Rect::from_points(
+            &points
+                .iter()
+                .map(|pos| Pos2::from(*pos) * canvas_scale)
+                .collect::<Vec<Pos2>>(),
+        )
+        .expand(style.extent());
+
+        bounds.union(line_rect)
+    })
+}
+
+/// This struct maps canvas positions into the coordinate system of the rendered image.
+#[derive(Debug, Clone, Copy)]
+pub struct CanvasFrame {
+    /// The area of the canvas which gets rendered, in points.
+    area: Rect,
+    /// The size of one canvas unit in points.
+    canvas_scale: f32,
+    /// The number of output pixels one point takes up.
+    scale: f32,
+    /// The largest width or height (In pixels) the rendered image can have.
+    max_size: f32,
+}
+
+impl CanvasFrame {
+    /// Creates a ```CanvasFrame``` which contains the ```RenderArea``` specified in the ```RenderOptions```.
+    /// The scale is lowered if the rendered image would be larger than the ```max_size``` of the ```RenderOptions```, so that the whole area fits into the image instead of getting cropped.
+    pub fn new(content: &CanvasContent, options: &RenderOptions) -> Self {
+        let area = match options.area {
+            RenderArea::DrawingBounds => {
+                let bounds = drawing_bounds(content, options.canvas_scale);
+
+                if bounds.is_positive() {
+                    bounds.expand(options.padding)
+                } else {
+                    Rect::from_min_size(Pos2::ZERO, Vec2::splat(options.padding * 2.))
+                }
+            }
+            RenderArea::Canvas(canvas_area) => Rect::from_min_max(
+                canvas_area.min * options.canvas_scale,
+                canvas_area.max * options.canvas_scale,
+            ),
+        };
+
+        let max_size = options.max_size.clamp(1., MAX_RENDER_SIZE);
+
+        Self {
+            area,
+            canvas_scale: options.canvas_scale,
+            scale: options.scale.min(max_size / area.size().max_elem()),
+            max_size,
+        }
+    }
+
+    /// Returns the size of the rendered image in pixels.
+    /// The size is clamped, so that it's always at least one pixel, and doesn't go over its largest size when it's rounded up.
+    pub fn size(&self) -> Vec2 {
+        (self.area.size() * self.scale)
+            .ceil()
+            .clamp(Vec2::splat(1.), Vec2::splat(self.max_size))
+    }
+
+    /// Maps a canvas position into the rendered image.
+    pub fn to_output(&self, pos: LinePos) -> Pos2 {
+        ((Pos2::from(pos) * self.canvas_scale - self.area.min) * self.scale).to_pos2()
+    }
+
+    /// Maps an area of the canvas (In canvas units) into the rendered image.
+    pub fn rect_to_output(&self, rect: Rect) -> Rect {
+        Rect::from_min_max(
+            self.to_output(rect.min.into()),
+            self.to_output(rect.max.into()),
+        )
+    }
+
+    /// Returns the center and the radius of a ```GraffitiParticle``` in the rendered image.
+    pub fn particle_to_output(&self, particle: &GraffitiParticle) -> (Pos2, f32) {
+        (
+            self.to_output(particle.anchor.into()) + particle.offset * self.scale,
+            particle.radius * self.scale,
+        )
+    }
+
+    /// Returns the center and the size of a ```BrushStamp``` in the rendered image.
+    pub fn stamp_to_output(&self, stamp: &BrushStamp) -> (Pos2, f32) {
+        (
+            self.to_output(stamp.anchor.into()) + stamp.offset * self.scale,
+            stamp.size * self.scale,
+        )
+    }
+
+    /// Returns the area of the canvas which gets rendered, in points.
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    /// Returns the number of output pixels one point takes up.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+}
@@ -0,0 +1,105 @@
+use std::{env, fs, path::PathBuf};
+
+use common_definitions::{CanvasImage, CanvasShape, CanvasText, ImageFile};
+use drawing_board_renderer::{
+    render_pdf, render_png, render_svg, render_thumbnail, CanvasContent, PageLayout, PdfOptions,
+    RenderArea, RenderOptions, MAX_RENDER_SIZE,
+};
+use egui::vec2;
+
+const USAGE: &str = "Usage: drawing_board_render <input.dbimg> <output.png|output.svg|output.pdf> [--scale <scale>] [--max-size <max size>] [--thumbnail <max size>] [--page-size <width>x<height>] [--tile-pages <true|false>]";
+
+/// The largest width or height (In pixels) of the rendered images, unless a larger one is passed with ```--max-size```.
+const DEFAULT_MAX_SIZE: f32 = 4096.;
+
+/// Renders a saved drawing board image (```.dbimg```) into a png, an svg or a pdf file without opening a window.
+fn main() -> anyhow::Result<()> {
+    let mut args = env::args().skip(1);
+
+    let (Some(input_path), Some(output_path)) = (args.next(), args.next()) else {
+        return Err(anyhow::Error::msg(USAGE));
+    };
+
+    let output_path = PathBuf::from(output_path);
+
+    let mut options = RenderOptions {
+        max_size: DEFAULT_MAX_SIZE,
+        ..Default::default()
+    };
+    let mut pdf_options = PdfOptions::default();
+    let mut thumbnail_size: Option<u32> = None;
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| anyhow::Error::msg(USAGE))?;
+
+        match arg.as_str() {
+            "--scale" => options.scale = value.parse()?,
+            "--max-size" => options.max_size = value.parse::<f32>()?.clamp(1., MAX_RENDER_SIZE),
+            "--thumbnail" => thumbnail_size = Some(value.parse()?),
+            "--page-size" => {
+                let (width, height) = value
+                    .split_once('x')
+                    .ok_or_else(|| anyhow::Error::msg(USAGE))?;
+
+                pdf_options.page_size = vec2(width.parse()?, height.parse()?);
+            }
+            "--tile-pages" => {
+                pdf_options.layout = if value.parse::<bool>()? {
+                    PageLayout::Tile
+                } else {
+                    PageLayout::Fit
+                }
+            }
+            _ => return Err(anyhow::Error::msg(USAGE)),
+        }
+    }
+
+    let image_file = ImageFile::from_bytes(&fs::read(input_path)?)?;
+
+    // Hidden layers aren't rendered, and the lines and shapes are ordered by their layers like on the server
+    let lines = image_file.layers.visible_lines(&image_file.lines);
+    let images: Vec<&CanvasImage> = image_file.images.values().collect();
+    let shapes: Vec<CanvasShape> = image_file
+        .layers
+        .layered_shapes(image_file.shapes.values())
+        .collect();
+    let texts: Vec<CanvasText> = image_file.texts.values().cloned().collect();
+
+    let content = CanvasContent {
+        background: image_file.background,
+        background_image: image_file.background_image.as_ref(),
+        images: &images,
+        shapes: &shapes,
+        lines: &lines,
+        texts: &texts,
+    };
+
+    // The canvas is rendered onto its background, and cropped to its page
+    if let Some(page_rect) = image_file.background.page_rect() {
+        options.area = RenderArea::Canvas(page_rect);
+    }
+
+    match output_path.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => {
+            let png = match thumbnail_size {
+                Some(max_size) => render_thumbnail(&content, max_size)?,
+                None => render_png(&content, &options)?,
+            };
+
+            fs::write(output_path, png)?;
+        }
+        Some("svg") => {
+            fs::write(output_path, render_svg(&content, &options))?;
+        }
+        Some("pdf") => {
+            fs::write(output_path, render_pdf(&content, &options, &pdf_options)?)?;
+        }
+        _ => {
+            return Err(anyhow::Error::msg(
+                "The output file must be a .png, an .svg or a .pdf file.",
+            ))
+        }
+    }
+
+    Ok(())
+}
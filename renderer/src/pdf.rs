@@ -0,0 +1,883 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use common_definitions::{BrushTexture, CanvasImage, CanvasShape};
+use egui::{vec2, Color32, Pos2, Vec2};
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::{
+    types::{LineCapStyle, LineJoinStyle},
+    Content, Filter, Finish, Name, Pdf, Rect, Ref,
+};
+use tiny_skia::Pixmap;
+
+use crate::{
+    background_layout, decode_image, shape_outline,
+    text::{text_outline, PathCommand},
+    variable_width_outline, visible_lines, CanvasContent, CanvasFrame, RenderOptions,
+};
+
+/// The size of an A4 page in points.
+pub const A4_PAGE_SIZE: Vec2 = vec2(595., 842.);
+/// The size of a US Letter page in points.
+pub const LETTER_PAGE_SIZE: Vec2 = vec2(612., 792.);
+
+/// The largest number of pages a pdf document can have, so that tiling a large drawing at a large scale can't create an unbounded document.
+pub const MAX_PDF_PAGES: usize = 500;
+
+/// The name of the form ```XObject``` containing the drawing, which every page draws.
+const DRAWING_NAME: &[u8] = b"D";
+
+/// How the drawing is placed onto the pages of the pdf document.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PageLayout {
+    /// The drawing is scaled to fit onto a single page.
+    #[default]
+    Fit,
+    /// The drawing is rendered at the scale set in the ```RenderOptions```, and gets split across as many pages as needed.
+    Tile,
+}
+
+/// This struct contains the page options of a pdf document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PdfOptions {
+    /// The size of a page in points.
+    pub page_size: Vec2,
+    /// The empty space left on the edges of every page in points.
+    pub margin: f32,
+    /// How the drawing is placed onto the pages.
+    pub layout: PageLayout,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: A4_PAGE_SIZE,
+            margin: 36.,
+            layout: PageLayout::default(),
+        }
+    }
+}
+
+impl PdfOptions {
+    /// Returns the size of the area the drawing can be placed on, on every page.
+    fn content_size(&self) -> Vec2 {
+        (self.page_size - Vec2::splat(self.margin * 2.)).max(Vec2::splat(1.))
+    }
+
+    /// Returns the number of columns and rows of pages the drawing is tiled across.
+    fn tile_grid(&self, area_size: Vec2, scale: f32) -> (usize, usize) {
+        let area_size = area_size.max(Vec2::splat(1.));
+        let content_size = self.content_size();
+
+        // Casting saturates, so a huge drawing gets a huge page count instead of wrapping around
+        (
+            (area_size.x * scale / content_size.x).ceil().max(1.) as usize,
+            (area_size.y * scale / content_size.y).ceil().max(1.) as usize,
+        )
+    }
+
+    /// Returns the number of pages the drawing is placed onto, without computing their transformations.
+    fn page_count(&self, area_size: Vec2, scale: f32) -> usize {
+        match self.layout {
+            PageLayout::Fit => 1,
+            PageLayout::Tile => {
+                let (columns, rows) = self.tile_grid(area_size, scale);
+
+                columns.saturating_mul(rows)
+            }
+        }
+    }
+
+    /// Returns the transformation matrices of the pages, mapping the rendered area of the canvas (In points) onto the pages.
+    /// The transformations also flip the y axis, as pdf coordinates start from the bottom left corner of the page.
+    fn page_transforms(&self, area_size: Vec2, scale: f32) -> Vec<[f32; 6]> {
+        let area_size = area_size.max(Vec2::splat(1.));
+        let content_size = self.content_size();
+
+        match self.layout {
+            PageLayout::Fit => {
+                let scale = (content_size / area_size).min_elem();
+                let offset = (content_size - area_size * scale) / 2.;
+
+                vec![[
+                    scale,
+                    0.,
+                    0.,
+                    -scale,
+                    self.margin + offset.x,
+                    self.page_size.y - self.margin - offset.y,
+                ]]
+            }
+            PageLayout::Tile => {
+                let (columns, rows) = self.tile_grid(area_size, scale);
+
+                (0..rows)
+                    .flat_map(|row| {
+                        (0..columns).map(move |column| {
+                            [
+                                scale,
+                                0.,
+                                0.,
+                                -scale,
+                                self.margin - column as f32 * content_size.x,
+                                self.page_size.y - self.margin + row as f32 * content_size.y,
+                            ]
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Returns the number of pages the pdf document of the canvas' contents would have.
+pub fn pdf_page_count(
+    content: &CanvasContent,
+    options: &RenderOptions,
+    pdf_options: &PdfOptions,
+) -> usize {
+    let area_size = CanvasFrame::new(content, options).area().size();
+
+    pdf_options.page_count(area_size, options.scale)
+}
+
+/// Converts an ```egui::Color32``` into pdf rgb and alpha values.
+fn to_pdf_color(color: Color32) -> ([f32; 3], u8) {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+
+    ([r, g, b].map(|channel| channel as f32 / 255.), a)
+}
+
+/// Returns the name of the ```ExtGState``` which sets the opacity to ```alpha```.
+fn alpha_state_name(alpha: u8) -> String {
+    format!("A{alpha}")
+}
+
+/// Returns the name of the image ```XObject``` of the ```idx```-th stamp texture.
+fn stamp_texture_name(idx: usize) -> String {
+    format!("T{idx}")
+}
+
+/// Returns the name of the image ```XObject``` of the ```idx```-th ```CanvasImage```.
+fn image_name(idx: usize) -> String {
+    format!("I{idx}")
+}
+
+/// A ```CanvasImage``` decoded into the pixel data of an image ```XObject```, the channels are compressed with zlib.
+struct EmbeddedImage {
+    width: u32,
+    height: u32,
+    rgb: Vec<u8>,
+    /// The alpha channel of the image, which is used as the image's soft mask.
+    alpha: Vec<u8>,
+}
+
+impl EmbeddedImage {
+    fn new(image: &CanvasImage) -> Option<Self> {
+        let decoded_image = decode_image(image).ok()?;
+
+        let rgb: Vec<u8> = decoded_image
+            .pixels()
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+        let alpha: Vec<u8> = decoded_image.pixels().map(|pixel| pixel[3]).collect();
+
+        Some(Self {
+            width: decoded_image.width(),
+            height: decoded_image.height(),
+            rgb: compress_to_vec_zlib(&rgb, 6),
+            alpha: compress_to_vec_zlib(&alpha, 6),
+        })
+    }
+}
+
+/// A texture of a ```BrushType::Stamp``` line, which is embedded as an image tinted with the line's color.
+struct StampTexture {
+    texture: BrushTexture,
+    color: [u8; 3],
+    width: u32,
+    height: u32,
+    /// The alpha channel of the texture, which is used as the image's soft mask.
+    alpha: Vec<u8>,
+}
+
+impl StampTexture {
+    fn new(texture: BrushTexture, color: [u8; 3]) -> Option<Self> {
+        let pixmap = Pixmap::decode_png(texture.png_bytes()).ok()?;
+
+        Some(Self {
+            texture,
+            color,
+            width: pixmap.width(),
+            height: pixmap.height(),
+            alpha: pixmap.pixels().iter().map(|pixel| pixel.alpha()).collect(),
+        })
+    }
+}
+
+/// Returns the transformation matrix which maps the unit square of an image onto a stamp.
+/// The image is flipped vertically, as the drawing's y axis points downwards.
+fn stamp_transform(center: Pos2, size: f32, rotation: f32) -> [f32; 6] {
+    let (sin, cos) = rotation.sin_cos();
+    let (a, b, c, d) = (size * cos, size * sin, size * sin, -size * cos);
+
+    [a, b, c, d, center.x - (a + c) / 2., center.y - (b + d) / 2.]
+}
+
+/// Adds the polygon to the current path of the ```Content```.
+fn polygon(content: &mut Content, points: &[Pos2], closed: bool) {
+    for (idx, pos) in points.iter().enumerate() {
+        if idx == 0 {
+            content.move_to(pos.x, pos.y);
+        } else {
+            content.line_to(pos.x, pos.y);
+        }
+    }
+
+    if closed {
+        content.close_path();
+    }
+}
+
+/// Draws the image stretched onto the ```rect``` of the drawing, the image gets added to ```embedded_images```.
+fn draw_image(
+    content: &mut Content,
+    embedded_images: &mut Vec<EmbeddedImage>,
+    image: &CanvasImage,
+    rect: egui::Rect,
+) {
+    // Images which can't be decoded are skipped, just like on the canvas
+    let Some(embedded_image) = EmbeddedImage::new(image) else {
+        return;
+    };
+
+    // The image's unit square is flipped vertically, as the drawing's y axis points downwards
+    content.save_state();
+    content.transform([rect.width(), 0., 0., -rect.height(), rect.min.x, rect.max.y]);
+    content.x_object(Name(image_name(embedded_images.len()).as_bytes()));
+    content.restore_state();
+
+    embedded_images.push(embedded_image);
+}
+
+/// Draws the canvas' background color, paper lines and image, the opacities used are added to ```used_alphas```.
+fn draw_background(
+    content: &mut Content,
+    used_alphas: &mut BTreeSet<u8>,
+    embedded_images: &mut Vec<EmbeddedImage>,
+    canvas_content: &CanvasContent,
+    frame: &CanvasFrame,
+) {
+    let background = background_layout(canvas_content, frame);
+
+    if let Some(fill) = background.fill {
+        let ([r, g, b], alpha) = to_pdf_color(fill);
+
+        used_alphas.insert(alpha);
+        content.set_parameters(Name(alpha_state_name(alpha).as_bytes()));
+        content.set_fill_rgb(r, g, b);
+        content.rect(
+            background.area.min.x,
+            background.area.min.y,
+            background.area.width(),
+            background.area.height(),
+        );
+        content.fill_nonzero();
+    }
+
+    if !background.lines.is_empty() {
+        let ([r, g, b], alpha) = to_pdf_color(background.line_color);
+
+        used_alphas.insert(alpha);
+        content.set_parameters(Name(alpha_state_name(alpha).as_bytes()));
+        content.set_stroke_rgb(r, g, b);
+        content.set_line_cap(LineCapStyle::ButtCap);
+        content.set_dash_pattern([0.; 0], 0.);
+
+        for ([start, end], width) in &background.lines {
+            content.set_line_width(*width);
+            content.move_to(start.x, start.y);
+            content.line_to(end.x, end.y);
+            content.stroke();
+        }
+    }
+
+    if let Some((image, rect)) = background.image {
+        draw_image(content, embedded_images, image, rect);
+    }
+}
+
+/// Draws the shape's fill, outline and arrow head, the opacities used are added to ```used_alphas```.
+fn draw_shape(
+    content: &mut Content,
+    used_alphas: &mut BTreeSet<u8>,
+    shape: &CanvasShape,
+    frame: &CanvasFrame,
+) {
+    let outline = shape_outline(shape, frame);
+
+    if let Some(fill) = shape.fill_color() {
+        let ([r, g, b], alpha) = to_pdf_color(fill);
+
+        used_alphas.insert(alpha);
+        content.set_parameters(Name(alpha_state_name(alpha).as_bytes()));
+        content.set_fill_rgb(r, g, b);
+
+        polygon(content, &outline.points, true);
+        content.fill_nonzero();
+    }
+
+    let ([r, g, b], alpha) = to_pdf_color(shape.stroke_color);
+
+    used_alphas.insert(alpha);
+    content.set_parameters(Name(alpha_state_name(alpha).as_bytes()));
+
+    if let Some(head) = outline.arrow_head {
+        content.set_fill_rgb(r, g, b);
+
+        polygon(content, &head, true);
+        content.fill_nonzero();
+    }
+
+    content.set_stroke_rgb(r, g, b);
+    content.set_line_width(shape.stroke_width);
+    content.set_line_cap(LineCapStyle::ButtCap);
+    content.set_line_join(LineJoinStyle::MiterJoin);
+    content.set_dash_pattern([0.; 0], 0.);
+
+    polygon(content, &outline.points, outline.closed);
+    content.stroke();
+}
+
+/// Adds a circle to the current path of the ```Content```, approximated with four cubic bezier curves.
+fn circle(content: &mut Content, center: Pos2, radius: f32) {
+    // The distance of the control points from the curves' endpoints, for a quarter circle
+    const KAPPA: f32 = 0.552_284_8;
+
+    let control = radius * KAPPA;
+    let (x, y) = (center.x, center.y);
+
+    content.move_to(x + radius, y);
+    content.cubic_to(
+        x + radius,
+        y + control,
+        x + control,
+        y + radius,
+        x,
+        y + radius,
+    );
+    content.cubic_to(
+        x - control,
+        y + radius,
+        x - radius,
+        y + control,
+        x - radius,
+        y,
+    );
+    content.cubic_to(
+        x - radius,
+        y - control,
+        x - control,
+        y - radius,
+        x,
+        y - radius,
+    );
+    content.cubic_to(
+        x + control,
+        y - radius,
+        x + radius,
+        y - control,
+        x + radius,
+        y,
+    );
+    content.close_path();
+}
+
+/// Renders the contents of the canvas into a pdf document, returning the bytes of the document.
+/// The lines and the shapes are drawn as vector strokes and the texts as the outlines of their glyphs, so the document can be printed at any resolution.
+/// Images are embedded with their original resolution.
+/// The drawing is stored once as a form ```XObject```, which every page draws with its own transformation and clipping.
+/// Returns an error if the document would have more than ```MAX_PDF_PAGES``` pages.
+pub fn render_pdf(
+    content: &CanvasContent,
+    options: &RenderOptions,
+    pdf_options: &PdfOptions,
+) -> anyhow::Result<Vec<u8>> {
+    // Lines are drawn in points, the pages' transformations take care of the scaling, so the drawing isn't limited to MAX_RENDER_SIZE
+    let frame = CanvasFrame {
+        scale: 1.,
+        ..CanvasFrame::new(content, options)
+    };
+    let area_size = frame.area().size();
+
+    let page_count = pdf_options.page_count(area_size, options.scale);
+
+    if page_count > MAX_PDF_PAGES {
+        return Err(anyhow::Error::msg(format!(
+            "The pdf document would have {page_count} pages, the limit is {MAX_PDF_PAGES} pages. Lower the scale, or fit the drawing onto a single page."
+        )));
+    }
+
+    let mut drawing = Content::new();
+    let mut used_alphas: BTreeSet<u8> = BTreeSet::new();
+    let mut stamp_textures: Vec<StampTexture> = Vec::new();
+    let mut embedded_images: Vec<EmbeddedImage> = Vec::new();
+
+    if let Some(background) = options.background {
+        let ([r, g, b], alpha) = to_pdf_color(background);
+
+        used_alphas.insert(alpha);
+        drawing.set_parameters(Name(alpha_state_name(alpha).as_bytes()));
+        drawing.set_fill_rgb(r, g, b);
+        drawing.rect(0., 0., area_size.x, area_size.y);
+        drawing.fill_nonzero();
+    }
+
+    draw_background(
+        &mut drawing,
+        &mut used_alphas,
+        &mut embedded_images,
+        content,
+        &frame,
+    );
+
+    for image in content.images {
+        draw_image(
+            &mut drawing,
+            &mut embedded_images,
+            image,
+            frame.rect_to_output(image.rect),
+        );
+    }
+
+    for shape in content.shapes {
+        draw_shape(&mut drawing, &mut used_alphas, shape, &frame);
+    }
+
+    for (points, style) in visible_lines(content.lines) {
+        let ([r, g, b], alpha) = to_pdf_color(style.color);
+
+        used_alphas.insert(alpha);
+        drawing.set_parameters(Name(alpha_state_name(alpha).as_bytes()));
+
+        if let (Some(stamps), Some(settings)) = (style.stamps(points), style.stamp) {
+            let [r, g, b, _] = style.color.to_srgba_unmultiplied();
+
+            let idx = match stamp_textures.iter().position(|stamp_texture| {
+                stamp_texture.texture == settings.texture && stamp_texture.color == [r, g, b]
+            }) {
+                Some(idx) => idx,
+                None => {
+                    let Some(stamp_texture) = StampTexture::new(settings.texture, [r, g, b]) else {
+                        continue;
+                    };
+
+                    stamp_textures.push(stamp_texture);
+                    stamp_textures.len() - 1
+                }
+            };
+
+            let name = stamp_texture_name(idx);
+
+            for stamp in &stamps {
+                let (center, size) = frame.stamp_to_output(stamp);
+
+                drawing.save_state();
+                drawing.transform(stamp_transform(center, size, stamp.rotation));
+                drawing.x_object(Name(name.as_bytes()));
+                drawing.restore_state();
+            }
+
+            continue;
+        }
+
+        if let Some(particles) = style.particles(points) {
+            drawing.set_fill_rgb(r, g, b);
+
+            // Every particle is filled separately, so that overlapping particles get darker just like on the canvas
+            for particle in &particles {
+                let (center, radius) = frame.particle_to_output(particle);
+
+                circle(&mut drawing, center, radius);
+                drawing.fill_nonzero();
+            }
+
+            continue;
+        }
+
+        if style.is_variable_width(points) {
+            drawing.set_fill_rgb(r, g, b);
+
+            for polygon in variable_width_outline(points, &style, &frame) {
+                for (idx, pos) in polygon.iter().enumerate() {
+                    if idx == 0 {
+                        drawing.move_to(pos.x, pos.y);
+                    } else {
+                        drawing.line_to(pos.x, pos.y);
+                    }
+                }
+
+                drawing.close_path();
+            }
+
+            drawing.fill_nonzero();
+
+            continue;
+        }
+
+        drawing.set_stroke_rgb(r, g, b);
+        drawing.set_line_width(style.width);
+        drawing.set_line_cap(LineCapStyle::ButtCap);
+        drawing.set_line_join(LineJoinStyle::RoundJoin);
+
+        match style.dash {
+            Some((dash_length, gap_length)) => {
+                drawing.set_dash_pattern([dash_length, gap_length], 0.);
+            }
+            None => {
+                drawing.set_dash_pattern([0.; 0], 0.);
+            }
+        }
+
+        for (idx, pos) in points.iter().enumerate() {
+            let output_pos = frame.to_output(*pos);
+
+            if idx == 0 {
+                drawing.move_to(output_pos.x, output_pos.y);
+            } else {
+                drawing.line_to(output_pos.x, output_pos.y);
+            }
+        }
+
+        drawing.stroke();
+    }
+
+    for text in content.texts {
+        let ([r, g, b], alpha) = to_pdf_color(text.color);
+
+        used_alphas.insert(alpha);
+        drawing.set_parameters(Name(alpha_state_name(alpha).as_bytes()));
+        drawing.set_fill_rgb(r, g, b);
+
+        let mut current_pos = Pos2::ZERO;
+        let mut has_outline = false;
+
+        for command in text_outline(text, &frame) {
+            match command {
+                PathCommand::MoveTo(pos) => drawing.move_to(pos.x, pos.y),
+                PathCommand::LineTo(pos) => drawing.line_to(pos.x, pos.y),
+                // Pdf has no quadratic curves, they are converted into cubic ones
+                PathCommand::QuadTo(control, pos) => {
+                    let first_control = current_pos + (control - current_pos) * 2. / 3.;
+                    let second_control = pos + (control - pos) * 2. / 3.;
+
+                    drawing.cubic_to(
+                        first_control.x,
+                        first_control.y,
+                        second_control.x,
+                        second_control.y,
+                        pos.x,
+                        pos.y,
+                    )
+                }
+                PathCommand::CubicTo(first_control, second_control, pos) => drawing.cubic_to(
+                    first_control.x,
+                    first_control.y,
+                    second_control.x,
+                    second_control.y,
+                    pos.x,
+                    pos.y,
+                ),
+                PathCommand::Close => drawing.close_path(),
+            };
+
+            match command {
+                PathCommand::MoveTo(pos)
+                | PathCommand::LineTo(pos)
+                | PathCommand::QuadTo(_, pos)
+                | PathCommand::CubicTo(_, _, pos) => current_pos = pos,
+                PathCommand::Close => (),
+            }
+
+            has_outline = true;
+        }
+
+        if has_outline {
+            drawing.fill_nonzero();
+        }
+    }
+
+    let drawing = drawing.finish();
+
+    let page_transforms = pdf_options.page_transforms(area_size, options.scale);
+
+    let mut pdf = Pdf::new();
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let mut next_id = 3;
+
+    let mut allocate_id = || {
+        let id = Ref::new(next_id);
+        next_id += 1;
+        id
+    };
+
+    let alpha_states: BTreeMap<u8, Ref> = used_alphas
+        .into_iter()
+        .map(|alpha| (alpha, allocate_id()))
+        .collect();
+
+    let stamp_texture_ids: Vec<(Ref, Ref)> = stamp_textures
+        .iter()
+        .map(|_| (allocate_id(), allocate_id()))
+        .collect();
+
+    let embedded_image_ids: Vec<(Ref, Ref)> = embedded_images
+        .iter()
+        .map(|_| (allocate_id(), allocate_id()))
+        .collect();
+
+    let drawing_id = allocate_id();
+
+    let page_ids: Vec<(Ref, Ref)> = page_transforms
+        .iter()
+        .map(|_| (allocate_id(), allocate_id()))
+        .collect();
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().map(|(page_id, _)| *page_id))
+        .count(page_ids.len() as i32);
+
+    for (alpha, alpha_state_id) in &alpha_states {
+        let alpha = *alpha as f32 / 255.;
+
+        pdf.ext_graphics(*alpha_state_id)
+            .stroking_alpha(alpha)
+            .non_stroking_alpha(alpha);
+    }
+
+    for (stamp_texture, (image_id, mask_id)) in stamp_textures.iter().zip(&stamp_texture_ids) {
+        let color = stamp_texture
+            .color
+            .repeat((stamp_texture.width * stamp_texture.height) as usize);
+
+        let mut image = pdf.image_xobject(*image_id, &color);
+        image.width(stamp_texture.width as i32);
+        image.height(stamp_texture.height as i32);
+        image.color_space().device_rgb();
+        image.bits_per_component(8);
+        image.s_mask(*mask_id);
+        image.finish();
+
+        let mut mask = pdf.image_xobject(*mask_id, &stamp_texture.alpha);
+        mask.width(stamp_texture.width as i32);
+        mask.height(stamp_texture.height as i32);
+        mask.color_space().device_gray();
+        mask.bits_per_component(8);
+        mask.finish();
+    }
+
+    for (embedded_image, (image_id, mask_id)) in embedded_images.iter().zip(&embedded_image_ids) {
+        let mut image = pdf.image_xobject(*image_id, &embedded_image.rgb);
+        image.filter(Filter::FlateDecode);
+        image.width(embedded_image.width as i32);
+        image.height(embedded_image.height as i32);
+        image.color_space().device_rgb();
+        image.bits_per_component(8);
+        image.s_mask(*mask_id);
+        image.finish();
+
+        let mut mask = pdf.image_xobject(*mask_id, &embedded_image.alpha);
+        mask.filter(Filter::FlateDecode);
+        mask.width(embedded_image.width as i32);
+        mask.height(embedded_image.height as i32);
+        mask.color_space().device_gray();
+        mask.bits_per_component(8);
+        mask.finish();
+    }
+
+    let mut form = pdf.form_xobject(drawing_id, &drawing);
+    form.bbox(Rect::new(0., 0., area_size.x, area_size.y));
+
+    let mut resources = form.resources();
+    let mut ext_g_states = resources.ext_g_states();
+
+    for (alpha, alpha_state_id) in &alpha_states {
+        ext_g_states.pair(Name(alpha_state_name(*alpha).as_bytes()), *alpha_state_id);
+    }
+
+    ext_g_states.finish();
+
+    let mut x_objects = resources.x_objects();
+
+    for (idx, (image_id, _)) in stamp_texture_ids.iter().enumerate() {
+        x_objects.pair(Name(stamp_texture_name(idx).as_bytes()), *image_id);
+    }
+
+    for (idx, (image_id, _)) in embedded_image_ids.iter().enumerate() {
+        x_objects.pair(Name(image_name(idx).as_bytes()), *image_id);
+    }
+
+    x_objects.finish();
+    resources.finish();
+    form.finish();
+
+    let content_size = pdf_options.content_size();
+
+    for ((page_id, content_id), transform) in page_ids.iter().zip(page_transforms) {
+        let mut page = pdf.page(*page_id);
+
+        page.media_box(Rect::new(
+            0.,
+            0.,
+            pdf_options.page_size.x,
+            pdf_options.page_size.y,
+        ));
+        page.parent(page_tree_id);
+        page.contents(*content_id);
+        page.resources()
+            .x_objects()
+            .pair(Name(DRAWING_NAME), drawing_id);
+        page.finish();
+
+        let mut content = Content::new();
+
+        // Clip to the margins, so that tiled pages don't overlap
+        content.save_state();
+        content.rect(
+            pdf_options.margin,
+            pdf_options.margin,
+            content_size.x,
+            content_size.y,
+        );
+        content.clip_nonzero();
+        content.end_path();
+        content.transform(transform);
+        content.x_object(Name(DRAWING_NAME));
+        content.restore_state();
+
+        pdf.stream(*content_id, &content.finish());
+    }
+
+    Ok(pdf.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use common_definitions::{Brush, BrushType, LinePos};
+    use egui::Rect;
+
+    use super::*;
+    use crate::{RenderArea, DEFAULT_CANVAS_SCALE};
+
+    fn test_lines() -> Vec<(Vec<LinePos>, Brush)> {
+        vec![(
+            vec![Pos2::new(0.1, 0.1).into(), Pos2::new(0.9, 0.4).into()],
+            Brush {
+                width: 8.,
+                color: Color32::RED,
+                brush_type: BrushType::Marker,
+                ..Default::default()
+            },
+        )]
+    }
+
+    /// Returns the number of pages in the page tree of the pdf document, and the media boxes of the pages.
+    fn pages(pdf: &[u8]) -> (usize, Vec<String>) {
+        let pdf = String::from_utf8_lossy(pdf);
+
+        let count = pdf
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("/Count "))
+            .and_then(|count| count.parse().ok())
+            .unwrap();
+        let media_boxes = pdf
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("/MediaBox "))
+            .map(String::from)
+            .collect();
+
+        (count, media_boxes)
+    }
+
+    /// Renders the test lines on a canvas area which is ```DEFAULT_CANVAS_SCALE``` wide and half as high.
+    fn render(scale: f32, pdf_options: &PdfOptions) -> anyhow::Result<Vec<u8>> {
+        let lines = test_lines();
+        let content = CanvasContent {
+            lines: &lines,
+            ..Default::default()
+        };
+        let options = RenderOptions {
+            scale,
+            area: RenderArea::Canvas(Rect::from_min_max(Pos2::ZERO, Pos2::new(1., 0.5))),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pdf_page_count(&content, &options, pdf_options),
+            pdf_options.page_count(vec2(DEFAULT_CANVAS_SCALE, DEFAULT_CANVAS_SCALE / 2.), scale)
+        );
+
+        render_pdf(&content, &options, pdf_options)
+    }
+
+    #[test]
+    fn fit_uses_a_single_page() {
+        let pdf_options = PdfOptions::default();
+
+        for scale in [0.5, 1., 4.] {
+            let (count, media_boxes) = pages(&render(scale, &pdf_options).unwrap());
+
+            assert_eq!(count, 1);
+            assert_eq!(media_boxes, vec!["[0 0 595 842]"]);
+        }
+
+        // The drawing fills the width of the page without the margins
+        let area_size = vec2(DEFAULT_CANVAS_SCALE, DEFAULT_CANVAS_SCALE / 2.);
+        let [scale, ..] = pdf_options.page_transforms(area_size, 1.)[0];
+
+        assert!((area_size.x * scale - (595. - 72.)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn tile_splits_the_drawing_across_pages() {
+        let pdf_options = PdfOptions {
+            layout: PageLayout::Tile,
+            ..Default::default()
+        };
+
+        // The 1024 x 512 points large drawing is two pages wide and one page high
+        let (count, media_boxes) = pages(&render(1., &pdf_options).unwrap());
+
+        assert_eq!(count, 2);
+        assert_eq!(media_boxes, vec!["[0 0 595 842]"; 2]);
+
+        // At twice the scale it's four pages wide and two pages high
+        let (count, _) = pages(&render(2., &pdf_options).unwrap());
+
+        assert_eq!(count, 8);
+
+        let letter_options = PdfOptions {
+            page_size: LETTER_PAGE_SIZE,
+            ..pdf_options
+        };
+        let (count, media_boxes) = pages(&render(0.25, &letter_options).unwrap());
+
+        assert_eq!(count, 1);
+        assert_eq!(media_boxes, vec!["[0 0 612 792]"]);
+    }
+
+    #[test]
+    fn too_many_pages_are_refused() {
+        let pdf_options = PdfOptions {
+            layout: PageLayout::Tile,
+            ..Default::default()
+        };
+
+        assert!(render(100., &pdf_options).is_err());
+
+        // A single page is rendered at any scale
+        assert!(render(100., &PdfOptions::default()).is_ok());
+    }
+}
@@ -0,0 +1,459 @@
+use common_definitions::{BrushTexture, CanvasImage, CanvasShape};
+use egui::{Color32, Pos2, Rect};
+use tiny_skia::{
+    Color, ColorU8, FillRule, FilterQuality, LineCap, LineJoin, Paint, Path, PathBuilder, Pixmap,
+    PixmapPaint, Stroke, StrokeDash, Transform,
+};
+
+use crate::{
+    background_layout, decode_image, shape_outline,
+    text::{text_outline, PathCommand},
+    variable_width_outline, visible_lines, CanvasContent, CanvasFrame, RenderArea, RenderOptions,
+};
+
+/// Converts an ```egui::Color32``` into a ```tiny_skia::Color```.
+fn to_skia_color(color: Color32) -> Color {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+
+    Color::from_rgba8(r, g, b, a)
+}
+
+/// Decodes the ```BrushTexture``` and tints it with the color, keeping the texture's alpha.
+pub(crate) fn tinted_texture(texture: BrushTexture, color: Color32) -> anyhow::Result<Pixmap> {
+    let mut pixmap = Pixmap::decode_png(texture.png_bytes())?;
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+
+    for pixel in pixmap.pixels_mut() {
+        let alpha = (pixel.alpha() as u16 * a as u16 / 255) as u8;
+
+        *pixel = ColorU8::from_rgba(r, g, b, alpha).premultiply();
+    }
+
+    Ok(pixmap)
+}
+
+/// Returns the path going through the points, or ```None``` if all of the points are at the same position.
+fn polygon_path(points: &[Pos2], closed: bool) -> Option<Path> {
+    let mut path_builder = PathBuilder::new();
+
+    for (idx, pos) in points.iter().enumerate() {
+        if idx == 0 {
+            path_builder.move_to(pos.x, pos.y);
+        } else {
+            path_builder.line_to(pos.x, pos.y);
+        }
+    }
+
+    if closed {
+        path_builder.close();
+    }
+
+    path_builder.finish()
+}
+
+/// Draws the image stretched onto the ```rect``` of the rendered image.
+fn draw_image(pixmap: &mut Pixmap, image: &CanvasImage, rect: Rect) -> anyhow::Result<()> {
+    let decoded_image = decode_image(image)?;
+
+    let mut image_pixmap = Pixmap::new(decoded_image.width(), decoded_image.height())
+        .ok_or_else(|| anyhow::Error::msg("Invalid image size."))?;
+
+    for (pixel, [r, g, b, a]) in image_pixmap
+        .pixels_mut()
+        .iter_mut()
+        .zip(decoded_image.pixels().map(|pixel| pixel.0))
+    {
+        *pixel = ColorU8::from_rgba(r, g, b, a).premultiply();
+    }
+
+    pixmap.draw_pixmap(
+        0,
+        0,
+        image_pixmap.as_ref(),
+        &PixmapPaint {
+            quality: FilterQuality::Bilinear,
+            ..Default::default()
+        },
+        Transform::from_row(
+            rect.width() / image_pixmap.width() as f32,
+            0.,
+            0.,
+            rect.height() / image_pixmap.height() as f32,
+            rect.min.x,
+            rect.min.y,
+        ),
+        None,
+    );
+
+    Ok(())
+}
+
+/// Draws the canvas' background color, paper lines and image.
+fn draw_background(
+    pixmap: &mut Pixmap,
+    content: &CanvasContent,
+    frame: &CanvasFrame,
+) -> anyhow::Result<()> {
+    let background = background_layout(content, frame);
+
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Default::default()
+    };
+
+    if let (Some(fill), Some(rect)) = (
+        background.fill,
+        tiny_skia::Rect::from_ltrb(
+            background.area.min.x,
+            background.area.min.y,
+            background.area.max.x,
+            background.area.max.y,
+        ),
+    ) {
+        paint.set_color(to_skia_color(fill));
+
+        pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+    }
+
+    paint.set_color(to_skia_color(background.line_color));
+
+    for (endpoints, width) in background.lines {
+        if let Some(path) = polygon_path(&endpoints, false) {
+            let stroke = Stroke {
+                width,
+                line_cap: LineCap::Butt,
+                ..Default::default()
+            };
+
+            pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+        }
+    }
+
+    if let Some((image, rect)) = background.image {
+        draw_image(pixmap, image, rect)?;
+    }
+
+    Ok(())
+}
+
+/// Draws the shape's fill, outline and arrow head.
+fn draw_shape(pixmap: &mut Pixmap, shape: &CanvasShape, frame: &CanvasFrame) {
+    let outline = shape_outline(shape, frame);
+
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Default::default()
+    };
+
+    if let (Some(fill), Some(path)) = (shape.fill_color(), polygon_path(&outline.points, true)) {
+        paint.set_color(to_skia_color(fill));
+
+        pixmap.fill_path(
+            &path,
+            &paint,
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+
+    paint.set_color(to_skia_color(shape.stroke_color));
+
+    if let Some(path) = outline
+        .arrow_head
+        .and_then(|head| polygon_path(&head, true))
+    {
+        pixmap.fill_path(
+            &path,
+            &paint,
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+
+    if let Some(path) = polygon_path(&outline.points, outline.closed) {
+        let stroke = Stroke {
+            width: shape.stroke_width * frame.scale(),
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            ..Default::default()
+        };
+
+        pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    }
+}
+
+/// Renders the contents of the canvas into a ```Pixmap```.
+pub fn render_pixmap(content: &CanvasContent, options: &RenderOptions) -> anyhow::Result<Pixmap> {
+    let frame = CanvasFrame::new(content, options);
+    let size = frame.size();
+
+    let mut pixmap = Pixmap::new(size.x as u32, size.y as u32)
+        .ok_or_else(|| anyhow::Error::msg("Invalid rendered image size."))?;
+
+    if let Some(background) = options.background {
+        pixmap.fill(to_skia_color(background));
+    }
+
+    draw_background(&mut pixmap, content, &frame)?;
+
+    for image in content.images {
+        draw_image(&mut pixmap, image, frame.rect_to_output(image.rect))?;
+    }
+
+    for shape in content.shapes {
+        draw_shape(&mut pixmap, shape, &frame);
+    }
+
+    for (points, style) in visible_lines(content.lines) {
+        let mut paint = Paint::default();
+        paint.set_color(to_skia_color(style.color));
+        paint.anti_alias = true;
+
+        if let (Some(stamps), Some(settings)) = (style.stamps(points), style.stamp) {
+            let texture = tinted_texture(settings.texture, style.color)?;
+            let texture_size = texture.width().max(texture.height()) as f32;
+
+            for stamp in &stamps {
+                let (center, size) = frame.stamp_to_output(stamp);
+
+                let transform = Transform::from_translate(center.x, center.y)
+                    .pre_rotate(stamp.rotation.to_degrees())
+                    .pre_scale(size / texture_size, size / texture_size)
+                    .pre_translate(
+                        -(texture.width() as f32) / 2.,
+                        -(texture.height() as f32) / 2.,
+                    );
+
+                pixmap.draw_pixmap(
+                    0,
+                    0,
+                    texture.as_ref(),
+                    &PixmapPaint::default(),
+                    transform,
+                    None,
+                );
+            }
+
+            continue;
+        }
+
+        if let Some(particles) = style.particles(points) {
+            for particle in &particles {
+                let (center, radius) = frame.particle_to_output(particle);
+
+                if let Some(path) = PathBuilder::from_circle(center.x, center.y, radius) {
+                    pixmap.fill_path(
+                        &path,
+                        &paint,
+                        FillRule::Winding,
+                        Transform::identity(),
+                        None,
+                    );
+                }
+            }
+
+            continue;
+        }
+
+        if style.is_variable_width(points) {
+            let mut path_builder = PathBuilder::new();
+
+            for polygon in variable_width_outline(points, &style, &frame) {
+                for (idx, pos) in polygon.iter().enumerate() {
+                    if idx == 0 {
+                        path_builder.move_to(pos.x, pos.y);
+                    } else {
+                        path_builder.line_to(pos.x, pos.y);
+                    }
+                }
+
+                path_builder.close();
+            }
+
+            if let Some(path) = path_builder.finish() {
+                pixmap.fill_path(
+                    &path,
+                    &paint,
+                    FillRule::Winding,
+                    Transform::identity(),
+                    None,
+                );
+            }
+
+            continue;
+        }
+
+        let mut path_builder = PathBuilder::new();
+
+        for (idx, pos) in points.iter().enumerate() {
+            let output_pos = frame.to_output(*pos);
+
+            if idx == 0 {
+                path_builder.move_to(output_pos.x, output_pos.y);
+            } else {
+                path_builder.line_to(output_pos.x, output_pos.y);
+            }
+        }
+
+        // A path can't be created if all of its points are at the same position
+        let Some(path) = path_builder.finish() else {
+            continue;
+        };
+
+        let stroke = Stroke {
+            width: style.width * frame.scale(),
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Round,
+            dash: style.dash.and_then(|(dash_length, gap_length)| {
+                StrokeDash::new(
+                    vec![dash_length * frame.scale(), gap_length * frame.scale()],
+                    0.,
+                )
+            }),
+            ..Default::default()
+        };
+
+        pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    }
+
+    // Texts are drawn on the top of the lines, just like on the canvas
+    for text in content.texts {
+        let mut paint = Paint::default();
+        paint.set_color(to_skia_color(text.color));
+        paint.anti_alias = true;
+
+        let mut path_builder = PathBuilder::new();
+
+        for command in text_outline(text, &frame) {
+            match command {
+                PathCommand::MoveTo(pos) => path_builder.move_to(pos.x, pos.y),
+                PathCommand::LineTo(pos) => path_builder.line_to(pos.x, pos.y),
+                PathCommand::QuadTo(control, pos) => {
+                    path_builder.quad_to(control.x, control.y, pos.x, pos.y)
+                }
+                PathCommand::CubicTo(first_control, second_control, pos) => path_builder.cubic_to(
+                    first_control.x,
+                    first_control.y,
+                    second_control.x,
+                    second_control.y,
+                    pos.x,
+                    pos.y,
+                ),
+                PathCommand::Close => path_builder.close(),
+            }
+        }
+
+        // Texts containing only whitespace don't have an outline
+        if let Some(path) = path_builder.finish() {
+            pixmap.fill_path(
+                &path,
+                &paint,
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+    }
+
+    Ok(pixmap)
+}
+
+/// Renders the contents of the canvas into a png image, returning the encoded bytes of the image.
+pub fn render_png(content: &CanvasContent, options: &RenderOptions) -> anyhow::Result<Vec<u8>> {
+    Ok(render_pixmap(content, options)?.encode_png()?)
+}
+
+/// Renders the contents of the canvas into a png thumbnail, whose longer side is ```max_size``` pixels long.
+/// The thumbnail shows the canvas' page if it is bounded to one, otherwise the area covered by the drawing.
+pub fn render_thumbnail(content: &CanvasContent, max_size: u32) -> anyhow::Result<Vec<u8>> {
+    let mut options = RenderOptions {
+        area: content
+            .background
+            .page_rect()
+            .map_or(RenderArea::DrawingBounds, RenderArea::Canvas),
+        ..Default::default()
+    };
+
+    // Measure the drawing in points, so that it can be scaled to fit the thumbnail
+    let original_size = CanvasFrame::new(content, &options).area().size();
+
+    options.scale = max_size as f32 / original_size.max_elem();
+
+    render_png(content, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use common_definitions::{Brush, BrushType, LinePos};
+
+    use super::*;
+    use crate::DEFAULT_CANVAS_SCALE;
+
+    fn test_lines() -> Vec<(Vec<LinePos>, Brush)> {
+        vec![(
+            vec![Pos2::new(0.1, 0.1).into(), Pos2::new(0.4, 0.3).into()],
+            Brush {
+                width: 8.,
+                color: Color32::RED,
+                brush_type: BrushType::Marker,
+                ..Default::default()
+            },
+        )]
+    }
+
+    #[test]
+    fn lines_are_rendered() {
+        let lines = test_lines();
+        let pixmap = render_pixmap(
+            &CanvasContent {
+                lines: &lines,
+                ..Default::default()
+            },
+            &RenderOptions::default(),
+        )
+        .unwrap();
+
+        assert!(pixmap.pixels().iter().any(|pixel| pixel.alpha() > 0));
+    }
+
+    #[test]
+    fn size_matches_the_options() {
+        let lines = test_lines();
+        let pixmap = render_pixmap(
+            &CanvasContent {
+                lines: &lines,
+                ..Default::default()
+            },
+            &RenderOptions {
+                scale: 0.5,
+                area: RenderArea::Canvas(Rect::from_min_max(Pos2::ZERO, Pos2::new(1., 0.5))),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(pixmap.width() as f32, DEFAULT_CANVAS_SCALE * 0.5);
+        assert_eq!(pixmap.height() as f32, DEFAULT_CANVAS_SCALE * 0.25);
+    }
+
+    #[test]
+    fn size_is_capped() {
+        let lines = test_lines();
+        let pixmap = render_pixmap(
+            &CanvasContent {
+                lines: &lines,
+                ..Default::default()
+            },
+            &RenderOptions {
+                area: RenderArea::Canvas(Rect::from_min_max(Pos2::ZERO, Pos2::new(1., 1.))),
+                max_size: 64.,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!((pixmap.width(), pixmap.height()), (64, 64));
+    }
+}
@@ -0,0 +1,395 @@
+use std::{collections::HashMap, fmt::Write};
+
+use base64::Engine;
+use common_definitions::{BrushTexture, CanvasImage, CanvasShape};
+use egui::{Color32, Pos2, Rect};
+
+use crate::{
+    background_layout,
+    raster::tinted_texture,
+    shape_outline,
+    text::{text_outline, PathCommand},
+    variable_width_outline, visible_lines, CanvasContent, CanvasFrame, RenderOptions,
+};
+
+/// Converts an ```egui::Color32``` into an svg color and opacity pair.
+fn to_svg_color(color: Color32) -> (String, f32) {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+
+    (format!("rgb({r},{g},{b})"), a as f32 / 255.)
+}
+
+/// Joins the points into the value of a ```points``` attribute.
+fn svg_points(points: &[Pos2]) -> String {
+    points
+        .iter()
+        .map(|pos| format!("{},{}", pos.x, pos.y))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Writes the image stretched onto the ```rect``` of the rendered image, embedded as a data url.
+fn write_image(svg: &mut String, image: &CanvasImage, rect: Rect) {
+    // Images which can't be decoded are skipped, just like on the canvas
+    let Ok(format) = image::guess_format(&image.data) else {
+        return;
+    };
+
+    let _ = writeln!(
+        svg,
+        r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" href="data:{};base64,{}"/>"#,
+        rect.min.x,
+        rect.min.y,
+        rect.width(),
+        rect.height(),
+        format.to_mime_type(),
+        base64::engine::general_purpose::STANDARD.encode(&image.data)
+    );
+}
+
+/// Writes the canvas' background color as a ```rect```, its paper lines as a group of ```line```-s and its image as an ```image```.
+fn write_background(svg: &mut String, content: &CanvasContent, frame: &CanvasFrame) {
+    let background = background_layout(content, frame);
+
+    if let Some(fill) = background.fill {
+        let (color, opacity) = to_svg_color(fill);
+
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{color}" fill-opacity="{opacity}"/>"#,
+            background.area.min.x,
+            background.area.min.y,
+            background.area.width(),
+            background.area.height()
+        );
+    }
+
+    if !background.lines.is_empty() {
+        let (color, opacity) = to_svg_color(background.line_color);
+
+        let _ = writeln!(
+            svg,
+            r#"<g stroke="{color}" stroke-opacity="{opacity}" stroke-linecap="butt">"#
+        );
+
+        for ([start, end], width) in &background.lines {
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke-width="{width}"/>"#,
+                start.x, start.y, end.x, end.y
+            );
+        }
+
+        svg.push_str("</g>\n");
+    }
+
+    if let Some((image, rect)) = background.image {
+        write_image(svg, image, rect);
+    }
+}
+
+/// Writes the shape as a ```polygon``` or a ```polyline```, arrow heads are written as a separate filled ```polygon```.
+fn write_shape(svg: &mut String, shape: &CanvasShape, frame: &CanvasFrame) {
+    let outline = shape_outline(shape, frame);
+    let (stroke, stroke_opacity) = to_svg_color(shape.stroke_color);
+
+    let fill = match shape.fill_color() {
+        Some(fill) => {
+            let (fill, fill_opacity) = to_svg_color(fill);
+
+            format!(r#"fill="{fill}" fill-opacity="{fill_opacity}""#)
+        }
+        None => r#"fill="none""#.to_string(),
+    };
+
+    let _ = writeln!(
+        svg,
+        r#"<{} points="{}" {fill} stroke="{stroke}" stroke-opacity="{stroke_opacity}" stroke-width="{}" stroke-linecap="butt" stroke-linejoin="miter"/>"#,
+        if outline.closed {
+            "polygon"
+        } else {
+            "polyline"
+        },
+        svg_points(&outline.points),
+        shape.stroke_width * frame.scale()
+    );
+
+    if let Some(head) = outline.arrow_head {
+        let _ = writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{stroke}" fill-opacity="{stroke_opacity}"/>"#,
+            svg_points(&head)
+        );
+    }
+}
+
+/// Renders the contents of the canvas into an svg document.
+/// Images are embedded as data urls, and shapes are exported as a ```polygon``` or a ```polyline```.
+/// Every line is exported as a ```polyline``` (Variable width lines as a filled ```path```, sprayed lines as a group of ```circle```-s, stamped lines as embedded images), colors are exported with their alpha as ```stroke-opacity```.
+/// Texts are exported as the filled outlines of their glyphs, so that they look the same without the fonts installed.
+pub fn render_svg(content: &CanvasContent, options: &RenderOptions) -> String {
+    let frame = CanvasFrame::new(content, options);
+    let size = frame.size();
+
+    let mut svg = String::new();
+
+    svg.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+    // Writing into a `String` can't fail, so the results are ignored.
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        size.x, size.y
+    );
+
+    if let Some(background) = options.background {
+        let (color, opacity) = to_svg_color(background);
+
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{color}" fill-opacity="{opacity}"/>"#
+        );
+    }
+
+    write_background(&mut svg, content, &frame);
+
+    for image in content.images {
+        write_image(&mut svg, image, frame.rect_to_output(image.rect));
+    }
+
+    for shape in content.shapes {
+        write_shape(&mut svg, shape, &frame);
+    }
+
+    // The tinted textures are only embedded once, the stamps reference them
+    let mut stamp_textures: HashMap<(BrushTexture, Color32), (String, f32, f32)> = HashMap::new();
+
+    for (points, style) in visible_lines(content.lines) {
+        let (color, opacity) = to_svg_color(style.color);
+
+        if let (Some(stamps), Some(settings)) = (style.stamps(points), style.stamp) {
+            let key = (settings.texture, style.color);
+
+            if !stamp_textures.contains_key(&key) {
+                let Ok(texture) = tinted_texture(settings.texture, style.color) else {
+                    continue;
+                };
+                let Ok(png) = texture.encode_png() else {
+                    continue;
+                };
+
+                let id = format!("stamp{}", stamp_textures.len());
+                let (width, height) = (texture.width() as f32, texture.height() as f32);
+
+                let _ = writeln!(
+                    svg,
+                    r#"<defs><image id="{id}" width="{width}" height="{height}" href="data:image/png;base64,{}"/></defs>"#,
+                    base64::engine::general_purpose::STANDARD.encode(&png)
+                );
+
+                stamp_textures.insert(key, (id, width, height));
+            }
+
+            let (id, width, height) = &stamp_textures[&key];
+
+            for stamp in &stamps {
+                let (center, size) = frame.stamp_to_output(stamp);
+
+                let _ = writeln!(
+                    svg,
+                    r##"<use href="#{id}" transform="translate({} {}) rotate({}) scale({}) translate({} {})"/>"##,
+                    center.x,
+                    center.y,
+                    stamp.rotation.to_degrees(),
+                    size / width.max(*height),
+                    -width / 2.,
+                    -height / 2.
+                );
+            }
+
+            continue;
+        }
+
+        if let Some(particles) = style.particles(points) {
+            let _ = writeln!(svg, r#"<g fill="{color}" fill-opacity="{opacity}">"#);
+
+            for particle in &particles {
+                let (center, radius) = frame.particle_to_output(particle);
+
+                let _ = writeln!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="{radius}"/>"#,
+                    center.x, center.y
+                );
+            }
+
+            svg.push_str("</g>\n");
+
+            continue;
+        }
+
+        if style.is_variable_width(points) {
+            let path = variable_width_outline(points, &style, &frame)
+                .iter()
+                .map(|polygon| {
+                    let polygon = polygon
+                        .iter()
+                        .map(|pos| format!("{},{}", pos.x, pos.y))
+                        .collect::<Vec<String>>()
+                        .join(" L");
+
+                    format!("M{polygon} Z")
+                })
+                .collect::<Vec<String>>()
+                .join(" ");
+
+            let _ = writeln!(
+                svg,
+                r#"<path d="{path}" fill="{color}" fill-opacity="{opacity}" fill-rule="nonzero"/>"#
+            );
+
+            continue;
+        }
+
+        let points = points
+            .iter()
+            .map(|pos| {
+                let output_pos = frame.to_output(*pos);
+
+                format!("{},{}", output_pos.x, output_pos.y)
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        // `Shape::dashed_line` starts with a dash at the first point, and keeps the dash pattern going across the segments, just like `stroke-dasharray`
+        let dash = style
+            .dash
+            .map(|(dash_length, gap_length)| {
+                format!(
+                    r#" stroke-dasharray="{} {}""#,
+                    dash_length * frame.scale(),
+                    gap_length * frame.scale()
+                )
+            })
+            .unwrap_or_default();
+
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{points}" fill="none" stroke="{color}" stroke-opacity="{opacity}" stroke-width="{}" stroke-linecap="butt" stroke-linejoin="round"{dash}/>"#,
+            style.width * frame.scale()
+        );
+    }
+
+    for text in content.texts {
+        let (color, opacity) = to_svg_color(text.color);
+
+        let path = text_outline(text, &frame)
+            .iter()
+            .map(|command| match command {
+                PathCommand::MoveTo(pos) => format!("M{},{}", pos.x, pos.y),
+                PathCommand::LineTo(pos) => format!("L{},{}", pos.x, pos.y),
+                PathCommand::QuadTo(control, pos) => {
+                    format!("Q{},{} {},{}", control.x, control.y, pos.x, pos.y)
+                }
+                PathCommand::CubicTo(first_control, second_control, pos) => format!(
+                    "C{},{} {},{} {},{}",
+                    first_control.x,
+                    first_control.y,
+                    second_control.x,
+                    second_control.y,
+                    pos.x,
+                    pos.y
+                ),
+                PathCommand::Close => "Z".to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        if path.is_empty() {
+            continue;
+        }
+
+        let _ = writeln!(
+            svg,
+            r#"<path d="{path}" fill="{color}" fill-opacity="{opacity}" fill-rule="nonzero"/>"#
+        );
+    }
+
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use common_definitions::{Brush, BrushType, LinePos};
+
+    use super::*;
+
+    fn test_line(brush_type: BrushType, color: Color32) -> (Vec<LinePos>, Brush) {
+        (
+            vec![Pos2::new(0.1, 0.1).into(), Pos2::new(0.4, 0.3).into()],
+            Brush {
+                width: 4.,
+                color,
+                brush_type,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn render_line(brush_type: BrushType, color: Color32) -> String {
+        let lines = [test_line(brush_type, color)];
+
+        render_svg(
+            &CanvasContent {
+                lines: &lines,
+                ..Default::default()
+            },
+            &RenderOptions::default(),
+        )
+    }
+
+    #[test]
+    fn every_brush_type_has_its_element() {
+        for (brush_type, element) in [
+            (BrushType::Pencil, Some("<polyline")),
+            (BrushType::Marker, Some("<polyline")),
+            (BrushType::Graffiti, Some("<circle")),
+            (BrushType::Stamp, Some("<use")),
+            (BrushType::Shape, None),
+            (BrushType::Text, None),
+            (BrushType::Eraser, None),
+            (BrushType::None, None),
+        ] {
+            let svg = render_line(brush_type, Color32::RED);
+
+            match element {
+                Some(element) => assert!(svg.contains(element), "{brush_type:?}"),
+                None => assert!(
+                    ["<polyline", "<circle", "<use", "<path"]
+                        .iter()
+                        .all(|element| !svg.contains(element)),
+                    "{brush_type:?}"
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn alpha_is_written_as_stroke_opacity() {
+        let svg = render_line(
+            BrushType::Marker,
+            Color32::from_rgba_unmultiplied(255, 0, 0, 128),
+        );
+
+        assert!(svg.contains(r#"stroke="rgb(255,0,0)""#));
+        assert!(svg.contains(&format!(r#"stroke-opacity="{}""#, 128_f32 / 255.)));
+    }
+
+    #[test]
+    fn only_pencil_is_dashed() {
+        assert!(render_line(BrushType::Pencil, Color32::RED).contains("stroke-dasharray"));
+        assert!(!render_line(BrushType::Marker, Color32::RED).contains("stroke-dasharray"));
+    }
+}
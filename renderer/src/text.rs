@@ -0,0 +1,141 @@
+use ab_glyph::{Font, FontRef, OutlineCurve, PxScale, ScaleFont};
+use common_definitions::{CanvasText, TextFont};
+use egui::{vec2, Pos2, Vec2};
+
+use crate::CanvasFrame;
+
+/// A command of the path outlining a text, in the coordinate system of the rendered image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PathCommand {
+    MoveTo(Pos2),
+    LineTo(Pos2),
+    /// A quadratic bezier curve with the control point and the end point.
+    QuadTo(Pos2, Pos2),
+    /// A cubic bezier curve with the two control points and the end point.
+    CubicTo(Pos2, Pos2, Pos2),
+    Close,
+}
+
+/// Returns the font egui draws the ```TextFont``` with, so that the rendered text looks the same as on the canvas.
+fn font(text_font: TextFont) -> FontRef<'static> {
+    let data = match text_font {
+        TextFont::Proportional => epaint_default_fonts::UBUNTU_LIGHT,
+        TextFont::Monospace => epaint_default_fonts::HACK_REGULAR,
+    };
+
+    FontRef::try_from_slice(data).expect("The fonts bundled with egui are valid.")
+}
+
+/// Returns the size of the text in points.
+pub(crate) fn text_size(text: &CanvasText) -> Vec2 {
+    let font = font(text.font).into_scaled(PxScale::from(text.size));
+
+    let width = text
+        .text
+        .lines()
+        .map(|line| {
+            let mut previous_glyph = None;
+
+            line.chars()
+                .map(|character| {
+                    let glyph = font.glyph_id(character);
+                    let kern = previous_glyph.map_or(0., |previous| font.kern(previous, glyph));
+
+                    previous_glyph = Some(glyph);
+
+                    kern + font.h_advance(glyph)
+                })
+                .sum::<f32>()
+        })
+        .fold(0., f32::max);
+
+    let row_height = font.ascent() - font.descent() + font.line_gap();
+
+    vec2(width, text.text.lines().count().max(1) as f32 * row_height)
+}
+
+/// Returns the outline of the text's glyphs in the rendered image, the outline has to be filled with the nonzero rule.
+pub(crate) fn text_outline(text: &CanvasText, frame: &CanvasFrame) -> Vec<PathCommand> {
+    let font = font(text.font).into_scaled(PxScale::from(text.size * frame.scale()));
+    let scale_factor = font.scale_factor();
+
+    let origin = frame.to_output(text.position.into());
+    let row_height = font.ascent() - font.descent() + font.line_gap();
+
+    let mut commands = vec![];
+
+    for (row, line) in text.text.lines().enumerate() {
+        let baseline = origin.y + font.ascent() + row as f32 * row_height;
+
+        let mut x = origin.x;
+        let mut previous_glyph = None;
+
+        for character in line.chars() {
+            let glyph = font.glyph_id(character);
+
+            if let Some(previous) = previous_glyph {
+                x += font.kern(previous, glyph);
+            }
+
+            previous_glyph = Some(glyph);
+
+            // The glyphs' outlines are in unscaled font units, with the y axis pointing upwards
+            let to_output = |point: ab_glyph::Point| {
+                Pos2::new(
+                    x + point.x * scale_factor.horizontal,
+                    baseline - point.y * scale_factor.vertical,
+                )
+            };
+
+            if let Some(outline) = font.font.outline(glyph) {
+                let mut current_pos: Option<Pos2> = None;
+
+                for curve in outline.curves {
+                    let (start, command) = match curve {
+                        OutlineCurve::Line(start, end) => {
+                            (to_output(start), PathCommand::LineTo(to_output(end)))
+                        }
+                        OutlineCurve::Quad(start, control, end) => (
+                            to_output(start),
+                            PathCommand::QuadTo(to_output(control), to_output(end)),
+                        ),
+                        OutlineCurve::Cubic(start, first_control, second_control, end) => (
+                            to_output(start),
+                            PathCommand::CubicTo(
+                                to_output(first_control),
+                                to_output(second_control),
+                                to_output(end),
+                            ),
+                        ),
+                    };
+
+                    // A new contour starts whenever a curve doesn't continue the previous one
+                    if current_pos != Some(start) {
+                        if current_pos.is_some() {
+                            commands.push(PathCommand::Close);
+                        }
+
+                        commands.push(PathCommand::MoveTo(start));
+                    }
+
+                    current_pos = Some(match command {
+                        PathCommand::LineTo(end)
+                        | PathCommand::QuadTo(_, end)
+                        | PathCommand::CubicTo(_, _, end) => end,
+                        _ => start,
+                    });
+
+                    commands.push(command);
+                }
+
+                if current_pos.is_some() {
+                    commands.push(PathCommand::Close);
+                }
+            }
+
+            x += font.h_advance(glyph);
+        }
+    }
+
+    commands
+}
@@ -1,13 +1,36 @@
-use std::{net::SocketAddr, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    net::SocketAddr,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
 #[derive(Clone)]
 pub struct ServerState {
     pub client_list: Arc<DashMap<SocketAddr, Client>>,
     pub canvas: Arc<DashMap<Vec<LinePos>, Brush>>,
+    /// The images placed on the canvas, keyed by their ```Uuid```.
+    pub images: Arc<DashMap<Uuid, CanvasImage>>,
+    /// The shapes placed on the canvas, keyed by their ```Uuid```.
+    pub shapes: Arc<DashMap<Uuid, CanvasShape>>,
+    /// The texts placed on the canvas, keyed by their ```Uuid```.
+    pub texts: Arc<DashMap<Uuid, CanvasText>>,
+    /// The layers of the canvas, in the order they are drawn in.
+    pub layers: Arc<RwLock<Layers>>,
+    /// The background of the canvas, the background image is stored with the images.
+    pub background: Arc<RwLock<CanvasBackground>>,
 }
 
-use common_definitions::{Brush, CancellationToken, LinePos, Message, MessageType};
+use common_definitions::{
+    Brush, CancellationToken, CanvasBackground, CanvasImage, CanvasShape, CanvasText, ExportFormat,
+    ExportRequest, Layers, LinePos, Message, MessageType, BACKGROUND_IMAGE_ID, IMAGE_CHUNK_SIZE,
+    MAX_IMAGE_LENGTH, MAX_MESSAGE_LENGTH,
+};
 use dashmap::DashMap;
+use drawing_board_renderer::{
+    render_pdf, render_png, render_svg, render_thumbnail, CanvasContent, PdfOptions, RenderArea,
+    RenderOptions,
+};
 use quinn::{
     rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer},
     RecvStream, SendStream, ServerConfig,
@@ -15,7 +38,8 @@ use quinn::{
 use tokio::{
     io::AsyncReadExt,
     select,
-    sync::broadcast::{Receiver, Sender},
+    sync::broadcast::{error::RecvError, Receiver, Sender},
+    task::JoinHandle,
 };
 use tracing::{event, Level};
 use uuid::Uuid;
@@ -24,6 +48,17 @@ pub struct Client {
     pub uuid: String,
 }
 
+/// The number of messages the relay can hold for the clients which haven't received them yet.
+/// The chunks of the largest image fit in the relay with room to spare, as they are all sent at once.
+pub const RELAY_CAPACITY: usize = MAX_IMAGE_LENGTH / IMAGE_CHUNK_SIZE + 1024;
+
+/// The largest width or height (In pixels) of the images rendered for the clients.
+/// This is much lower than the renderer's limit, as any client can request an export.
+pub const MAX_EXPORT_SIZE: f32 = 4096.;
+
+/// The time a client has to wait after requesting an export before it can request another one.
+pub const EXPORT_COOLDOWN: Duration = Duration::from_secs(5);
+
 pub fn bytes_into_message(bytes: Vec<u8>) -> anyhow::Result<Message> {
     let username_buf = String::from_utf8(bytes)?;
 
@@ -37,6 +72,12 @@ pub async fn read_from_stream(recv_stream: &mut RecvStream) -> anyhow::Result<Ve
     // Fetch message length by getting the message's header
     let msg_length = recv_stream.read_u64().await?;
 
+    if msg_length > MAX_MESSAGE_LENGTH {
+        return Err(anyhow::Error::msg(format!(
+            "The message is too long ({msg_length} bytes, the limit is {MAX_MESSAGE_LENGTH} bytes)."
+        )));
+    }
+
     // Allocate the message's buffer
     let mut message_buffer: Vec<u8> = vec![0; msg_length as usize];
 
@@ -46,6 +87,95 @@ pub async fn read_from_stream(recv_stream: &mut RecvStream) -> anyhow::Result<Ve
     Ok(message_buffer)
 }
 
+/// Renders the canvas stored in the ```ServerState``` into the format requested by the ```ExportRequest```.
+/// Returns the bytes of the rendered file.
+pub fn export_canvas(
+    server_state: &ServerState,
+    export_request: &ExportRequest,
+) -> anyhow::Result<Vec<u8>> {
+    let lines: Vec<(Vec<LinePos>, Brush)> = server_state
+        .canvas
+        .iter()
+        .map(|line| (line.key().clone(), *line.value()))
+        .collect();
+
+    let shapes: Vec<CanvasShape> = server_state
+        .shapes
+        .iter()
+        .map(|shape| shape.value().clone())
+        .collect();
+
+    // Hidden layers aren't exported, and the lines and shapes are ordered by their layers
+    let (lines, shapes) = {
+        let layers = server_state
+            .layers
+            .read()
+            .map_err(|_| anyhow::Error::msg("The layers' lock is poisoned."))?;
+
+        (
+            layers.visible_lines(&lines),
+            layers.layered_shapes(&shapes).collect::<Vec<_>>(),
+        )
+    };
+
+    // The background image is stored with the images, but it isn't part of the drawing
+    let images: Vec<CanvasImage> = server_state
+        .images
+        .iter()
+        .filter(|image| *image.key() != BACKGROUND_IMAGE_ID)
+        .map(|image| image.value().clone())
+        .collect();
+
+    let texts: Vec<CanvasText> = server_state
+        .texts
+        .iter()
+        .map(|text| text.value().clone())
+        .collect();
+
+    let images: Vec<&CanvasImage> = images.iter().collect();
+
+    let background = *server_state
+        .background
+        .read()
+        .map_err(|_| anyhow::Error::msg("The background's lock is poisoned."))?;
+
+    let background_image: Option<CanvasImage> = server_state
+        .images
+        .get(&BACKGROUND_IMAGE_ID)
+        .map(|image| image.value().clone());
+
+    let content = CanvasContent {
+        background,
+        background_image: background_image.as_ref(),
+        images: &images,
+        shapes: &shapes,
+        lines: &lines,
+        texts: &texts,
+    };
+
+    // The canvas is rendered onto its background, and cropped to its page
+    let render_options = RenderOptions {
+        area: background
+            .page_rect()
+            .map_or(RenderArea::DrawingBounds, RenderArea::Canvas),
+        max_size: MAX_EXPORT_SIZE,
+        ..Default::default()
+    };
+
+    Ok(
+        match (export_request.format, export_request.thumbnail_size) {
+            (ExportFormat::Png, Some(max_size)) => {
+                render_thumbnail(&content, max_size.min(MAX_EXPORT_SIZE as u32))?
+            }
+            (ExportFormat::Png, None) => render_png(&content, &render_options)?,
+            (ExportFormat::Svg, _) => render_svg(&content, &render_options).into_bytes(),
+            (ExportFormat::Pdf, _) => {
+                render_pdf(&content, &render_options, &PdfOptions::default())?
+            }
+        },
+    )
+}
+
 /// Creates a custom ```(ServerConfig, CertificateDer<'static>)``` instance. The Certificate is insecure.
 pub fn configure_server() -> anyhow::Result<(ServerConfig, CertificateDer<'static>)> {
     let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
@@ -135,13 +265,26 @@ pub async fn listen_for_message(
                         // These messages can be sent to all the connected clients
                         MessageType::ClientList(_)
                         | MessageType::CursorPosition(_)
+                        | MessageType::Viewport(_)
+                        | MessageType::SummonToViewport(_)
                         | MessageType::Connecting(_)
                         | MessageType::Disconnecting => {
                             relay.send(message)?;
                         }
 
                         //These are sent to the Canvas writer to be backed up and to all of the clients.
-                        MessageType::ModifyLine(_) | MessageType::AddLine(_) => {
+                        MessageType::ModifyLine(_)
+                        | MessageType::AddLine(_)
+                        | MessageType::TransformLine(_)
+                        | MessageType::AddImage(_)
+                        | MessageType::ImageChunk(_)
+                        | MessageType::ModifyImage(_)
+                        | MessageType::AddShape(_)
+                        | MessageType::ModifyShape(_)
+                        | MessageType::AddText(_)
+                        | MessageType::ModifyText(_)
+                        | MessageType::ModifyLayers(_)
+                        | MessageType::ModifyBackground(_) => {
                             canvas_sender.send(message.msg_type.clone()).await?;
                             relay.send(message)?;
                         }
@@ -151,16 +294,16 @@ pub async fn listen_for_message(
                             client_exclusive_sender.send(MessageType::KeepAlive).await?;
                         },
 
-                        // When a `LineSync` is requested the server should exclusively reply to the client who requested the `Sync`
-                        MessageType::RequestSyncLine(_) => {
+                        // When a `LineSync` or an `Export` is requested the server should exclusively reply to the client who requested it
+                        MessageType::RequestSyncLine(_) | MessageType::RequestExport(_) => {
                             client_exclusive_sender
                                 .send(message.msg_type)
                                 .await
                                 ?;
                         }
 
-                        // This message can only be sent by the server. Client issue.
-                        MessageType::SyncLine(_) => {
+                        // These messages can only be sent by the server. Client issue.
+                        MessageType::SyncLine(_) | MessageType::Export(_) | MessageType::ExportFailed(_) => {
                             event!(Level::ERROR, "The client can't send this message");
                         }
                     }
@@ -209,6 +352,120 @@ pub fn spawn_client_sender(
     });
 }
 
+/// Sends the whole canvas to the client, this is requested by the clients when they connect.
+pub async fn send_full_sync(
+    send_stream: &mut SendStream,
+    server_state: &ServerState,
+) -> anyhow::Result<()> {
+    // The layers are sent first, so that the lines are drawn in the right order as they arrive
+    let layers = server_state
+        .layers
+        .read()
+        .map_err(|_| anyhow::Error::msg("The layers' lock is poisoned."))?
+        .clone();
+
+    send_stream
+        .write_all(
+            &Message {
+                uuid: Uuid::default(),
+                msg_type: MessageType::ModifyLayers(layers),
+            }
+            .into_sendable(),
+        )
+        .await?;
+
+    let background = *server_state
+        .background
+        .read()
+        .map_err(|_| anyhow::Error::msg("The background's lock is poisoned."))?;
+
+    send_stream
+        .write_all(
+            &Message {
+                uuid: Uuid::default(),
+                msg_type: MessageType::ModifyBackground(background),
+            }
+            .into_sendable(),
+        )
+        .await?;
+
+    send_stream
+        .write_all(
+            &Message {
+                uuid: Uuid::default(),
+                msg_type: MessageType::SyncLine(common_definitions::LineSyncType::Full(
+                    Vec::from_iter(
+                        server_state
+                            .canvas
+                            .iter()
+                            .map(|line| (line.key().clone(), *line.value())),
+                    ),
+                )),
+            }
+            .into_sendable(),
+        )
+        .await?;
+
+    // Images are sent in chunks after the lines
+    let images: Vec<(Uuid, CanvasImage)> = server_state
+        .images
+        .iter()
+        .map(|image| (*image.key(), image.value().clone()))
+        .collect();
+
+    for (id, image) in images {
+        for msg_type in image.to_messages(id) {
+            send_stream
+                .write_all(
+                    &Message {
+                        uuid: Uuid::default(),
+                        msg_type,
+                    }
+                    .into_sendable(),
+                )
+                .await?;
+        }
+    }
+
+    let shapes: Vec<(Uuid, CanvasShape)> = server_state
+        .shapes
+        .iter()
+        .map(|shape| (*shape.key(), shape.value().clone()))
+        .collect();
+
+    for shape in shapes {
+        send_stream
+            .write_all(
+                &Message {
+                    uuid: Uuid::default(),
+                    msg_type: MessageType::AddShape(shape),
+                }
+                .into_sendable(),
+            )
+            .await?;
+    }
+
+    let texts: Vec<(Uuid, CanvasText)> = server_state
+        .texts
+        .iter()
+        .map(|text| (*text.key(), text.value().clone()))
+        .collect();
+
+    for text in texts {
+        send_stream
+            .write_all(
+                &Message {
+                    uuid: Uuid::default(),
+                    msg_type: MessageType::AddText(text),
+                }
+                .into_sendable(),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
 /// Relays messages to the client.
 pub async fn relay_message(
     mut all_client_relay: Receiver<Message>,
@@ -218,16 +475,29 @@ pub async fn relay_message(
     client_shutdown_token: CancellationToken,
     client_address: SocketAddr,
 ) -> anyhow::Result<()> {
+    // The export being rendered for the client, exports are rendered on a blocking thread one at a time
+    let mut export_task: Option<JoinHandle<(ExportFormat, anyhow::Result<Vec<u8>>)>> = None;
+    let mut last_export_request: Option<Instant> = None;
+
     loop {
         select! {
             received_message = all_client_relay.recv() => {
                 event!(Level::INFO, "Received global client message from: {client_address}.");
 
-                let received_message = received_message?;
+                match received_message {
+                    Ok(received_message) => {
+                        send_stream
+                            .write_all(&received_message.into_sendable())
+                            .await?;
+                    }
+                    // The client missed some messages, so it gets the whole canvas again
+                    Err(RecvError::Lagged(missed_messages)) => {
+                        event!(Level::WARN, "{client_address} missed {missed_messages} messages, resyncing the canvas.");
 
-                send_stream
-                    .write_all(&received_message.into_sendable())
-                    .await?;
+                        send_full_sync(&mut send_stream, &server_state).await?;
+                    }
+                    Err(err) => return Err(err.into()),
+                }
             }
 
             exclusive_message = client_exclusive_reciver.recv() => {
@@ -251,22 +521,30 @@ pub async fn relay_message(
                                     .await?;
                             },
                             None => {
-                                send_stream
-                                    .write_all(&Message {
-                                        uuid: Uuid::default(),
-                                        msg_type: MessageType::SyncLine(common_definitions::LineSyncType::Full(Vec::from_iter(
-                                            server_state.canvas.iter().map(|line| {
-                                                (line.key().clone(), *line.value())
-                                             })
-                                        )
-                                            )),
-                                        }.into_sendable()
-                                    )
-                                    .await?;
+                                send_full_sync(&mut send_stream, &server_state).await?;
                             },
                         }
                     },
 
+                    MessageType::RequestExport(export_request) => {
+                        let is_cooling_down = last_export_request.is_some_and(|requested_at| requested_at.elapsed() < EXPORT_COOLDOWN);
+
+                        if export_task.is_some() || is_cooling_down {
+                            event!(Level::WARN, "{client_address} requested an export too soon, rejecting it.");
+
+                            send_stream
+                                .write_all(&Message {uuid: Uuid::default(), msg_type: MessageType::ExportFailed(String::from("An export was requested too soon after the previous one, try again later."))}.into_sendable())
+                                .await?;
+                        } else {
+                            let server_state = server_state.clone();
+
+                            last_export_request = Some(Instant::now());
+                            export_task = Some(tokio::task::spawn_blocking(move || {
+                                (export_request.format, export_canvas(&server_state, &export_request))
+                            }));
+                        }
+                    }
+
                     MessageType::KeepAlive => {
                         send_stream
                                     .write_all(&Message {uuid: Uuid::default(), msg_type: MessageType::KeepAlive}.into_sendable())
@@ -278,6 +556,35 @@ pub async fn relay_message(
                 }
             }
 
+            exported_canvas = async {
+                match export_task.as_mut() {
+                    Some(export_task) => export_task.await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                export_task = None;
+
+                // A failed export is reported to the client, instead of closing its connection
+                let msg_type = match exported_canvas {
+                    Ok((format, Ok(exported_canvas))) => MessageType::Export((format, exported_canvas)),
+                    Ok((_, Err(err))) => {
+                        event!(Level::ERROR, "Failed to export the canvas for {client_address}: {err}");
+
+                        MessageType::ExportFailed(err.to_string())
+                    }
+                    Err(err) => {
+                        event!(Level::ERROR, "The export of {client_address} panicked: {err}");
+
+                        MessageType::ExportFailed(String::from("The server failed to render the canvas."))
+                    }
+                };
+
+                send_stream
+                    .write_all(&Message {uuid: Uuid::default(), msg_type}.into_sendable())
+                    .await?;
+                event!(Level::INFO, "Sent exported canvas to: {client_address}.");
+            }
+
             _ = client_shutdown_token.cancelled() => break,
         }
         event!(Level::INFO, "Relayed message to: {client_address}.");
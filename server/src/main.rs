@@ -1,13 +1,15 @@
 use std::{
     net::{Ipv6Addr, SocketAddr, SocketAddrV6},
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
 
-use common_definitions::{CancellationToken, Message, MessageType};
+use common_definitions::{
+    CancellationToken, CanvasBackground, ImageReceiver, Layers, Message, MessageType,
+};
 use dashmap::DashMap;
 use drawing_board_server::{
     bytes_into_message, configure_server, read_from_stream, spawn_client_listener,
-    spawn_client_sender, Client, ServerState,
+    spawn_client_sender, Client, ServerState, RELAY_CAPACITY,
 };
 use quinn::{Endpoint, RecvStream, SendStream};
 use tokio::sync::{
@@ -44,7 +46,8 @@ async fn main() -> anyhow::Result<()> {
 
     // Create the relay channel pair
     // This is used to broadcast a message to all of the clients.
-    let (relay_sender, relay_reciver) = broadcast::channel::<Message>(100);
+    // The capacity has to be large enough for the chunks of the largest image, which are all sent at once.
+    let (relay_sender, relay_reciver) = broadcast::channel::<Message>(RELAY_CAPACITY);
 
     // This channel is used to send messages to the canvas writer, which writes information to the server's internal storage
     let (canvas_sender, mut canvas_receiver) = channel::<MessageType>(1000);
@@ -53,6 +56,11 @@ async fn main() -> anyhow::Result<()> {
     let server_state = ServerState {
         client_list: Arc::new(DashMap::new()),
         canvas: Arc::new(DashMap::new()),
+        images: Arc::new(DashMap::new()),
+        shapes: Arc::new(DashMap::new()),
+        texts: Arc::new(DashMap::new()),
+        layers: Arc::new(RwLock::new(Layers::default())),
+        background: Arc::new(RwLock::new(CanvasBackground::default())),
     };
 
     //Clone the client list's handle
@@ -62,6 +70,9 @@ async fn main() -> anyhow::Result<()> {
     let server_state_clone = server_state.clone();
 
     tokio::spawn(async move {
+        // The images whose chunks are still being received
+        let mut image_receiver = ImageReceiver::default();
+
         loop {
             if let Some(message) = canvas_receiver.recv().await {
                 match message {
@@ -88,6 +99,92 @@ async fn main() -> anyhow::Result<()> {
                             }
                         }
                     }
+                    MessageType::TransformLine((pos, transformed_pos)) => {
+                        match server_state.canvas.remove(&pos) {
+                            Some((_, props)) => {
+                                server_state.canvas.insert(transformed_pos, props);
+                            }
+                            None => {
+                                event!(Level::ERROR, "Client/Server desync");
+                            }
+                        }
+                    }
+                    MessageType::AddImage((id, header)) => {
+                        if let Err(err) = image_receiver.start(id, header) {
+                            event!(Level::ERROR, "Refused to receive an image: {err}");
+                        }
+                    }
+                    MessageType::ImageChunk((id, offset, chunk)) => {
+                        if let Some(image) = image_receiver.receive_chunk(id, offset, &chunk) {
+                            server_state.images.insert(id, image);
+                        }
+                    }
+                    MessageType::ModifyImage((id, rect)) => match rect {
+                        // The image gets moved or resized
+                        Some(rect) => {
+                            if let Some(mut image) = server_state.images.get_mut(&id) {
+                                image.rect = rect;
+                            } else {
+                                event!(Level::ERROR, "Client/Server desync");
+                            }
+                        }
+                        // The image gets deleted
+                        None => {
+                            server_state.images.remove(&id);
+                        }
+                    },
+                    MessageType::AddShape((id, shape)) => {
+                        server_state.shapes.insert(id, shape);
+                    }
+                    MessageType::ModifyShape((id, shape)) => match shape {
+                        // The shape gets changed
+                        Some(shape) => {
+                            if let Some(mut old_shape) = server_state.shapes.get_mut(&id) {
+                                *old_shape = shape;
+                            } else {
+                                event!(Level::ERROR, "Client/Server desync");
+                            }
+                        }
+                        // The shape gets deleted
+                        None => {
+                            server_state.shapes.remove(&id);
+                        }
+                    },
+                    MessageType::ModifyLayers(layers) => match server_state.layers.write() {
+                        Ok(mut server_layers) => {
+                            *server_layers = layers;
+                        }
+                        Err(_) => {
+                            event!(Level::ERROR, "The layers' lock is poisoned");
+                        }
+                    },
+                    MessageType::ModifyBackground(background) => {
+                        match server_state.background.write() {
+                            Ok(mut server_background) => {
+                                *server_background = background;
+                            }
+                            Err(_) => {
+                                event!(Level::ERROR, "The background's lock is poisoned");
+                            }
+                        }
+                    }
+                    MessageType::AddText((id, text)) => {
+                        server_state.texts.insert(id, text);
+                    }
+                    MessageType::ModifyText((id, text)) => match text {
+                        // The text gets edited or moved
+                        Some(text) => {
+                            if let Some(mut old_text) = server_state.texts.get_mut(&id) {
+                                *old_text = text;
+                            } else {
+                                event!(Level::ERROR, "Client/Server desync");
+                            }
+                        }
+                        // The text gets deleted
+                        None => {
+                            server_state.texts.remove(&id);
+                        }
+                    },
 
                     _ => unreachable!(),
                 }